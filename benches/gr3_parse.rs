@@ -0,0 +1,57 @@
+//! Sequential versus parallel parsing of a large synthetic gr3 file.
+//!
+//! Run with `cargo bench --bench gr3_parse`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use schismrs_hgrid::gr3::{parse_from_bytes_parallel, parse_from_reader};
+use std::fmt::Write;
+use std::io::BufReader;
+
+/// An `n` x `n` node square split into triangles, with one open and one
+/// land boundary.
+fn synthetic_gr3(n: u32) -> String {
+    let mut text = String::new();
+    writeln!(text, "synthetic EPSG:4326").unwrap();
+    writeln!(text, "{} {}", 2 * (n - 1) * (n - 1), n * n).unwrap();
+    for j in 0..n {
+        for i in 0..n {
+            let depth = (i + j) as f64 * 0.5;
+            writeln!(text, "{} {} {} {:.3}", j * n + i + 1, i, j, depth).unwrap();
+        }
+    }
+    let mut id = 1;
+    for j in 0..n - 1 {
+        for i in 0..n - 1 {
+            let a = j * n + i + 1;
+            writeln!(text, "{} 3 {} {} {}", id, a, a + 1, a + n + 1).unwrap();
+            writeln!(text, "{} 3 {} {} {}", id + 1, a, a + n + 1, a + n).unwrap();
+            id += 2;
+        }
+    }
+    writeln!(text, "1 ! open\n{} ! nodes\n{} ! open 1", n, n).unwrap();
+    for i in 0..n {
+        writeln!(text, "{}", i + 1).unwrap();
+    }
+    writeln!(text, "1 ! land\n{} ! nodes\n{} 0 ! land 1", n, n).unwrap();
+    for j in 0..n {
+        writeln!(text, "{}", j * n + n).unwrap();
+    }
+    text
+}
+
+fn bench_gr3_parse(c: &mut Criterion) {
+    let text = synthetic_gr3(500);
+    let mut group = c.benchmark_group("gr3_parse");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("sequential", |b| {
+        b.iter(|| parse_from_reader(BufReader::new(text.as_bytes()), "bench").unwrap())
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| parse_from_bytes_parallel(text.as_bytes(), "bench").unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_gr3_parse);
+criterion_main!(benches);
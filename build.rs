@@ -13,7 +13,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         // Get current workspace HEAD (what the workspace is at)
         let workspace_hash = std::process::Command::new("git")
-            .args(&["rev-parse", "HEAD"])
+            .args(["rev-parse", "HEAD"])
             .output()
             .map(|output| {
                 let hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
@@ -27,7 +27,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         // Check if workspace (excluding current directory) is dirty
         let workspace_dirty = std::process::Command::new("git")
-            .args(&["diff", "--quiet", ":(exclude)."])
+            .args(["diff", "--quiet", ":(exclude)."])
             .status()
             .map(|status| if status.success() { "" } else { "-dirty" })
             .unwrap_or("");
@@ -45,7 +45,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         // Check if local directory is dirty
         let local_dirty = std::process::Command::new("git")
-            .args(&["diff", "--quiet", "."])
+            .args(["diff", "--quiet", "."])
             .status()
             .map(|status| if status.success() { "" } else { "-dirty" })
             .unwrap_or("");
@@ -57,7 +57,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     } else {
         // Standalone mode - just local hash and dirty state
         let local_hash = std::process::Command::new("git")
-            .args(&["rev-parse", "HEAD"])
+            .args(["rev-parse", "HEAD"])
             .output()
             .map(|output| {
                 let hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
@@ -70,7 +70,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .unwrap_or_else(|_| "unknown".to_string());
 
         let local_dirty = std::process::Command::new("git")
-            .args(&["diff", "--quiet"])
+            .args(["diff", "--quiet"])
             .status()
             .map(|status| if status.success() { "" } else { "-dirty" })
             .unwrap_or("");
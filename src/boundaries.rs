@@ -1,6 +1,9 @@
 use super::nodes::Nodes;
 use derive_builder::Builder;
 use linked_hash_map::LinkedHashMap;
+#[cfg(feature = "serde")]
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Open boundary segments for an unstructured mesh.
@@ -43,6 +46,12 @@ impl<'a> Iterator for OpenBoundariesIter<'a> {
     }
 }
 
+/// IBTYPE flag written after the node count of a land boundary segment.
+pub const LAND_IBTYPE: u8 = 0;
+
+/// IBTYPE flag written after the node count of an island boundary segment.
+pub const ISLAND_IBTYPE: u8 = 1;
+
 /// Land boundary segments for an unstructured mesh.
 /// Validation is performed separately via `Hgrid::check_validity()`.
 #[derive(Builder, Debug, Clone)]
@@ -50,27 +59,231 @@ impl<'a> Iterator for OpenBoundariesIter<'a> {
 pub struct LandBoundaries {
     nodes: Arc<Nodes>,
     nodes_ids: Vec<Vec<u32>>,
+    /// IBTYPE flag of each segment, as read from the gr3 file.
+    /// Segments without an entry are treated as `LAND_IBTYPE`.
+    #[builder(default)]
+    ibtypes: Vec<u8>,
 }
 
 impl LandBoundaries {
     pub fn nodes_ids(&self) -> &Vec<Vec<u32>> {
         &self.nodes_ids
     }
+
+    /// IBTYPE flag of every segment, in the same order as `nodes_ids()`.
+    pub fn ibtypes(&self) -> Vec<u8> {
+        (0..self.nodes_ids.len()).map(|i| self.ibtype(i)).collect()
+    }
+
+    /// IBTYPE flag of the segment at `index`.
+    pub fn ibtype(&self, index: usize) -> u8 {
+        self.ibtypes.get(index).copied().unwrap_or(LAND_IBTYPE)
+    }
 }
 
-/// Interior boundary segments for an unstructured mesh.
+/// Interior (island) boundary segments for an unstructured mesh.
 /// Validation is performed separately via `Hgrid::check_validity()`.
 #[derive(Builder, Debug, Clone)]
 #[allow(dead_code)]
 pub struct InteriorBoundaries {
     nodes: Arc<Nodes>,
     nodes_ids: Vec<Vec<u32>>,
+    /// IBTYPE flag of each segment, as read from the gr3 file.
+    /// Segments without an entry are treated as `ISLAND_IBTYPE`.
+    #[builder(default)]
+    ibtypes: Vec<u8>,
 }
 
 impl InteriorBoundaries {
     pub fn nodes_ids(&self) -> &Vec<Vec<u32>> {
         &self.nodes_ids
     }
+
+    /// IBTYPE flag of every segment, in the same order as `nodes_ids()`.
+    pub fn ibtypes(&self) -> Vec<u8> {
+        (0..self.nodes_ids.len()).map(|i| self.ibtype(i)).collect()
+    }
+
+    /// IBTYPE flag of the segment at `index`.
+    pub fn ibtype(&self, index: usize) -> u8 {
+        self.ibtypes.get(index).copied().unwrap_or(ISLAND_IBTYPE)
+    }
+}
+
+/// IBTYPE flag used for flow boundary segments that do not state their own.
+pub const FLOW_IBTYPE: u8 = 2;
+
+/// Specified normal flow boundary segments (ADCIRC IBTYPE 2, 12, 22, 30, 32, 52, ...).
+/// Validation is performed separately via `Hgrid::check_validity()`.
+#[derive(Builder, Debug, Clone)]
+#[allow(dead_code)]
+pub struct FlowBoundaries {
+    nodes: Arc<Nodes>,
+    nodes_ids: Vec<Vec<u32>>,
+    /// IBTYPE flag of each segment, as read from the fort.14 file.
+    /// Segments without an entry are treated as `FLOW_IBTYPE`.
+    #[builder(default)]
+    ibtypes: Vec<u8>,
+}
+
+impl FlowBoundaries {
+    pub fn nodes_ids(&self) -> &Vec<Vec<u32>> {
+        &self.nodes_ids
+    }
+
+    /// IBTYPE flag of every segment, in the same order as `nodes_ids()`.
+    pub fn ibtypes(&self) -> Vec<u8> {
+        (0..self.nodes_ids.len()).map(|i| self.ibtype(i)).collect()
+    }
+
+    /// IBTYPE flag of the segment at `index`.
+    pub fn ibtype(&self, index: usize) -> u8 {
+        self.ibtypes.get(index).copied().unwrap_or(FLOW_IBTYPE)
+    }
+}
+
+/// Per-node attributes of an external barrier (ADCIRC BARLANHT, BARLANCFSP).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BarrierAttributes {
+    /// Barrier crest height
+    pub height: f64,
+    /// Coefficient of free surface supercritical flow
+    pub supercritical_coefficient: f64,
+}
+
+/// Per-node attributes of an internal barrier, i.e. a weir pair
+/// (ADCIRC BARINHT, BARINCFSB, BARINCFSP).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WeirAttributes {
+    /// Weir crest height
+    pub height: f64,
+    /// Coefficient of free surface subcritical flow
+    pub subcritical_coefficient: f64,
+    /// Coefficient of free surface supercritical flow
+    pub supercritical_coefficient: f64,
+}
+
+/// Per-node attributes of a culvert: a weir pair with a cross-barrier pipe
+/// (ADCIRC BARINHT, BARINCFSB, BARINCFSP, PIPEHT, PIPECOEF, PIPEDIAM).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CulvertAttributes {
+    pub weir: WeirAttributes,
+    /// Pipe centerline height
+    pub pipe_height: f64,
+    /// Bulk coefficient for pipe flow
+    pub pipe_coefficient: f64,
+    /// Pipe diameter
+    pub pipe_diameter: f64,
+}
+
+/// One external barrier segment (ADCIRC IBTYPE 3, 13 or 23).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExternalBarrierSegment {
+    pub ibtype: u8,
+    pub nodes_ids: Vec<u32>,
+    /// One entry per node in `nodes_ids`
+    pub attributes: Vec<BarrierAttributes>,
+}
+
+/// One internal barrier segment (ADCIRC IBTYPE 4, 24 or 64).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InternalBarrierSegment {
+    pub ibtype: u8,
+    /// Front face nodes (NBVV)
+    pub nodes_ids: Vec<u32>,
+    /// Back face node paired with each front face node (IBCONN)
+    pub paired_nodes_ids: Vec<u32>,
+    /// One entry per node pair
+    pub attributes: Vec<WeirAttributes>,
+}
+
+/// One culvert segment (ADCIRC IBTYPE 5 or 25).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CulvertSegment {
+    pub ibtype: u8,
+    /// Front face nodes (NBVV)
+    pub nodes_ids: Vec<u32>,
+    /// Back face node paired with each front face node (IBCONN)
+    pub paired_nodes_ids: Vec<u32>,
+    /// One entry per node pair
+    pub attributes: Vec<CulvertAttributes>,
+}
+
+/// External barrier segments for an unstructured mesh.
+/// Validation is performed separately via `Hgrid::check_validity()`.
+#[derive(Builder, Debug, Clone)]
+#[allow(dead_code)]
+pub struct ExternalBarrierBoundaries {
+    nodes: Arc<Nodes>,
+    segments: Vec<ExternalBarrierSegment>,
+}
+
+impl ExternalBarrierBoundaries {
+    pub fn segments(&self) -> &Vec<ExternalBarrierSegment> {
+        &self.segments
+    }
+
+    pub fn nodes_ids(&self) -> Vec<Vec<u32>> {
+        self.segments.iter().map(|s| s.nodes_ids.clone()).collect()
+    }
+}
+
+/// Internal barrier (weir pair) segments for an unstructured mesh.
+/// Validation is performed separately via `Hgrid::check_validity()`.
+#[derive(Builder, Debug, Clone)]
+#[allow(dead_code)]
+pub struct InternalBarrierBoundaries {
+    nodes: Arc<Nodes>,
+    segments: Vec<InternalBarrierSegment>,
+}
+
+impl InternalBarrierBoundaries {
+    pub fn segments(&self) -> &Vec<InternalBarrierSegment> {
+        &self.segments
+    }
+
+    pub fn nodes_ids(&self) -> Vec<Vec<u32>> {
+        self.segments.iter().map(|s| s.nodes_ids.clone()).collect()
+    }
+
+    pub fn paired_nodes_ids(&self) -> Vec<Vec<u32>> {
+        self.segments
+            .iter()
+            .map(|s| s.paired_nodes_ids.clone())
+            .collect()
+    }
+}
+
+/// Culvert segments for an unstructured mesh.
+/// Validation is performed separately via `Hgrid::check_validity()`.
+#[derive(Builder, Debug, Clone)]
+#[allow(dead_code)]
+pub struct CulvertBoundaries {
+    nodes: Arc<Nodes>,
+    segments: Vec<CulvertSegment>,
+}
+
+impl CulvertBoundaries {
+    pub fn segments(&self) -> &Vec<CulvertSegment> {
+        &self.segments
+    }
+
+    pub fn nodes_ids(&self) -> Vec<Vec<u32>> {
+        self.segments.iter().map(|s| s.nodes_ids.clone()).collect()
+    }
+
+    pub fn paired_nodes_ids(&self) -> Vec<Vec<u32>> {
+        self.segments
+            .iter()
+            .map(|s| s.paired_nodes_ids.clone())
+            .collect()
+    }
 }
 
 #[derive(Builder, Debug, Clone)]
@@ -82,6 +295,18 @@ pub struct Boundaries {
     land: Option<LandBoundaries>,
     #[builder(default)]
     interior: Option<InteriorBoundaries>,
+    #[builder(default)]
+    flow: Option<FlowBoundaries>,
+    #[builder(default)]
+    external_barrier: Option<ExternalBarrierBoundaries>,
+    #[builder(default)]
+    internal_barrier: Option<InternalBarrierBoundaries>,
+    #[builder(default)]
+    culvert: Option<CulvertBoundaries>,
+    /// Kind of each non-ocean segment, in file order.
+    /// Without it, segments are written grouped by kind.
+    #[builder(default)]
+    non_ocean_order: Option<Vec<BoundaryType>>,
 }
 
 impl Boundaries {
@@ -100,16 +325,421 @@ impl Boundaries {
             map.insert(BoundaryType::Interior, interior_boundary.nodes_ids());
         }
 
+        if let Some(ref flow_boundary) = self.flow {
+            map.insert(BoundaryType::Flow, flow_boundary.nodes_ids());
+        }
+
         map
     }
     pub fn open(&self) -> Option<&OpenBoundaries> {
         self.open.as_ref()
     }
+
+    pub fn land(&self) -> Option<&LandBoundaries> {
+        self.land.as_ref()
+    }
+
+    pub fn interior(&self) -> Option<&InteriorBoundaries> {
+        self.interior.as_ref()
+    }
+
+    pub fn flow(&self) -> Option<&FlowBoundaries> {
+        self.flow.as_ref()
+    }
+
+    pub fn external_barrier(&self) -> Option<&ExternalBarrierBoundaries> {
+        self.external_barrier.as_ref()
+    }
+
+    pub fn internal_barrier(&self) -> Option<&InternalBarrierBoundaries> {
+        self.internal_barrier.as_ref()
+    }
+
+    pub fn culvert(&self) -> Option<&CulvertBoundaries> {
+        self.culvert.as_ref()
+    }
+
+    /// Kind of each non-ocean segment, in the order they were read.
+    pub fn non_ocean_order(&self) -> Option<&[BoundaryType]> {
+        self.non_ocean_order.as_deref()
+    }
+
+    /// Returns a copy of these boundaries referencing `nodes` instead.
+    pub(crate) fn with_nodes(&self, nodes: Arc<Nodes>) -> Boundaries {
+        Boundaries {
+            open: self.open.as_ref().map(|open| OpenBoundaries {
+                nodes: nodes.clone(),
+                nodes_ids: open.nodes_ids.clone(),
+            }),
+            land: self.land.as_ref().map(|land| LandBoundaries {
+                nodes: nodes.clone(),
+                nodes_ids: land.nodes_ids.clone(),
+                ibtypes: land.ibtypes.clone(),
+            }),
+            interior: self.interior.as_ref().map(|interior| InteriorBoundaries {
+                nodes: nodes.clone(),
+                nodes_ids: interior.nodes_ids.clone(),
+                ibtypes: interior.ibtypes.clone(),
+            }),
+            flow: self.flow.as_ref().map(|flow| FlowBoundaries {
+                nodes: nodes.clone(),
+                nodes_ids: flow.nodes_ids.clone(),
+                ibtypes: flow.ibtypes.clone(),
+            }),
+            external_barrier: self.external_barrier.as_ref().map(|external| {
+                ExternalBarrierBoundaries {
+                    nodes: nodes.clone(),
+                    segments: external.segments.clone(),
+                }
+            }),
+            internal_barrier: self.internal_barrier.as_ref().map(|internal| {
+                InternalBarrierBoundaries {
+                    nodes: nodes.clone(),
+                    segments: internal.segments.clone(),
+                }
+            }),
+            culvert: self.culvert.as_ref().map(|culvert| CulvertBoundaries {
+                nodes: nodes.clone(),
+                segments: culvert.segments.clone(),
+            }),
+            non_ocean_order: self.non_ocean_order.clone(),
+        }
+    }
+
+    /// Drops the segments for which `keep(kind, nodes_ids, paired_nodes_ids)`
+    /// is false, together with their IBTYPE flag and place in the segment
+    /// order.
+    fn retain_segments(&mut self, keep: impl Fn(BoundaryType, &[u32], &[u32]) -> bool) {
+        let flags = |kind: BoundaryType, segments: &[Vec<u32>]| -> Vec<bool> {
+            segments
+                .iter()
+                .map(|segment| keep(kind, segment, &[]))
+                .collect()
+        };
+        let mut kept = HashMap::new();
+        if let Some(open) = self.open.as_mut() {
+            open.nodes_ids
+                .retain(|segment| keep(BoundaryType::Open, segment, &[]));
+        }
+        if let Some(land) = self.land.as_mut() {
+            let flags = flags(BoundaryType::Land, &land.nodes_ids);
+            land.ibtypes = land.ibtypes();
+            retain_flagged(&mut land.nodes_ids, &flags);
+            retain_flagged(&mut land.ibtypes, &flags);
+            kept.insert(BoundaryType::Land, flags);
+        }
+        if let Some(interior) = self.interior.as_mut() {
+            let flags = flags(BoundaryType::Interior, &interior.nodes_ids);
+            interior.ibtypes = interior.ibtypes();
+            retain_flagged(&mut interior.nodes_ids, &flags);
+            retain_flagged(&mut interior.ibtypes, &flags);
+            kept.insert(BoundaryType::Interior, flags);
+        }
+        if let Some(flow) = self.flow.as_mut() {
+            let flags = flags(BoundaryType::Flow, &flow.nodes_ids);
+            flow.ibtypes = flow.ibtypes();
+            retain_flagged(&mut flow.nodes_ids, &flags);
+            retain_flagged(&mut flow.ibtypes, &flags);
+            kept.insert(BoundaryType::Flow, flags);
+        }
+        if let Some(external) = self.external_barrier.as_mut() {
+            let flags: Vec<bool> = external
+                .segments
+                .iter()
+                .map(|segment| keep(BoundaryType::ExternalBarrier, &segment.nodes_ids, &[]))
+                .collect();
+            retain_flagged(&mut external.segments, &flags);
+            kept.insert(BoundaryType::ExternalBarrier, flags);
+        }
+        if let Some(internal) = self.internal_barrier.as_mut() {
+            let flags: Vec<bool> = internal
+                .segments
+                .iter()
+                .map(|segment| {
+                    let (front, back) = (&segment.nodes_ids, &segment.paired_nodes_ids);
+                    keep(BoundaryType::InternalBarrier, front, back)
+                })
+                .collect();
+            retain_flagged(&mut internal.segments, &flags);
+            kept.insert(BoundaryType::InternalBarrier, flags);
+        }
+        if let Some(culvert) = self.culvert.as_mut() {
+            let flags: Vec<bool> = culvert
+                .segments
+                .iter()
+                .map(|segment| {
+                    let (front, back) = (&segment.nodes_ids, &segment.paired_nodes_ids);
+                    keep(BoundaryType::Culvert, front, back)
+                })
+                .collect();
+            retain_flagged(&mut culvert.segments, &flags);
+            kept.insert(BoundaryType::Culvert, flags);
+        }
+        if let Some(order) = self.non_ocean_order.as_mut() {
+            let mut next: HashMap<BoundaryType, usize> = HashMap::new();
+            order.retain(|kind| {
+                let index = next.entry(*kind).or_default();
+                *index += 1;
+                kept.get(kind).and_then(|flags| flags.get(*index - 1)) == Some(&true)
+            });
+        }
+    }
+
+    /// Returns a copy of these boundaries referencing `nodes` instead, keeping
+    /// only the segments whose nodes are all in `nodes`.
+    pub(crate) fn restricted_to(&self, nodes: Arc<Nodes>) -> Boundaries {
+        let kept = |segment: &[u32]| segment.iter().all(|&id| nodes.index_of(id).is_some());
+        let mut boundaries = self.with_nodes(nodes.clone());
+        boundaries.retain_segments(|_kind, segment, paired| kept(segment) && kept(paired));
+        boundaries
+    }
+
+    /// Returns a copy of these boundaries referencing `nodes` instead, with
+    /// node ids replaced through `node_map`.
+    ///
+    /// Nodes that become equal to the one before them are dropped, barrier
+    /// nodes together with their pair and attributes. Open, land, interior
+    /// and flow segments left with fewer than two nodes are dropped, barrier
+    /// and culvert segments only once they have no nodes left.
+    pub(crate) fn remapped(&self, node_map: &HashMap<u32, u32>, nodes: Arc<Nodes>) -> Boundaries {
+        let map = |id: &u32| node_map.get(id).copied().unwrap_or(*id);
+        let remap = |segment: &Vec<u32>| {
+            let mut segment: Vec<u32> = segment.iter().map(map).collect();
+            segment.dedup();
+            segment
+        };
+        // Positions of the nodes kept by dedup, compared on `key`
+        let kept = |len: usize, key: &dyn Fn(usize) -> (u32, u32)| {
+            (0..len)
+                .filter(|&i| i == 0 || key(i) != key(i - 1))
+                .collect::<Vec<usize>>()
+        };
+        let pick = |positions: &[usize], values: &[u32]| {
+            positions
+                .iter()
+                .map(|&i| map(&values[i]))
+                .collect::<Vec<u32>>()
+        };
+        let mut boundaries = self.with_nodes(nodes);
+        for nodes_ids in [
+            boundaries.open.as_mut().map(|b| &mut b.nodes_ids),
+            boundaries.land.as_mut().map(|b| &mut b.nodes_ids),
+            boundaries.interior.as_mut().map(|b| &mut b.nodes_ids),
+            boundaries.flow.as_mut().map(|b| &mut b.nodes_ids),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *nodes_ids = nodes_ids.iter().map(remap).collect();
+        }
+        if let Some(external) = boundaries.external_barrier.as_mut() {
+            for segment in external.segments.iter_mut() {
+                let ids = &segment.nodes_ids;
+                let positions = kept(ids.len(), &|i| (map(&ids[i]), 0));
+                segment.attributes = positions.iter().map(|&i| segment.attributes[i]).collect();
+                segment.nodes_ids = pick(&positions, &segment.nodes_ids);
+            }
+        }
+        if let Some(internal) = boundaries.internal_barrier.as_mut() {
+            for segment in internal.segments.iter_mut() {
+                let (front, back) = (&segment.nodes_ids, &segment.paired_nodes_ids);
+                let positions = kept(front.len(), &|i| (map(&front[i]), map(&back[i])));
+                segment.attributes = positions.iter().map(|&i| segment.attributes[i]).collect();
+                segment.nodes_ids = pick(&positions, &segment.nodes_ids);
+                segment.paired_nodes_ids = pick(&positions, &segment.paired_nodes_ids);
+            }
+        }
+        if let Some(culvert) = boundaries.culvert.as_mut() {
+            for segment in culvert.segments.iter_mut() {
+                let (front, back) = (&segment.nodes_ids, &segment.paired_nodes_ids);
+                let positions = kept(front.len(), &|i| (map(&front[i]), map(&back[i])));
+                segment.attributes = positions.iter().map(|&i| segment.attributes[i]).collect();
+                segment.nodes_ids = pick(&positions, &segment.nodes_ids);
+                segment.paired_nodes_ids = pick(&positions, &segment.paired_nodes_ids);
+            }
+        }
+        boundaries.retain_segments(|kind, segment, _| match kind {
+            BoundaryType::ExternalBarrier
+            | BoundaryType::InternalBarrier
+            | BoundaryType::Culvert => !segment.is_empty(),
+            _ => segment.len() >= 2,
+        });
+        boundaries
+    }
 }
 
-#[derive(Hash, Eq, PartialEq, Debug, Ord, PartialOrd)]
+/// Keeps the entries of `values` whose flag is set.
+fn retain_flagged<T>(values: &mut Vec<T>, flags: &[bool]) {
+    let mut flags = flags.iter();
+    values.retain(|_| flags.next() == Some(&true));
+}
+
+#[derive(Hash, Eq, PartialEq, Debug, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BoundaryType {
     Open,
     Land,
     Interior,
+    Flow,
+    ExternalBarrier,
+    InternalBarrier,
+    Culvert,
+}
+
+impl BoundaryType {
+    /// Classify a non-ocean boundary segment by its ADCIRC/SCHISM IBTYPE flag.
+    ///
+    /// Returns `None` for flags that are not known to this crate.
+    pub fn from_ibtype(ibtype: u8) -> Option<BoundaryType> {
+        match ibtype {
+            0 | 10 | 20 => Some(BoundaryType::Land),
+            1 | 11 | 21 => Some(BoundaryType::Interior),
+            2 | 12 | 22 | 30 | 32 | 52 | 102 | 112 | 122 => Some(BoundaryType::Flow),
+            3 | 13 | 23 => Some(BoundaryType::ExternalBarrier),
+            4 | 24 | 64 => Some(BoundaryType::InternalBarrier),
+            5 | 25 => Some(BoundaryType::Culvert),
+            _ => None,
+        }
+    }
+}
+
+/// Serialized layout of [`Boundaries`].
+///
+/// A standalone `Boundaries` carries its nodes; inside an `Hgrid` they are
+/// omitted and re-linked to the grid's shared nodes.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct BoundariesRepr<'a> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nodes: Option<Cow<'a, Nodes>>,
+    open: Option<Cow<'a, [Vec<u32>]>>,
+    land: Option<TypedSegmentsRepr<'a>>,
+    interior: Option<TypedSegmentsRepr<'a>>,
+    flow: Option<TypedSegmentsRepr<'a>>,
+    external_barrier: Option<Cow<'a, [ExternalBarrierSegment]>>,
+    internal_barrier: Option<Cow<'a, [InternalBarrierSegment]>>,
+    culvert: Option<Cow<'a, [CulvertSegment]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    non_ocean_order: Option<Cow<'a, [BoundaryType]>>,
+}
+
+/// Node ids and IBTYPE flags of land, interior or flow segments.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TypedSegmentsRepr<'a> {
+    nodes_ids: Cow<'a, [Vec<u32>]>,
+    ibtypes: Cow<'a, [u8]>,
+}
+
+#[cfg(feature = "serde")]
+impl Boundaries {
+    pub(crate) fn to_repr(&self, with_nodes: bool) -> BoundariesRepr<'_> {
+        // Every boundary kind shares the grid's nodes, so any of them will do
+        let nodes = [
+            self.open.as_ref().map(|b| &b.nodes),
+            self.land.as_ref().map(|b| &b.nodes),
+            self.interior.as_ref().map(|b| &b.nodes),
+            self.flow.as_ref().map(|b| &b.nodes),
+            self.external_barrier.as_ref().map(|b| &b.nodes),
+            self.internal_barrier.as_ref().map(|b| &b.nodes),
+            self.culvert.as_ref().map(|b| &b.nodes),
+        ]
+        .into_iter()
+        .flatten()
+        .next();
+        BoundariesRepr {
+            nodes: nodes
+                .filter(|_| with_nodes)
+                .map(|nodes| Cow::Borrowed(nodes.as_ref())),
+            open: self.open.as_ref().map(|b| Cow::Borrowed(&b.nodes_ids[..])),
+            land: self.land.as_ref().map(|b| TypedSegmentsRepr {
+                nodes_ids: Cow::Borrowed(&b.nodes_ids),
+                ibtypes: Cow::Borrowed(&b.ibtypes),
+            }),
+            interior: self.interior.as_ref().map(|b| TypedSegmentsRepr {
+                nodes_ids: Cow::Borrowed(&b.nodes_ids),
+                ibtypes: Cow::Borrowed(&b.ibtypes),
+            }),
+            flow: self.flow.as_ref().map(|b| TypedSegmentsRepr {
+                nodes_ids: Cow::Borrowed(&b.nodes_ids),
+                ibtypes: Cow::Borrowed(&b.ibtypes),
+            }),
+            external_barrier: self
+                .external_barrier
+                .as_ref()
+                .map(|b| Cow::Borrowed(&b.segments[..])),
+            internal_barrier: self
+                .internal_barrier
+                .as_ref()
+                .map(|b| Cow::Borrowed(&b.segments[..])),
+            culvert: self
+                .culvert
+                .as_ref()
+                .map(|b| Cow::Borrowed(&b.segments[..])),
+            non_ocean_order: self.non_ocean_order.as_deref().map(Cow::Borrowed),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl BoundariesRepr<'_> {
+    pub(crate) fn into_boundaries(self, nodes: Arc<Nodes>) -> Boundaries {
+        Boundaries {
+            open: self.open.map(|nodes_ids| OpenBoundaries {
+                nodes: nodes.clone(),
+                nodes_ids: nodes_ids.into_owned(),
+            }),
+            land: self.land.map(|b| LandBoundaries {
+                nodes: nodes.clone(),
+                nodes_ids: b.nodes_ids.into_owned(),
+                ibtypes: b.ibtypes.into_owned(),
+            }),
+            interior: self.interior.map(|b| InteriorBoundaries {
+                nodes: nodes.clone(),
+                nodes_ids: b.nodes_ids.into_owned(),
+                ibtypes: b.ibtypes.into_owned(),
+            }),
+            flow: self.flow.map(|b| FlowBoundaries {
+                nodes: nodes.clone(),
+                nodes_ids: b.nodes_ids.into_owned(),
+                ibtypes: b.ibtypes.into_owned(),
+            }),
+            external_barrier: self
+                .external_barrier
+                .map(|segments| ExternalBarrierBoundaries {
+                    nodes: nodes.clone(),
+                    segments: segments.into_owned(),
+                }),
+            internal_barrier: self
+                .internal_barrier
+                .map(|segments| InternalBarrierBoundaries {
+                    nodes: nodes.clone(),
+                    segments: segments.into_owned(),
+                }),
+            culvert: self.culvert.map(|segments| CulvertBoundaries {
+                nodes: nodes.clone(),
+                segments: segments.into_owned(),
+            }),
+            non_ocean_order: self.non_ocean_order.map(Cow::into_owned),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Boundaries {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_repr(true).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Boundaries {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut repr = BoundariesRepr::deserialize(deserializer)?;
+        let nodes = repr
+            .nodes
+            .take()
+            .ok_or_else(|| serde::de::Error::missing_field("nodes"))?;
+        Ok(repr.into_boundaries(Arc::new(nodes.into_owned())))
+    }
 }
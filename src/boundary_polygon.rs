@@ -16,6 +16,41 @@ pub struct BoundaryPolygon {
     pub edges: Array2<usize>,
 }
 
+impl BoundaryPolygon {
+    /// Build a polygon from closed rings of (x, y) vertices, e.g. a region mask.
+    ///
+    /// Each ring is closed implicitly (the last vertex connects back to the first).
+    /// Later rings inside earlier ones act as holes, as with mesh islands.
+    pub fn from_rings(rings: &[Vec<(f64, f64)>]) -> Self {
+        let total: usize = rings.iter().map(|r| r.len()).sum();
+        let mut nodes_data = Vec::with_capacity(total * 2);
+        let mut edges_data = Vec::with_capacity(total * 2);
+        let mut offset = 0;
+        for ring in rings {
+            for (i, (x, y)) in ring.iter().enumerate() {
+                nodes_data.push(*x);
+                nodes_data.push(*y);
+                edges_data.push(offset + i);
+                edges_data.push(offset + (i + 1) % ring.len());
+            }
+            offset += ring.len();
+        }
+        BoundaryPolygon {
+            nodes: Array2::from_shape_vec((total, 2), nodes_data).unwrap(),
+            edges: Array2::from_shape_vec((total, 2), edges_data).unwrap(),
+        }
+    }
+
+    /// Check which of the points (an Nx2 array) fall inside the polygon.
+    pub fn contains_points(&self, points: &Array2<f64>) -> Vec<bool> {
+        if self.nodes.is_empty() || points.is_empty() {
+            return vec![false; points.nrows()];
+        }
+        let (inside, _on_edge) = inpoly::inpoly2(points, &self.nodes, Some(&self.edges), None);
+        inside.to_vec()
+    }
+}
+
 impl Hgrid {
     /// Extract the mesh boundary polygon for containment testing.
     ///
@@ -88,36 +123,90 @@ impl Hgrid {
         inside.to_vec()
     }
 
-    /// Find boundary edges (edges that appear in only one element).
+    /// Outline of the mesh domain as polygons of node ids.
     ///
-    /// An edge is on the boundary if it belongs to exactly one element.
-    /// Edges are stored as (smaller_node_id, larger_node_id) for deduplication.
-    fn find_boundary_edges(&self) -> Vec<(u32, u32)> {
-        let mut edge_count: HashMap<(u32, u32), usize> = HashMap::new();
-
-        for node_ids in self.elements().hash_map().values() {
-            let n = node_ids.len();
-            for i in 0..n {
-                let a = node_ids[i];
-                let b = node_ids[(i + 1) % n];
-                // Normalize edge direction for counting
-                let edge = if a < b { (a, b) } else { (b, a) };
-                *edge_count.entry(edge).or_insert(0) += 1;
+    /// Each polygon is an exterior ring followed by its island rings. Exterior
+    /// rings run counterclockwise and islands clockwise (the GeoJSON winding);
+    /// rings are not closed, the last node connects back to the first.
+    pub fn domain_polygons(&self) -> Vec<Vec<Vec<u32>>> {
+        let nodes = self.nodes();
+        let coords = |ring: &[u32]| -> Vec<(f64, f64)> {
+            ring.iter()
+                .map(|node_id| nodes.get_node(*node_id).unwrap_or((f64::NAN, f64::NAN)))
+                .collect()
+        };
+        let rings: Vec<Vec<u32>> = edges_to_rings(self.find_boundary_edges())
+            .into_iter()
+            .map(|ring| ring.into_iter().map(|(a, _b)| a).collect())
+            .collect();
+        let ring_coords: Vec<Vec<(f64, f64)>> = rings.iter().map(|ring| coords(ring)).collect();
+        let areas: Vec<f64> = ring_coords.iter().map(|ring| signed_area(ring)).collect();
+
+        // Rings nested an even number of times are exteriors, odd ones holes.
+        // The midpoint of a ring's first edge is never on another ring.
+        let containers: Vec<Vec<usize>> = ring_coords
+            .iter()
+            .enumerate()
+            .map(|(i, ring)| {
+                let probe = match ring.len() {
+                    0 => (f64::NAN, f64::NAN),
+                    1 => ring[0],
+                    _ => ((ring[0].0 + ring[1].0) / 2.0, (ring[0].1 + ring[1].1) / 2.0),
+                };
+                (0..ring_coords.len())
+                    .filter(|&j| j != i && ring_contains(&ring_coords[j], probe))
+                    .collect()
+            })
+            .collect();
+
+        let mut polygons: Vec<Vec<Vec<u32>>> = Vec::new();
+        let mut polygon_of_ring = HashMap::new();
+        for (i, ring) in rings.iter().enumerate() {
+            if containers[i].len().is_multiple_of(2) {
+                let mut ring = ring.clone();
+                if areas[i] < 0.0 {
+                    ring.reverse();
+                }
+                polygon_of_ring.insert(i, polygons.len());
+                polygons.push(vec![ring]);
             }
         }
+        for (i, ring) in rings.iter().enumerate() {
+            if !containers[i].len().is_multiple_of(2) {
+                // The innermost exterior around the hole owns it
+                let owner = containers[i]
+                    .iter()
+                    .filter(|j| polygon_of_ring.contains_key(*j))
+                    .min_by(|a, b| areas[**a].abs().total_cmp(&areas[**b].abs()));
+                let mut ring = ring.clone();
+                if areas[i] > 0.0 {
+                    ring.reverse();
+                }
+                match owner {
+                    Some(owner) => polygons[polygon_of_ring[owner]].push(ring),
+                    None => polygons.push(vec![ring]),
+                }
+            }
+        }
+        polygons
+    }
 
-        // Return edges that appear exactly once
-        edge_count
-            .into_iter()
-            .filter(|(_, count)| *count == 1)
-            .map(|(edge, _)| edge)
+    /// Find boundary edges (sides of the cached topology with a single element).
+    ///
+    /// Edges are stored as (smaller_node_id, larger_node_id) for deduplication.
+    fn find_boundary_edges(&self) -> Vec<(u32, u32)> {
+        self.topology()
+            .boundary_sides()
+            .map(|side| {
+                let (a, b) = side.nodes;
+                (a.min(b), a.max(b))
+            })
             .collect()
     }
 
     /// Build inpoly-compatible node and edge arrays from ordered rings.
     fn build_inpoly_arrays(&self, rings: &[Vec<(u32, u32)>]) -> BoundaryPolygon {
-        let xs = self.x();
-        let ys = self.y();
+        let nodes = self.nodes();
 
         // Collect all unique nodes from all rings
         let mut all_nodes: Vec<u32> = Vec::new();
@@ -135,10 +224,10 @@ impl Hgrid {
         // Build nodes array (Nx2)
         let mut nodes_data = Vec::with_capacity(all_nodes.len() * 2);
         for node_id in &all_nodes {
-            // Node IDs are 1-indexed
-            let idx = (*node_id as usize) - 1;
-            nodes_data.push(xs[idx]);
-            nodes_data.push(ys[idx]);
+            // Elements referencing missing nodes are caught by check_validity()
+            let (x, y) = nodes.get_node(*node_id).unwrap_or((f64::NAN, f64::NAN));
+            nodes_data.push(x);
+            nodes_data.push(y);
         }
         let nodes = Array2::from_shape_vec((all_nodes.len(), 2), nodes_data).unwrap();
 
@@ -158,6 +247,33 @@ impl Hgrid {
     }
 }
 
+/// Shoelace area of a ring, positive when counterclockwise.
+fn signed_area(ring: &[(f64, f64)]) -> f64 {
+    let n = ring.len();
+    (0..n)
+        .map(|i| {
+            let (x0, y0) = ring[i];
+            let (x1, y1) = ring[(i + 1) % n];
+            x0 * y1 - x1 * y0
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Even-odd point in ring test.
+fn ring_contains(ring: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    let n = ring.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (x0, y0) = ring[i];
+        let (x1, y1) = ring[(i + 1) % n];
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) * (x1 - x0) / (y1 - y0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
 /// Order boundary edges into closed rings.
 ///
 /// Takes a list of unordered edges and chains them into closed loops.
@@ -395,4 +511,66 @@ mod tests {
 
         assert_eq!(results, vec![true, false, true, false]);
     }
+
+    /// 3x3 block of unit quads with the centre one missing (node 6-7-11-10 hole)
+    fn make_mesh_with_island() -> Hgrid {
+        let mut nodes_map: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> = LinkedHashMap::new();
+        for j in 0..4 {
+            for i in 0..4 {
+                nodes_map.insert(1 + i + 4 * j, (vec![i as f64, j as f64], Some(vec![1.0])));
+            }
+        }
+        let mut elements_map = LinkedHashMap::new();
+        for j in 0..3 {
+            for i in 0..3 {
+                if (i, j) == (1, 1) {
+                    continue;
+                }
+                let first = 1 + i + 4 * j;
+                elements_map.insert(
+                    elements_map.len() as u32 + 1,
+                    vec![first, first + 1, first + 5, first + 4],
+                );
+            }
+        }
+        let nodes_arc = Arc::new(NodesBuilder::default().hash_map(nodes_map).build().unwrap());
+        let elements = ElementsBuilder::default()
+            .hash_map(elements_map)
+            .nodes(nodes_arc.clone())
+            .build()
+            .unwrap();
+        HgridBuilder::default()
+            .nodes(nodes_arc)
+            .elements(elements)
+            .boundaries(None)
+            .description(None::<String>)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_domain_polygons_with_island() {
+        let hgrid = make_mesh_with_island();
+        let polygons = hgrid.domain_polygons();
+        assert_eq!(polygons.len(), 1);
+        let [exterior, island] = &polygons[0][..] else {
+            panic!("expected an exterior ring and one island");
+        };
+        assert_eq!(exterior.len(), 12);
+        let mut island_nodes = island.clone();
+        island_nodes.sort();
+        assert_eq!(island_nodes, vec![6, 7, 10, 11]);
+
+        let nodes = hgrid.nodes();
+        let area = |ring: &Vec<u32>| {
+            signed_area(
+                &ring
+                    .iter()
+                    .map(|id| nodes.get_node(*id).unwrap())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(area(exterior), 9.0);
+        assert_eq!(area(island), -1.0);
+    }
 }
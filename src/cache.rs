@@ -0,0 +1,716 @@
+//! Versioned binary cache of an `Hgrid` for fast reloading
+//!
+//! The file is a fixed header followed by little-endian, fixed-width sections,
+//! so it is read through a memory map without any text parsing or CRS
+//! detection:
+//!
+//! - header: magic `HGRIDBIN`, format version (u32), depth convention (u32),
+//!   the 64 hex digits of [`Hgrid::calculate_hash`] (informational), and the
+//!   byte length and modification time (u64 each, nanoseconds since the Unix
+//!   epoch) of the grid file the cache was built from, zero when there is none
+//! - description and CRS: presence flag (u8), byte length (u64), UTF-8 bytes
+//! - nodes: count (u64), value columns (u32), ids (u32), x/y pairs (f64) and
+//!   depth rows (f64, gr3 sign convention, NaN rows for nodes without values)
+//! - elements: count (u64), ids (u32), vertex counts (u8), node ids (u32)
+//! - boundaries: open, land, interior and flow segment lists with their IBTYPE
+//!   flags, then external barrier, internal barrier and culvert segments, and
+//!   the kind of each non-ocean segment in file order (u8 each)
+//!
+//! A segment list is a count (u64) followed by each segment's length (u64)
+//! and node ids (u32). Any change to the layout must bump [`CACHE_VERSION`].
+//! Node attributes other than depth and element attributes are not cached.
+
+use crate::boundaries::{
+    BarrierAttributes, BoundaryType, CulvertAttributes, CulvertSegment, ExternalBarrierSegment,
+    InternalBarrierSegment, WeirAttributes,
+};
+use crate::gr3::{Gr3ParserOutputBuilder, Gr3ParserOutputBuilderError};
+use crate::hgrid::{DepthConvention, HgridTryFromError};
+use crate::nodes::DEPTH_ATTRIBUTE;
+use crate::Hgrid;
+use linked_hash_map::LinkedHashMap;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{prelude::*, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tempfile::NamedTempFile;
+use thiserror::Error;
+
+const MAGIC: &[u8; 8] = b"HGRIDBIN";
+/// Version of the cache layout, checked on read
+pub const CACHE_VERSION: u32 = 3;
+const HASH_LENGTH: usize = 64;
+/// Magic, version, depth convention, hash and source stamp
+const HEADER_LENGTH: usize = 16 + HASH_LENGTH + 16;
+/// Non-ocean boundary kinds, stored by their position in this list
+const NON_OCEAN_KINDS: [BoundaryType; 6] = [
+    BoundaryType::Land,
+    BoundaryType::Interior,
+    BoundaryType::Flow,
+    BoundaryType::ExternalBarrier,
+    BoundaryType::InternalBarrier,
+    BoundaryType::Culvert,
+];
+/// Appended to the grid file name to name its sidecar cache
+const CACHE_SUFFIX: &str = "cache";
+
+#[derive(Error, Debug)]
+pub enum CacheError {
+    #[error("I/O error: {0}")]
+    IoError(String),
+
+    #[error("Invalid cache file {0}: {1}")]
+    FormatError(String, String),
+
+    #[error(transparent)]
+    Gr3ParserOutputBuilderError(#[from] Gr3ParserOutputBuilderError),
+
+    #[error(transparent)]
+    HgridTryFromError(#[from] HgridTryFromError),
+}
+
+impl Hgrid {
+    /// Write the grid to a binary cache file.
+    pub fn write_cache(&self, path: &Path) -> Result<(), CacheError> {
+        write_to_path(path, self)
+    }
+
+    /// Read a grid from a binary cache file.
+    pub fn read_cache(path: &Path) -> Result<Hgrid, CacheError> {
+        read_from_path(path)
+    }
+
+    /// Load a grid file, going through its sidecar cache (`<file>.cache`).
+    ///
+    /// The cache is used when it has the current layout version and records
+    /// the same byte length and modification time as the grid file has now;
+    /// otherwise the grid is parsed and the cache rewritten. The stored
+    /// [`Hgrid::calculate_hash`] is informational and not checked, since it
+    /// can only be computed from the parsed grid. Failing to write the cache
+    /// (e.g. in a read-only directory) is logged and otherwise ignored.
+    ///
+    /// Node attributes other than depth and element attributes are not
+    /// cached, so a grid loaded from its cache has none of them.
+    pub fn load_cached(path: &Path) -> Result<Hgrid, CacheError> {
+        let cache_path = cache_path(path);
+        // Taken before parsing, so a file changed meanwhile is parsed again
+        let source = SourceStamp::of(path);
+        let header = read_header(&cache_path);
+        if source.is_some() && header.is_ok_and(|(_hash, stamp)| Some(stamp) == source) {
+            match read_from_path(&cache_path) {
+                Ok(hgrid) => return Ok(hgrid),
+                Err(e) => log::debug!("Ignoring cache {}: {}", cache_path.display(), e),
+            }
+        }
+        let hgrid = Hgrid::try_from(&path.to_path_buf())?;
+        let source = source.unwrap_or_default();
+        if let Err(e) = write_with_source(&cache_path, &hgrid, source) {
+            log::warn!("Failed to write cache {}: {}", cache_path.display(), e);
+        }
+        Ok(hgrid)
+    }
+}
+
+/// Byte length and modification time of the grid file behind a cache
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct SourceStamp {
+    len: u64,
+    modified: u64,
+}
+
+impl SourceStamp {
+    fn of(path: &Path) -> Option<SourceStamp> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(SourceStamp {
+            len: metadata.len(),
+            modified: modified.as_nanos() as u64,
+        })
+    }
+}
+
+/// Sidecar cache path of a grid file
+pub fn cache_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(CACHE_SUFFIX);
+    path.with_file_name(file_name)
+}
+
+/// Key of a cache file, the [`Hgrid::calculate_hash`] of the cached grid,
+/// read from the header only.
+pub fn read_hash(path: &Path) -> Result<String, CacheError> {
+    read_header(path).map(|(hash, _source)| hash)
+}
+
+fn read_header(path: &Path) -> Result<(String, SourceStamp), CacheError> {
+    let fname = path.display().to_string();
+    let mut header = [0u8; HEADER_LENGTH];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_err(|e| CacheError::IoError(format!("Failed to read {}: {}", fname, e)))?;
+    let mut decoder = Decoder {
+        bytes: &header,
+        position: 0,
+        fname: &fname,
+    };
+    decoder
+        .header()
+        .map(|(_convention, hash, source)| (hash, source))
+}
+
+struct Encoder<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Encoder<W> {
+    fn bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.writer.write_all(bytes)
+    }
+
+    fn u8(&mut self, value: u8) -> std::io::Result<()> {
+        self.bytes(&[value])
+    }
+
+    fn u32(&mut self, value: u32) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn u64(&mut self, value: usize) -> std::io::Result<()> {
+        self.bytes(&(value as u64).to_le_bytes())
+    }
+
+    fn f64(&mut self, value: f64) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn u32s(&mut self, values: &[u32]) -> std::io::Result<()> {
+        values.iter().try_for_each(|&value| self.u32(value))
+    }
+
+    fn text(&mut self, text: Option<&str>) -> std::io::Result<()> {
+        self.u8(u8::from(text.is_some()))?;
+        let text = text.unwrap_or_default();
+        self.u64(text.len())?;
+        self.bytes(text.as_bytes())
+    }
+
+    fn segments<'a>(
+        &mut self,
+        segments: impl ExactSizeIterator<Item = &'a [u32]>,
+    ) -> std::io::Result<()> {
+        self.u64(segments.len())?;
+        for segment in segments {
+            self.u64(segment.len())?;
+            self.u32s(segment)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_cache<W: Write>(writer: W, hgrid: &Hgrid, source: SourceStamp) -> std::io::Result<()> {
+    let mut encoder = Encoder { writer };
+    encoder.bytes(MAGIC)?;
+    encoder.u32(CACHE_VERSION)?;
+    encoder.u32(match hgrid.depth_convention() {
+        DepthConvention::PositiveDown => 0,
+        DepthConvention::PositiveUp => 1,
+    })?;
+    encoder.bytes(hgrid.calculate_hash().as_bytes())?;
+    encoder.bytes(&source.len.to_le_bytes())?;
+    encoder.bytes(&source.modified.to_le_bytes())?;
+    encoder.text(hgrid.description().map(String::as_str))?;
+    encoder.text(hgrid.crs())?;
+
+    let nodes = hgrid.nodes();
+    let depth = nodes.attribute(DEPTH_ATTRIBUTE);
+    // Rows are stored in the gr3 sign convention, as written by `Hgrid::write`
+    let sign = match hgrid.depth_convention() {
+        DepthConvention::PositiveDown => 1.0,
+        DepthConvention::PositiveUp => -1.0,
+    };
+    encoder.u64(nodes.len())?;
+    encoder.u32(depth.map_or(0, |depth| depth.ncols()) as u32)?;
+    encoder.u32s(nodes.ids())?;
+    for (&x, &y) in hgrid.x().iter().zip(hgrid.y().iter()) {
+        encoder.f64(x)?;
+        encoder.f64(y)?;
+    }
+    if let Some(depth) = depth {
+        for index in 0..nodes.len() {
+            for value in depth.row(index) {
+                encoder.f64(sign * value)?;
+            }
+        }
+    }
+
+    let elements = hgrid.elements();
+    encoder.u64(elements.len())?;
+    encoder.u32s(elements.ids())?;
+    encoder.bytes(elements.vertex_counts())?;
+    for (_elem_id, element) in elements.iter() {
+        encoder.u32s(element)?;
+    }
+
+    let boundaries = hgrid.boundaries();
+    let no_segments: Vec<Vec<u32>> = Vec::new();
+    let segment_lists = [
+        (
+            boundaries.and_then(|b| b.open()).map(|b| b.nodes_ids()),
+            None,
+        ),
+        (
+            boundaries.and_then(|b| b.land()).map(|b| b.nodes_ids()),
+            boundaries.and_then(|b| b.land()).map(|b| b.ibtypes()),
+        ),
+        (
+            boundaries.and_then(|b| b.interior()).map(|b| b.nodes_ids()),
+            boundaries.and_then(|b| b.interior()).map(|b| b.ibtypes()),
+        ),
+        (
+            boundaries.and_then(|b| b.flow()).map(|b| b.nodes_ids()),
+            boundaries.and_then(|b| b.flow()).map(|b| b.ibtypes()),
+        ),
+    ];
+    for (segments, ibtypes) in segment_lists {
+        encoder.segments(segments.unwrap_or(&no_segments).iter().map(Vec::as_slice))?;
+        let ibtypes = ibtypes.unwrap_or_default();
+        encoder.u64(ibtypes.len())?;
+        encoder.bytes(&ibtypes)?;
+    }
+
+    let external = boundaries
+        .and_then(|b| b.external_barrier())
+        .map(|b| b.segments().as_slice())
+        .unwrap_or_default();
+    encoder.u64(external.len())?;
+    for segment in external {
+        encoder.u8(segment.ibtype)?;
+        encoder.segments(std::iter::once(segment.nodes_ids.as_slice()))?;
+        for attributes in &segment.attributes {
+            encoder.f64(attributes.height)?;
+            encoder.f64(attributes.supercritical_coefficient)?;
+        }
+    }
+    let write_weir = |encoder: &mut Encoder<W>, weir: &WeirAttributes| {
+        encoder.f64(weir.height)?;
+        encoder.f64(weir.subcritical_coefficient)?;
+        encoder.f64(weir.supercritical_coefficient)
+    };
+    let internal = boundaries
+        .and_then(|b| b.internal_barrier())
+        .map(|b| b.segments().as_slice())
+        .unwrap_or_default();
+    encoder.u64(internal.len())?;
+    for segment in internal {
+        encoder.u8(segment.ibtype)?;
+        encoder.segments(
+            [
+                segment.nodes_ids.as_slice(),
+                segment.paired_nodes_ids.as_slice(),
+            ]
+            .into_iter(),
+        )?;
+        for attributes in &segment.attributes {
+            write_weir(&mut encoder, attributes)?;
+        }
+    }
+    let culverts = boundaries
+        .and_then(|b| b.culvert())
+        .map(|b| b.segments().as_slice())
+        .unwrap_or_default();
+    encoder.u64(culverts.len())?;
+    for segment in culverts {
+        encoder.u8(segment.ibtype)?;
+        encoder.segments(
+            [
+                segment.nodes_ids.as_slice(),
+                segment.paired_nodes_ids.as_slice(),
+            ]
+            .into_iter(),
+        )?;
+        for attributes in &segment.attributes {
+            write_weir(&mut encoder, &attributes.weir)?;
+            encoder.f64(attributes.pipe_height)?;
+            encoder.f64(attributes.pipe_coefficient)?;
+            encoder.f64(attributes.pipe_diameter)?;
+        }
+    }
+    let order = boundaries
+        .and_then(|b| b.non_ocean_order())
+        .unwrap_or_default();
+    encoder.u64(order.len())?;
+    for kind in order {
+        // Every non-ocean kind is listed
+        encoder.u8(NON_OCEAN_KINDS.iter().position(|k| k == kind).unwrap() as u8)?;
+    }
+    encoder.writer.flush()
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    fname: &'a str,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CacheError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| {
+                self.error(format!(
+                    "Expected {} more bytes at offset {} but the file ends at {}.",
+                    len,
+                    self.position,
+                    self.bytes.len()
+                ))
+            })?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, CacheError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, CacheError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<usize, CacheError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()) as usize)
+    }
+
+    fn f64(&mut self) -> Result<f64, CacheError> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn u32s(&mut self, len: usize) -> Result<Vec<u32>, CacheError> {
+        let bytes = self.take(len.saturating_mul(4))?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+
+    fn f64s(&mut self, len: usize) -> Result<Vec<f64>, CacheError> {
+        let bytes = self.take(len.saturating_mul(8))?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+
+    fn text(&mut self) -> Result<Option<String>, CacheError> {
+        let is_present = self.u8()? != 0;
+        let len = self.u64()?;
+        let text = std::str::from_utf8(self.take(len)?)
+            .map_err(|e| self.error(format!("Invalid UTF-8 text: {}", e)))?;
+        Ok(is_present.then(|| text.to_string()))
+    }
+
+    fn segments(&mut self) -> Result<Vec<Vec<u32>>, CacheError> {
+        let count = self.u64()?;
+        let mut segments = Vec::new();
+        for _ in 0..count {
+            let len = self.u64()?;
+            segments.push(self.u32s(len)?);
+        }
+        Ok(segments)
+    }
+
+    /// Check the magic and version; returns the depth convention, hash and
+    /// source stamp.
+    fn header(&mut self) -> Result<(DepthConvention, String, SourceStamp), CacheError> {
+        if self.take(MAGIC.len())? != MAGIC {
+            return Err(self.error("Not an hgrid cache file.".to_string()));
+        }
+        let version = self.u32()?;
+        if version != CACHE_VERSION {
+            return Err(self.error(format!(
+                "Cache version {} does not match the supported version {}.",
+                version, CACHE_VERSION
+            )));
+        }
+        let convention = match self.u32()? {
+            0 => DepthConvention::PositiveDown,
+            1 => DepthConvention::PositiveUp,
+            other => return Err(self.error(format!("Unknown depth convention {}.", other))),
+        };
+        let hash = String::from_utf8_lossy(self.take(HASH_LENGTH)?).to_string();
+        let source = SourceStamp {
+            len: self.u64()? as u64,
+            modified: self.u64()? as u64,
+        };
+        Ok((convention, hash, source))
+    }
+
+    fn error(&self, message: String) -> CacheError {
+        CacheError::FormatError(self.fname.to_string(), message)
+    }
+}
+
+fn decode(bytes: &[u8], fname: &str) -> Result<Hgrid, CacheError> {
+    let mut decoder = Decoder {
+        bytes,
+        position: 0,
+        fname,
+    };
+    let (convention, _hash, _source) = decoder.header()?;
+    let description = decoder.text()?;
+    let crs = decoder.text()?;
+
+    let node_count = decoder.u64()?;
+    let ncols = decoder.u32()? as usize;
+    let ids = decoder.u32s(node_count)?;
+    let coords = decoder.f64s(node_count.saturating_mul(2))?;
+    let values = decoder.f64s(node_count.saturating_mul(ncols))?;
+    let nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> = ids
+        .into_iter()
+        .enumerate()
+        .map(|(index, node_id)| {
+            let row = &values[index * ncols..(index + 1) * ncols];
+            let row = (ncols > 0 && !row.iter().all(|v| v.is_nan())).then(|| row.to_vec());
+            (node_id, (coords[2 * index..2 * index + 2].to_vec(), row))
+        })
+        .collect();
+
+    let element_count = decoder.u64()?;
+    let element_ids = decoder.u32s(element_count)?;
+    let vertex_counts = decoder.take(element_count)?;
+    let mut elements: LinkedHashMap<u32, Vec<u32>> = LinkedHashMap::new();
+    for (&elem_id, &vertex_count) in element_ids.iter().zip(vertex_counts) {
+        elements.insert(elem_id, decoder.u32s(vertex_count as usize)?);
+    }
+
+    let mut segment_lists = Vec::with_capacity(4);
+    for _ in 0..4 {
+        let segments = decoder.segments()?;
+        let len = decoder.u64()?;
+        let ibtypes = decoder.take(len)?.to_vec();
+        segment_lists.push((segments, (!ibtypes.is_empty()).then_some(ibtypes)));
+    }
+    let [(open, _), (land, land_ibtypes), (interior, interior_ibtypes), (flow, flow_ibtypes)]: [_;
+        4] = segment_lists.try_into().unwrap();
+
+    let mut external = Vec::new();
+    for _ in 0..decoder.u64()? {
+        let ibtype = decoder.u8()?;
+        let nodes_ids = decoder.segments()?.into_iter().next().unwrap_or_default();
+        let mut attributes = Vec::with_capacity(nodes_ids.len());
+        for _ in 0..nodes_ids.len() {
+            attributes.push(BarrierAttributes {
+                height: decoder.f64()?,
+                supercritical_coefficient: decoder.f64()?,
+            });
+        }
+        external.push(ExternalBarrierSegment {
+            ibtype,
+            nodes_ids,
+            attributes,
+        });
+    }
+    let read_weir = |decoder: &mut Decoder| -> Result<WeirAttributes, CacheError> {
+        Ok(WeirAttributes {
+            height: decoder.f64()?,
+            subcritical_coefficient: decoder.f64()?,
+            supercritical_coefficient: decoder.f64()?,
+        })
+    };
+    let read_pairs = |decoder: &mut Decoder| -> Result<(Vec<u32>, Vec<u32>), CacheError> {
+        let mut faces = decoder.segments()?.into_iter();
+        Ok((
+            faces.next().unwrap_or_default(),
+            faces.next().unwrap_or_default(),
+        ))
+    };
+    let mut internal = Vec::new();
+    for _ in 0..decoder.u64()? {
+        let ibtype = decoder.u8()?;
+        let (nodes_ids, paired_nodes_ids) = read_pairs(&mut decoder)?;
+        let mut attributes = Vec::with_capacity(nodes_ids.len());
+        for _ in 0..nodes_ids.len() {
+            attributes.push(read_weir(&mut decoder)?);
+        }
+        internal.push(InternalBarrierSegment {
+            ibtype,
+            nodes_ids,
+            paired_nodes_ids,
+            attributes,
+        });
+    }
+    let mut culverts = Vec::new();
+    for _ in 0..decoder.u64()? {
+        let ibtype = decoder.u8()?;
+        let (nodes_ids, paired_nodes_ids) = read_pairs(&mut decoder)?;
+        let mut attributes = Vec::with_capacity(nodes_ids.len());
+        for _ in 0..nodes_ids.len() {
+            attributes.push(CulvertAttributes {
+                weir: read_weir(&mut decoder)?,
+                pipe_height: decoder.f64()?,
+                pipe_coefficient: decoder.f64()?,
+                pipe_diameter: decoder.f64()?,
+            });
+        }
+        culverts.push(CulvertSegment {
+            ibtype,
+            nodes_ids,
+            paired_nodes_ids,
+            attributes,
+        });
+    }
+    let order_len = decoder.u64()?;
+    let order = decoder
+        .take(order_len)?
+        .iter()
+        .map(|&code| {
+            NON_OCEAN_KINDS
+                .get(code as usize)
+                .copied()
+                .ok_or_else(|| decoder.error(format!("unknown boundary kind {}", code)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut builder = Gr3ParserOutputBuilder::default();
+    builder
+        .description(description)
+        .crs(crs)
+        .nodes(nodes)
+        .elements(Some(elements))
+        .open_boundaries(open)
+        .land_boundaries(land)
+        .land_boundaries_ibtypes(land_ibtypes)
+        .interior_boundaries(interior)
+        .interior_boundaries_ibtypes(interior_ibtypes)
+        .flow_boundaries(flow)
+        .flow_boundaries_ibtypes(flow_ibtypes)
+        .external_barrier_boundaries(external)
+        .internal_barrier_boundaries(internal)
+        .culvert_boundaries(culverts)
+        .non_ocean_boundaries_order((!order.is_empty()).then_some(order));
+    let mut hgrid = Hgrid::try_from(builder.build()?)?;
+    if convention == DepthConvention::PositiveUp {
+        hgrid.flip_depths();
+    }
+    Ok(hgrid)
+}
+
+pub fn read_from_path(path: &Path) -> Result<Hgrid, CacheError> {
+    let fname = path.display().to_string();
+    let file = File::open(path)
+        .map_err(|e| CacheError::IoError(format!("Failed to open {}: {}", fname, e)))?;
+    // SAFETY: the map is only read while decoding, which copies everything
+    // out; caches are replaced by rename, never rewritten in place.
+    let mmap = unsafe { Mmap::map(&file) }
+        .map_err(|e| CacheError::IoError(format!("Failed to map {}: {}", fname, e)))?;
+    decode(&mmap, &fname)
+}
+
+pub fn write_to_path(path: &Path, hgrid: &Hgrid) -> Result<(), CacheError> {
+    write_with_source(path, hgrid, SourceStamp::default())
+}
+
+fn write_with_source(path: &Path, hgrid: &Hgrid, source: SourceStamp) -> Result<(), CacheError> {
+    let io_error = |e: std::io::Error| {
+        CacheError::IoError(format!("Failed to write {}: {}", path.display(), e))
+    };
+    // Created beside the target so the final rename is atomic for readers
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut tmpfile = NamedTempFile::new_in(directory).map_err(io_error)?;
+    log::debug!("Will write to tmpfile: {:?}", tmpfile);
+    write_cache(BufWriter::new(tmpfile.as_file_mut()), hgrid, source).map_err(io_error)?;
+    tmpfile.persist(path).map_err(|e| io_error(e.error))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{hgrid_from_gr3, QUAD_AND_TRIANGLE};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.cache");
+        for flip in [false, true] {
+            let mut hgrid = hgrid_from_gr3(QUAD_AND_TRIANGLE);
+            if flip {
+                hgrid.flip_depths();
+            }
+            hgrid.write_cache(&path).unwrap();
+            assert_eq!(read_hash(&path).unwrap(), hgrid.calculate_hash());
+            let reloaded = Hgrid::read_cache(&path).unwrap();
+            assert_eq!(reloaded.depth_convention(), hgrid.depth_convention());
+            assert_eq!(reloaded.depths(), hgrid.depths());
+            assert_eq!(reloaded.crs(), hgrid.crs());
+            assert_eq!(reloaded.quick_hash(), hgrid.quick_hash());
+            assert_eq!(
+                reloaded.boundaries().unwrap().non_ocean_order(),
+                Some(&[BoundaryType::Interior, BoundaryType::Land][..])
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_cached_uses_fresh_sidecar() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.gr3");
+        std::fs::write(&path, QUAD_AND_TRIANGLE).unwrap();
+        let parsed = Hgrid::load_cached(&path).unwrap();
+        let sidecar = cache_path(&path);
+        assert_eq!(sidecar, temp_dir.path().join("hgrid.gr3.cache"));
+        assert_eq!(read_hash(&sidecar).unwrap(), parsed.calculate_hash());
+
+        // A stale layout version is ignored and replaced
+        let mut bytes = std::fs::read(&sidecar).unwrap();
+        bytes[8..12].copy_from_slice(&(CACHE_VERSION + 1).to_le_bytes());
+        std::fs::write(&sidecar, &bytes).unwrap();
+        let reloaded = Hgrid::load_cached(&path).unwrap();
+        assert_eq!(reloaded.calculate_hash(), parsed.calculate_hash());
+        assert!(read_hash(&sidecar).is_ok());
+    }
+
+    #[test]
+    fn test_load_cached_detects_edit_with_same_mtime() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.gr3");
+        std::fs::write(&path, QUAD_AND_TRIANGLE).unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        assert_eq!(Hgrid::load_cached(&path).unwrap().depths()[0], 5.0);
+
+        // Rewritten with a new depth but the old modification time, so the
+        // grid file is still older than its cache
+        std::fs::write(&path, QUAD_AND_TRIANGLE.replacen("1 0 0 5", "1 0 0 50", 1)).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(modified))
+            .unwrap();
+        assert_eq!(Hgrid::load_cached(&path).unwrap().depths()[0], 50.0);
+        assert_eq!(Hgrid::load_cached(&path).unwrap().depths()[0], 50.0);
+    }
+
+    #[test]
+    fn test_truncated_cache_is_rejected() {
+        let mut buffer = Vec::new();
+        write_cache(
+            &mut buffer,
+            &hgrid_from_gr3(QUAD_AND_TRIANGLE),
+            SourceStamp::default(),
+        )
+        .unwrap();
+        buffer.truncate(buffer.len() - 3);
+        assert!(matches!(
+            decode(&buffer, "test"),
+            Err(CacheError::FormatError(_, _))
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+//! Duplicate and near-coincident nodes.
+//!
+//! Stitching meshes together often leaves two nodes at (nearly) the same
+//! location, one on each side of the seam. Nodes are bucketed on a uniform
+//! grid with cells as wide as the tolerance, so only the neighbouring cells
+//! of each node are searched. Tolerances are in metres: geographic nodes are
+//! bucketed by their position on a sphere of the Earth's radius, in three
+//! dimensions, which keeps cells the same size near the poles and across the
+//! antimeridian, and compared with the haversine distance.
+
+use crate::Hgrid;
+use ndarray::ArrayView2;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Mean Earth radius in metres, used for geographic distances
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+impl Hgrid {
+    /// Groups of node ids lying within `tolerance` metres of each other.
+    ///
+    /// Groups are chained, so a node within the tolerance of any member joins
+    /// the group. Ids within a group and the groups themselves are in node
+    /// order.
+    pub fn find_coincident_nodes(&self, tolerance: f64) -> Vec<Vec<u32>> {
+        let ids = self.nodes().ids();
+        coincident_groups(self.nodes().xy(), tolerance, self.is_geographic())
+            .into_iter()
+            .map(|group| group.into_iter().map(|index| ids[index]).collect())
+            .collect()
+    }
+
+    /// Copy of the grid with each group of coincident nodes merged into its
+    /// first node.
+    ///
+    /// Elements and boundaries are rewritten to use the remaining node.
+    /// Elements left with a repeated vertex are dropped, as are boundary
+    /// segments left with fewer than two nodes.
+    pub fn merge_coincident_nodes(&self, tolerance: f64) -> Hgrid {
+        let groups = self.find_coincident_nodes(tolerance);
+        if groups.is_empty() {
+            return self.clone();
+        }
+        let node_map: HashMap<u32, u32> = groups
+            .iter()
+            .flat_map(|group| group[1..].iter().map(|&node_id| (node_id, group[0])))
+            .collect();
+        let nodes = self.nodes();
+        let positions: Vec<usize> = (0..nodes.len())
+            .filter(|&index| !node_map.contains_key(&nodes.ids()[index]))
+            .collect();
+        let nodes = Arc::new(nodes.select(&positions));
+        self.with_mesh(
+            nodes.clone(),
+            self.elements().remapped(&node_map, nodes.clone()),
+            self.boundaries()
+                .map(|boundaries| boundaries.remapped(&node_map, nodes)),
+        )
+    }
+}
+
+/// Groups of node positions within `tolerance` metres of each other, with
+/// more than one member each.
+pub(crate) fn coincident_groups(
+    xy: ArrayView2<'_, f64>,
+    tolerance: f64,
+    geographic: bool,
+) -> Vec<Vec<usize>> {
+    let n = xy.nrows();
+    let point = |index: usize| (xy[[index, 0]], xy[[index, 1]]);
+    // The chord between two nodes is never longer than the arc, so nodes
+    // within the tolerance are in neighbouring cells
+    let cartesian = |(x, y): (f64, f64)| {
+        if geographic {
+            let (lon, lat) = (x.to_radians(), y.to_radians());
+            (
+                EARTH_RADIUS * lat.cos() * lon.cos(),
+                EARTH_RADIUS * lat.cos() * lon.sin(),
+                EARTH_RADIUS * lat.sin(),
+            )
+        } else {
+            (x, y, 0.0)
+        }
+    };
+    let distance = |a: (f64, f64), b: (f64, f64)| {
+        if geographic {
+            haversine(a, b)
+        } else {
+            (a.0 - b.0).hypot(a.1 - b.1)
+        }
+    };
+    // Exact duplicates only for a zero tolerance, any cell size will do
+    let cell = if tolerance > 0.0 { tolerance } else { 1.0 };
+
+    let mut parent: Vec<usize> = (0..n).collect();
+    let layers = if geographic { -1..=1 } else { 0..=0 };
+    let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    for index in 0..n {
+        let (px, py, pz) = cartesian(point(index));
+        let [cx, cy, cz] = [px, py, pz].map(|p| (p / cell).floor() as i64);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in layers.clone() {
+                    let Some(others) = grid.get(&(cx + dx, cy + dy, cz + dz)) else {
+                        continue;
+                    };
+                    for &other in others {
+                        if distance(point(index), point(other)) <= tolerance {
+                            let (a, b) = (find(&mut parent, index), find(&mut parent, other));
+                            parent[a.max(b)] = a.min(b);
+                        }
+                    }
+                }
+            }
+        }
+        grid.entry((cx, cy, cz)).or_default().push(index);
+    }
+
+    // Roots are the smallest position of their group
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..n {
+        let root = find(&mut parent, index);
+        if root != index {
+            groups.entry(root).or_insert_with(|| vec![root]).push(index);
+        }
+    }
+    let mut groups: Vec<Vec<usize>> = groups.into_values().collect();
+    groups.sort_unstable_by_key(|group| group[0]);
+    groups
+}
+
+/// Root of `index` in a union-find forest, compressing the path on the way.
+fn find(parent: &mut [usize], mut index: usize) -> usize {
+    while parent[index] != index {
+        parent[index] = parent[parent[index]];
+        index = parent[index];
+    }
+    index
+}
+
+/// Great-circle distance in metres between two lon/lat points in degrees.
+fn haversine((lon1, lat1): (f64, f64), (lon2, lat2): (f64, f64)) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let dphi = phi2 - phi1;
+    let dlambda = (lon2 - lon1).to_radians();
+    let h = (dphi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (dlambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::hgrid_from_gr3;
+
+    /// Element 2 uses node 5, a copy of node 2, and element 3 collapses once
+    /// the copy is merged.
+    const GR3: &str = "stitched
+3 6
+1 1000 1000 1
+2 1001 1000 2
+3 1000 1001 3
+4 1001 1001 4
+5 1001.0000001 1000 5
+6 1001.5 1000.5 6
+1 3 1 2 3
+2 3 5 4 3
+3 3 2 6 5
+1 = Number of open boundaries
+4 = Total number of open boundary nodes
+4 = Number of nodes for open boundary 1
+1
+2
+5
+4
+1 = number of land boundaries
+2 = Total number of land boundary nodes
+2 0 = Number of nodes for land boundary 1
+2
+5
+";
+
+    #[test]
+    fn test_find_coincident_nodes() {
+        let hgrid = hgrid_from_gr3(GR3);
+        assert!(!hgrid.is_geographic());
+        assert_eq!(hgrid.find_coincident_nodes(1e-3), vec![vec![2, 5]]);
+        assert_eq!(hgrid.find_coincident_nodes(0.0), Vec::<Vec<u32>>::new());
+        assert_eq!(hgrid.find_coincident_nodes(1.0).len(), 1);
+        assert_eq!(hgrid.find_coincident_nodes(1.0)[0].len(), 6);
+
+        let validation = hgrid.check_validity();
+        assert_eq!(validation.coincident_nodes, vec![vec![2, 5]]);
+        assert!(!validation.is_geometrically_valid());
+        assert!(validation
+            .to_string()
+            .contains("1 groups of coincident nodes"));
+    }
+
+    #[test]
+    fn test_find_coincident_nodes_geographic() {
+        // Node 2 is about 7 cm east of node 1, node 3 about 1.4 m
+        let hgrid = hgrid_from_gr3(
+            "geographic\n1 4\n1 10 50 1\n2 10.000001 50 1\n3 10.00002 50 1\n4 10 50.1 1\n\
+             1 3 1 3 4\n",
+        );
+        assert!(hgrid.is_geographic());
+        assert_eq!(hgrid.find_coincident_nodes(0.1), vec![vec![1, 2]]);
+        assert_eq!(hgrid.find_coincident_nodes(2.0), vec![vec![1, 2, 3]]);
+        assert!((haversine((0.0, 0.0), (0.0, 1.0)) - 111_194.9).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_find_coincident_nodes_near_antimeridian() {
+        // Nodes 1 and 2 are 0.89 m apart, on the same meridian
+        let hgrid = hgrid_from_gr3(
+            "antimeridian\n1 3\n1 179 60 1\n2 179 60.000008 1\n3 179.5 60.5 1\n\
+             1 3 1 3 2\n",
+        );
+        assert!(hgrid.is_geographic());
+        assert_eq!(hgrid.find_coincident_nodes(1.0), vec![vec![1, 2]]);
+        assert!(hgrid.find_coincident_nodes(0.5).is_empty());
+    }
+
+    #[test]
+    fn test_find_coincident_nodes_near_pole() {
+        // Nodes 1 and 2 are 0.89 m apart, on opposite sides of the antimeridian
+        let hgrid = hgrid_from_gr3(
+            "pole\n1 4\n1 179.977 89.99 1\n2 -179.977 89.99 1\n3 0 89.99 1\n\
+             4 90 90 1\n1 3 1 2 3\n",
+        );
+        assert_eq!(hgrid.find_coincident_nodes(1.0), vec![vec![1, 2]]);
+        assert!(hgrid.find_coincident_nodes(0.5).is_empty());
+        assert_eq!(hgrid.find_coincident_nodes(1200.0), vec![vec![1, 2, 3, 4]]);
+    }
+
+    #[test]
+    fn test_merge_coincident_nodes() {
+        let merged = hgrid_from_gr3(GR3).merge_coincident_nodes(1e-3);
+        assert_eq!(merged.nodes().ids(), &[1, 2, 3, 4, 6]);
+        assert_eq!(merged.depths().to_vec(), vec![1.0, 2.0, 3.0, 4.0, 6.0]);
+        assert_eq!(merged.elements().ids(), &[1, 2]);
+        assert_eq!(merged.elements().get(2), Some(&[2, 4, 3][..]));
+        assert!(std::ptr::eq(merged.elements().nodes(), merged.nodes()));
+        let boundaries = merged.boundaries().unwrap();
+        assert_eq!(boundaries.open().unwrap().nodes_ids(), &vec![vec![1, 2, 4]]);
+        assert!(boundaries.land().unwrap().nodes_ids().is_empty());
+        assert!(merged.find_coincident_nodes(1e-3).is_empty());
+        assert!(merged.topology().element_neighbors(0).contains(&Some(1)));
+    }
+}
@@ -0,0 +1,158 @@
+//! Connected components of a mesh.
+//!
+//! Two elements are connected when they share a side, so pieces that only
+//! touch at a node are separate components. SCHISM runs such meshes without
+//! complaint, but the pieces never exchange water.
+
+use crate::Hgrid;
+use std::sync::Arc;
+
+impl Hgrid {
+    /// Connected component of each element, in element order.
+    ///
+    /// Components are numbered from 0 in the order of their first element.
+    pub fn connected_components(&self) -> Vec<usize> {
+        let topology = self.topology();
+        let mut components = vec![usize::MAX; self.elements().len()];
+        let mut count = 0;
+        let mut stack = Vec::new();
+        for start in 0..components.len() {
+            if components[start] != usize::MAX {
+                continue;
+            }
+            components[start] = count;
+            stack.push(start);
+            while let Some(element) = stack.pop() {
+                for &neighbor in topology.element_neighbors(element).iter().flatten() {
+                    if components[neighbor] == usize::MAX {
+                        components[neighbor] = count;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            count += 1;
+        }
+        components
+    }
+
+    /// Copy of the grid reduced to its largest connected component.
+    ///
+    /// Ties go to the component met first. Nodes not used by the remaining
+    /// elements are dropped, along with boundary segments that touch them.
+    pub fn largest_component(&self) -> Hgrid {
+        let components = self.connected_components();
+        let Some(largest) = largest_component(&components) else {
+            return self.clone();
+        };
+        let elements: Vec<usize> = (0..components.len())
+            .filter(|&element| components[element] == largest)
+            .collect();
+        if elements.len() == components.len() {
+            return self.clone();
+        }
+
+        let nodes = self.nodes();
+        let mut used = vec![false; nodes.len()];
+        for (element, (_elem_id, node_ids)) in self.elements().iter().enumerate() {
+            if components[element] == largest {
+                for &node_id in node_ids {
+                    if let Some(index) = nodes.index_of(node_id) {
+                        used[index] = true;
+                    }
+                }
+            }
+        }
+        let positions: Vec<usize> = (0..nodes.len()).filter(|&index| used[index]).collect();
+        let nodes = Arc::new(nodes.select(&positions));
+        self.with_mesh(
+            nodes.clone(),
+            self.elements().select(&elements, nodes.clone()),
+            self.boundaries()
+                .map(|boundaries| boundaries.restricted_to(nodes)),
+        )
+    }
+}
+
+/// Id of the component with the most elements, the first one on ties.
+pub(crate) fn largest_component(components: &[usize]) -> Option<usize> {
+    let count = components.iter().max()? + 1;
+    let mut sizes = vec![0usize; count];
+    for &component in components {
+        sizes[component] += 1;
+    }
+    // max_by_key keeps the last maximum, so compare on reversed ids
+    (0..count).max_by_key(|&component| (sizes[component], std::cmp::Reverse(component)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::hgrid_from_gr3;
+
+    /// Two triangles forming the main mesh, a separate triangle and a triangle
+    /// touching the main mesh at node 4 only.
+    const GR3: &str = "components
+4 9
+1 0 0 1
+2 1 0 2
+3 0 1 3
+4 1 1 4
+5 5 5 5
+6 6 5 6
+7 5 6 7
+8 2 1 8
+9 2 2 9
+1 3 1 2 3
+2 3 2 4 3
+3 3 5 6 7
+4 3 4 8 9
+1 = Number of open boundaries
+2 = Total number of open boundary nodes
+2 = Number of nodes for open boundary 1
+1
+2
+2 = number of land boundaries
+5 = Total number of land boundary nodes
+3 0 = Number of nodes for land boundary 1
+2
+4
+3
+2 0 = Number of nodes for land boundary 2
+5
+6
+";
+
+    #[test]
+    fn test_connected_components() {
+        let hgrid = hgrid_from_gr3(GR3);
+        assert_eq!(hgrid.connected_components(), vec![0, 0, 1, 2]);
+        assert_eq!(largest_component(&[0, 1, 1, 0]), Some(0));
+        assert_eq!(largest_component(&[]), None);
+    }
+
+    #[test]
+    fn test_largest_component() {
+        let hgrid = hgrid_from_gr3(GR3);
+        let main = hgrid.largest_component();
+        assert_eq!(main.elements().ids(), &[1, 2]);
+        assert_eq!(main.nodes().ids(), &[1, 2, 3, 4]);
+        assert_eq!(main.depths().to_vec(), vec![1.0, 2.0, 3.0, 4.0]);
+        assert!(std::ptr::eq(main.elements().nodes(), main.nodes()));
+        let boundaries = main.boundaries().unwrap();
+        assert_eq!(boundaries.open().unwrap().nodes_ids(), &vec![vec![1, 2]]);
+        assert_eq!(boundaries.land().unwrap().nodes_ids(), &vec![vec![2, 4, 3]]);
+        assert_eq!(main.connected_components(), vec![0, 0]);
+        assert!(main.check_validity().is_topologically_valid());
+    }
+
+    #[test]
+    fn test_disconnected_components_are_reported() {
+        let validation = hgrid_from_gr3(GR3).check_validity();
+        assert_eq!(validation.disconnected_components, vec![vec![3], vec![4]]);
+        assert!(!validation.is_ok());
+        assert!(validation.is_structurally_valid());
+        assert!(validation
+            .to_string()
+            .contains("2 components disconnected from the main mesh"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Transparent compression for mesh files
+//!
+//! Compressed input is detected from the leading magic bytes (falling back to the
+//! file extension), and compressed output is chosen by extension. Both directions
+//! stream through the codec rather than holding the whole file in memory.
+
+use bzip2::read::BzDecoder;
+use bzip2::write::BzEncoder;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+/// Followed by the block size, a digit from `1` to `9`
+const BZIP2_MAGIC: &[u8] = b"BZh";
+
+/// Compression codec of a mesh file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Codec implied by the file extension (`.gz`, `.zst`, `.bz2`)
+    pub fn from_extension(path: &Path) -> Compression {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("gz") | Some("gzip") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            Some("bz2") => Compression::Bzip2,
+            _ => Compression::None,
+        }
+    }
+
+    /// Codec identified by the leading bytes of a file, if any
+    pub fn from_magic_bytes(bytes: &[u8]) -> Option<Compression> {
+        if bytes.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if bytes.starts_with(ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else if bytes.starts_with(BZIP2_MAGIC) && matches!(bytes.get(3), Some(b'1'..=b'9')) {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+}
+
+/// The path without its compression extension (`hgrid.gr3.gz` -> `hgrid.gr3`),
+/// for callers that pick a format from the remaining extension.
+pub fn strip_compression_extension(path: &Path) -> PathBuf {
+    match Compression::from_extension(path) {
+        Compression::None => path.to_path_buf(),
+        _ => path.with_extension(""),
+    }
+}
+
+/// Open a file for reading, decompressing on the fly if needed.
+pub fn open_reader(path: &Path) -> io::Result<BufReader<Box<dyn Read>>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let compression = Compression::from_magic_bytes(file.fill_buf()?)
+        .unwrap_or_else(|| Compression::from_extension(path));
+    let reader: Box<dyn Read> = match compression {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        Compression::Bzip2 => Box::new(BzDecoder::new(file)),
+    };
+    Ok(BufReader::new(reader))
+}
+
+/// Streaming writer that compresses with the given codec.
+///
+/// Call [`CompressedWriter::finish`] to flush the codec's trailer; dropping the
+/// writer without finishing may leave a truncated stream.
+pub enum CompressedWriter<W: Write> {
+    None(BufWriter<W>),
+    Gzip(GzEncoder<BufWriter<W>>),
+    Zstd(zstd::stream::write::Encoder<'static, BufWriter<W>>),
+    Bzip2(BzEncoder<BufWriter<W>>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub fn new(inner: W, compression: Compression) -> io::Result<Self> {
+        let inner = BufWriter::new(inner);
+        Ok(match compression {
+            Compression::None => CompressedWriter::None(inner),
+            Compression::Gzip => {
+                CompressedWriter::Gzip(GzEncoder::new(inner, flate2::Compression::default()))
+            }
+            Compression::Zstd => {
+                CompressedWriter::Zstd(zstd::stream::write::Encoder::new(inner, 0)?)
+            }
+            Compression::Bzip2 => {
+                CompressedWriter::Bzip2(BzEncoder::new(inner, bzip2::Compression::default()))
+            }
+        })
+    }
+
+    /// Write the codec trailer and flush everything to the inner writer.
+    pub fn finish(self) -> io::Result<()> {
+        let mut inner = match self {
+            CompressedWriter::None(inner) => inner,
+            CompressedWriter::Gzip(encoder) => encoder.finish()?,
+            CompressedWriter::Zstd(encoder) => encoder.finish()?,
+            CompressedWriter::Bzip2(encoder) => encoder.finish()?,
+        };
+        inner.flush()
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressedWriter::None(writer) => writer.write(buf),
+            CompressedWriter::Gzip(writer) => writer.write(buf),
+            CompressedWriter::Zstd(writer) => writer.write(buf),
+            CompressedWriter::Bzip2(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressedWriter::None(writer) => writer.flush(),
+            CompressedWriter::Gzip(writer) => writer.flush(),
+            CompressedWriter::Zstd(writer) => writer.flush(),
+            CompressedWriter::Bzip2(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gr3::{parse_from_path_ref, parse_from_reader};
+    use crate::Hgrid;
+    use tempfile::tempdir;
+
+    const GR3: &str = "test mesh
+2 4
+1 0 0 5
+2 1 0 6
+3 1 1 7
+4 0 1 8
+1 3 1 2 3
+2 3 1 3 4
+0 ! total number of open boundaries
+0 ! total number of open boundary nodes
+0 ! total number of non-ocean boundaries
+0 ! total number of non-ocean boundaries nodes";
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let gr3 = parse_from_reader(BufReader::new(GR3.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+        let temp_dir = tempdir().unwrap();
+        for (file_name, compression) in [
+            ("hgrid.gr3.gz", Compression::Gzip),
+            ("hgrid.gr3.zst", Compression::Zstd),
+            ("hgrid.gr3.bz2", Compression::Bzip2),
+        ] {
+            let path = temp_dir.path().join(file_name);
+            hgrid.write(&path).unwrap();
+
+            let mut magic = [0u8; 4];
+            File::open(&path).unwrap().read_exact(&mut magic).unwrap();
+            assert_eq!(Compression::from_magic_bytes(&magic), Some(compression));
+
+            let reloaded = Hgrid::try_from(&path).unwrap();
+            assert_eq!(reloaded.calculate_hash(), hgrid.calculate_hash());
+        }
+    }
+
+    #[test]
+    fn test_magic_bytes_win_over_extension() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.gr3");
+        let mut writer =
+            CompressedWriter::new(File::create(&path).unwrap(), Compression::Zstd).unwrap();
+        write!(writer, "{}", GR3).unwrap();
+        writer.finish().unwrap();
+
+        let parsed = parse_from_path_ref(&path).unwrap();
+        assert_eq!(parsed.nodes().len(), 4);
+    }
+
+    #[test]
+    fn test_plain_file_starting_with_bzh() {
+        assert_eq!(
+            Compression::from_magic_bytes(b"BZh9"),
+            Some(Compression::Bzip2)
+        );
+        assert_eq!(Compression::from_magic_bytes(b"BZh mesh"), None);
+        assert_eq!(Compression::from_magic_bytes(b"BZh"), None);
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.gr3");
+        std::fs::write(&path, GR3.replacen("test", "BZh", 1)).unwrap();
+        let parsed = parse_from_path_ref(&path).unwrap();
+        assert_eq!(parsed.description().as_deref(), Some("BZh mesh"));
+    }
+
+    #[test]
+    fn test_strip_compression_extension() {
+        assert_eq!(
+            strip_compression_extension(Path::new("mesh.2dm.gz")),
+            PathBuf::from("mesh.2dm")
+        );
+        assert_eq!(
+            strip_compression_extension(Path::new("hgrid.gr3")),
+            PathBuf::from("hgrid.gr3")
+        );
+    }
+}
@@ -1,26 +1,83 @@
 use super::nodes::Nodes;
 use derive_builder::Builder;
 use linked_hash_map::LinkedHashMap;
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis};
+#[cfg(feature = "serde")]
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::Arc;
+use thiserror::Error;
+
+/// Fill value for the unused fourth vertex slot of triangles.
+pub const ELEMENT_PADDING: u32 = u32::MAX;
 
 /// Element connectivity data for an unstructured mesh.
 ///
+/// Connectivity is stored as an `(n, 4)` array of node ids, padded with
+/// [`ELEMENT_PADDING`] for triangles, plus a per-element vertex count and a side
+/// table from element id to row index.
+///
 /// Only data format validation (element sizes) is performed during construction.
 /// Full structural and geometric validation is available via `Hgrid::check_validity()`.
 #[derive(Builder, Debug, Clone)]
-#[builder(build_fn(validate = "Self::validate"))]
+#[builder(build_fn(private, name = "build_unindexed", validate = "Self::validate"))]
 pub struct Elements {
-    hash_map: LinkedHashMap<u32, Vec<u32>>,
+    /// Element ids in element order
+    ids: Vec<u32>,
+    #[builder(setter(skip))]
+    index_from_id: HashMap<u32, usize>,
+    /// Node ids of each element, shaped (number of elements, 4)
+    connectivity: Array2<u32>,
+    /// Number of vertices (3 or 4) of each element
+    vertex_counts: Vec<u8>,
     nodes: Arc<Nodes>,
+    /// Named per-element values, one per element in element order
+    #[builder(default)]
+    attributes: LinkedHashMap<String, Array1<f64>>,
+}
+
+#[derive(Error, Debug)]
+pub enum ElementAttributeError {
+    #[error("Attribute {0} has {1} values but the mesh has {2} elements")]
+    LengthMismatch(String, usize, usize),
 }
 
 impl ElementsBuilder {
+    /// Fill ids, connectivity and vertex counts from the parser's element map.
+    pub fn hash_map(&mut self, value: LinkedHashMap<u32, Vec<u32>>) -> &mut Self {
+        let mut ids = Vec::with_capacity(value.len());
+        let mut connectivity = Vec::with_capacity(value.len() * 4);
+        let mut vertex_counts = Vec::with_capacity(value.len());
+        for (&elem_id, nodes) in value.iter() {
+            ids.push(elem_id);
+            connectivity.extend((0..4).map(|i| nodes.get(i).copied().unwrap_or(ELEMENT_PADDING)));
+            vertex_counts.push(nodes.len().min(u8::MAX as usize) as u8);
+        }
+        self.connectivity = Some(Array2::from_shape_vec((ids.len(), 4), connectivity).unwrap());
+        self.vertex_counts = Some(vertex_counts);
+        self.ids = Some(ids);
+        self
+    }
+
+    pub fn build(&self) -> Result<Elements, ElementsBuilderError> {
+        let mut elements = self.build_unindexed()?;
+        if !elements.connectivity.is_standard_layout() {
+            elements.connectivity = elements.connectivity.as_standard_layout().into_owned();
+        }
+        elements.index_from_id = elements
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(index, &elem_id)| (elem_id, index))
+            .collect();
+        Ok(elements)
+    }
+
     /// Validates that all elements have exactly 3 or 4 nodes (triangles or quads).
     /// This is a fast data format check - structural/geometric validation is separate.
     fn validate(&self) -> Result<(), ElementsBuilderError> {
-        if let Some(hash_map) = &self.hash_map {
-            for (elem_id, nodes) in hash_map.iter() {
-                let len = nodes.len();
+        if let (Some(ids), Some(vertex_counts)) = (&self.ids, &self.vertex_counts) {
+            for (elem_id, &len) in ids.iter().zip(vertex_counts) {
                 if len != 3 && len != 4 {
                     return Err(ElementsBuilderError::ValidationError(format!(
                         "Element {} has {} nodes (expected 3 or 4)",
@@ -28,18 +85,210 @@ impl ElementsBuilder {
                     )));
                 }
             }
+            if vertex_counts.len() != ids.len() {
+                return Err(ElementsBuilderError::ValidationError(format!(
+                    "Got {} vertex counts for {} elements",
+                    vertex_counts.len(),
+                    ids.len()
+                )));
+            }
+        }
+        if let (Some(ids), Some(connectivity)) = (&self.ids, &self.connectivity) {
+            if connectivity.dim() != (ids.len(), 4) {
+                return Err(ElementsBuilderError::ValidationError(format!(
+                    "Connectivity has shape {:?} but expected ({}, 4)",
+                    connectivity.dim(),
+                    ids.len()
+                )));
+            }
+        }
+        if let (Some(ids), Some(attributes)) = (&self.ids, &self.attributes) {
+            for (name, values) in attributes.iter() {
+                if values.len() != ids.len() {
+                    return Err(ElementsBuilderError::ValidationError(
+                        ElementAttributeError::LengthMismatch(
+                            name.clone(),
+                            values.len(),
+                            ids.len(),
+                        )
+                        .to_string(),
+                    ));
+                }
+            }
         }
         Ok(())
     }
 }
 
 impl Elements {
-    pub fn hash_map(&self) -> &LinkedHashMap<u32, Vec<u32>> {
-        &self.hash_map
+    /// Element map in the parser's layout.
+    ///
+    /// Built on demand, so prefer `iter()` or `connectivity()` in hot paths.
+    pub fn hash_map(&self) -> LinkedHashMap<u32, Vec<u32>> {
+        self.iter()
+            .map(|(elem_id, nodes)| (elem_id, nodes.to_vec()))
+            .collect()
     }
+
     pub fn nodes(&self) -> &Nodes {
         &self.nodes
     }
+
+    /// Element ids in element order
+    pub fn ids(&self) -> &[u32] {
+        &self.ids
+    }
+
+    /// Padded connectivity array, shaped (number of elements, 4)
+    pub fn connectivity(&self) -> ArrayView2<'_, u32> {
+        self.connectivity.view()
+    }
+
+    /// Number of vertices of each element
+    pub fn vertex_counts(&self) -> &[u8] {
+        &self.vertex_counts
+    }
+
+    /// Row index of an element id in the connectivity array
+    pub fn index_of(&self, elem_id: u32) -> Option<usize> {
+        self.index_from_id.get(&elem_id).copied()
+    }
+
+    /// Node ids of an element, without padding
+    pub fn get(&self, elem_id: u32) -> Option<&[u32]> {
+        self.index_of(elem_id).map(|index| self.row(index))
+    }
+
+    /// Iterate over (element id, node ids) in element order
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &[u32])> + '_ {
+        self.ids
+            .iter()
+            .enumerate()
+            .map(|(index, &elem_id)| (elem_id, self.row(index)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Centroid (vertex average) of each element, shaped (number of elements, 2)
+    pub fn centroids(&self) -> Array2<f64> {
+        let mut centroids = Array2::zeros((self.len(), 2));
+        for (index, (_elem_id, node_ids)) in self.iter().enumerate() {
+            for node_id in node_ids {
+                // Elements referencing missing nodes are caught by check_validity()
+                let (x, y) = self
+                    .nodes
+                    .get_node(*node_id)
+                    .unwrap_or((f64::NAN, f64::NAN));
+                centroids[[index, 0]] += x;
+                centroids[[index, 1]] += y;
+            }
+            centroids
+                .row_mut(index)
+                .mapv_inplace(|v| v / node_ids.len() as f64);
+        }
+        centroids
+    }
+
+    /// Names of the per-element attributes
+    pub fn attribute_names(&self) -> Vec<&str> {
+        self.attributes.keys().map(String::as_str).collect()
+    }
+
+    /// Get a named per-element attribute
+    pub fn attribute(&self, name: &str) -> Option<ArrayView1<'_, f64>> {
+        self.attributes.get(name).map(Array1::view)
+    }
+
+    /// Add (or replace) a named per-element attribute, returning the previous one.
+    pub fn add_attribute(
+        &mut self,
+        name: &str,
+        values: Array1<f64>,
+    ) -> Result<Option<Array1<f64>>, ElementAttributeError> {
+        if values.len() != self.len() {
+            return Err(ElementAttributeError::LengthMismatch(
+                name.to_string(),
+                values.len(),
+                self.len(),
+            ));
+        }
+        Ok(self.attributes.insert(name.to_string(), values))
+    }
+
+    /// Remove a named per-element attribute, returning it if it was defined.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<Array1<f64>> {
+        self.attributes.remove(name)
+    }
+
+    fn row(&self, index: usize) -> &[u32] {
+        let flat = self
+            .connectivity
+            .as_slice()
+            .expect("connectivity is in standard layout");
+        &flat[index * 4..index * 4 + self.vertex_counts[index] as usize]
+    }
+
+    /// Copy of the elements at the given positions, in that order, pointing at
+    /// a new set of nodes
+    pub(crate) fn select(&self, positions: &[usize], nodes: Arc<Nodes>) -> Elements {
+        let ids: Vec<u32> = positions.iter().map(|&index| self.ids[index]).collect();
+        Elements {
+            index_from_id: ids
+                .iter()
+                .enumerate()
+                .map(|(index, &elem_id)| (elem_id, index))
+                .collect(),
+            ids,
+            connectivity: self.connectivity.select(Axis(0), positions),
+            vertex_counts: positions
+                .iter()
+                .map(|&index| self.vertex_counts[index])
+                .collect(),
+            nodes,
+            attributes: self
+                .attributes
+                .iter()
+                .map(|(name, values)| (name.clone(), values.select(Axis(0), positions)))
+                .collect(),
+        }
+    }
+
+    /// Clone the connectivity, pointing it at a new set of nodes
+    pub(crate) fn with_nodes(&self, nodes: Arc<Nodes>) -> Elements {
+        Elements {
+            ids: self.ids.clone(),
+            index_from_id: self.index_from_id.clone(),
+            connectivity: self.connectivity.clone(),
+            vertex_counts: self.vertex_counts.clone(),
+            nodes,
+            attributes: self.attributes.clone(),
+        }
+    }
+
+    /// Copy of the elements with node ids replaced through `node_map`, pointing
+    /// at a new set of nodes. Elements left with a repeated vertex are dropped.
+    pub(crate) fn remapped(&self, node_map: &HashMap<u32, u32>, nodes: Arc<Nodes>) -> Elements {
+        let mut elements = self.with_nodes(nodes.clone());
+        elements
+            .connectivity
+            .mapv_inplace(|node_id| node_map.get(&node_id).copied().unwrap_or(node_id));
+        let positions: Vec<usize> = (0..elements.len())
+            .filter(|&index| {
+                let row = elements.row(index);
+                (1..row.len()).all(|i| !row[..i].contains(&row[i]))
+            })
+            .collect();
+        if positions.len() == elements.len() {
+            return elements;
+        }
+        elements.select(&positions, nodes)
+    }
 }
 
 // #[derive(Error, Debug, Clone)]
@@ -50,3 +299,148 @@ impl Elements {
 //     #[error("Invalid number of nodes in an element")]
 //     InvalidElementSize,
 // }
+
+/// Serialized layout of [`Elements`].
+///
+/// A standalone `Elements` carries its nodes; inside an `Hgrid` they are
+/// omitted and re-linked to the grid's shared nodes.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct ElementsRepr<'a> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nodes: Option<Cow<'a, Nodes>>,
+    ids: Cow<'a, [u32]>,
+    connectivity: Cow<'a, Array2<u32>>,
+    vertex_counts: Cow<'a, [u8]>,
+    attributes: Cow<'a, LinkedHashMap<String, Array1<f64>>>,
+}
+
+#[cfg(feature = "serde")]
+impl Elements {
+    pub(crate) fn to_repr(&self, with_nodes: bool) -> ElementsRepr<'_> {
+        ElementsRepr {
+            nodes: with_nodes.then(|| Cow::Borrowed(self.nodes.as_ref())),
+            ids: Cow::Borrowed(&self.ids),
+            connectivity: Cow::Borrowed(&self.connectivity),
+            vertex_counts: Cow::Borrowed(&self.vertex_counts),
+            attributes: Cow::Borrowed(&self.attributes),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ElementsRepr<'_> {
+    pub(crate) fn into_elements(self, nodes: Arc<Nodes>) -> Result<Elements, ElementsBuilderError> {
+        ElementsBuilder::default()
+            .ids(self.ids.into_owned())
+            .connectivity(self.connectivity.into_owned())
+            .vertex_counts(self.vertex_counts.into_owned())
+            .nodes(nodes)
+            .attributes(self.attributes.into_owned())
+            .build()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Elements {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_repr(true).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Elements {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut repr = ElementsRepr::deserialize(deserializer)?;
+        let nodes = repr
+            .nodes
+            .take()
+            .ok_or_else(|| serde::de::Error::missing_field("nodes"))?;
+        repr.into_elements(Arc::new(nodes.into_owned()))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::NodesBuilder;
+
+    #[test]
+    fn test_padded_connectivity() {
+        let mut nodes_hash_map = LinkedHashMap::new();
+        for i in 1..=5 {
+            nodes_hash_map.insert(i, (vec![i as f64, 0.0], None));
+        }
+        let nodes = NodesBuilder::default()
+            .hash_map(nodes_hash_map)
+            .build()
+            .map(Arc::new)
+            .unwrap();
+
+        let mut hash_map = LinkedHashMap::new();
+        hash_map.insert(7, vec![1, 2, 3]);
+        hash_map.insert(9, vec![2, 4, 5, 3]);
+        let elements = ElementsBuilder::default()
+            .nodes(nodes)
+            .hash_map(hash_map.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(elements.len(), 2);
+        assert_eq!(elements.vertex_counts(), &[3, 4]);
+        assert_eq!(
+            elements.connectivity().row(0).to_vec(),
+            vec![1, 2, 3, ELEMENT_PADDING]
+        );
+        assert_eq!(elements.get(9), Some(&[2, 4, 5, 3][..]));
+        assert_eq!(elements.index_of(7), Some(0));
+        assert_eq!(elements.hash_map(), hash_map);
+    }
+
+    #[test]
+    fn test_element_attributes() {
+        let mut nodes_hash_map = LinkedHashMap::new();
+        nodes_hash_map.insert(1, (vec![0.0, 0.0], None));
+        nodes_hash_map.insert(2, (vec![3.0, 0.0], None));
+        nodes_hash_map.insert(3, (vec![0.0, 3.0], None));
+        let nodes = NodesBuilder::default()
+            .hash_map(nodes_hash_map)
+            .build()
+            .map(Arc::new)
+            .unwrap();
+        let mut hash_map = LinkedHashMap::new();
+        hash_map.insert(1, vec![1, 2, 3]);
+        let mut elements = ElementsBuilder::default()
+            .nodes(nodes)
+            .hash_map(hash_map)
+            .build()
+            .unwrap();
+
+        assert_eq!(elements.centroids(), ndarray::array![[1.0, 1.0]]);
+        assert!(elements
+            .add_attribute("tvd", ndarray::array![1.0, 0.0])
+            .is_err());
+        elements.add_attribute("tvd", ndarray::array![1.0]).unwrap();
+        assert_eq!(elements.attribute_names(), vec!["tvd"]);
+        assert_eq!(elements.attribute("tvd").unwrap()[0], 1.0);
+        assert!(elements.remove_attribute("tvd").is_some());
+        assert!(elements.attribute("tvd").is_none());
+    }
+
+    #[test]
+    fn test_invalid_element_size_is_rejected() {
+        let nodes = NodesBuilder::default()
+            .hash_map(LinkedHashMap::new())
+            .build()
+            .map(Arc::new)
+            .unwrap();
+        let mut hash_map = LinkedHashMap::new();
+        hash_map.insert(1, vec![1, 2]);
+        let result = ElementsBuilder::default()
+            .nodes(nodes)
+            .hash_map(hash_map)
+            .build();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! GeoJSON and WKT export of the mesh outline, boundaries and elements
+//!
+//! Features are built in the grid's own coordinates. GeoJSON output carries the
+//! CRS as a named `crs` member when it is not WGS84 (reproject with
+//! [`Hgrid::to_lonlat`] first for strict RFC 7946 output), and WKT output is a
+//! CSV with a `WKT` geometry column and a `crs` column, readable by GDAL/QGIS.
+
+use crate::boundaries::BoundaryType;
+use crate::hgrid::is_wgs84;
+use crate::Hgrid;
+use linked_hash_map::LinkedHashMap;
+use std::fmt::Write as _;
+use std::io::prelude::*;
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Planar geometry of a mesh feature; polygon rings are explicitly closed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Geometry {
+    LineString(Vec<(f64, f64)>),
+    Polygon(Vec<Vec<(f64, f64)>>),
+    MultiPolygon(Vec<Vec<Vec<(f64, f64)>>>),
+}
+
+fn join_coordinates(
+    coordinates: &[(f64, f64)],
+    format_point: impl Fn(&(f64, f64)) -> String,
+) -> String {
+    coordinates
+        .iter()
+        .map(format_point)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+impl Geometry {
+    pub fn to_wkt(&self) -> String {
+        let ring = |coordinates: &Vec<(f64, f64)>| {
+            format!(
+                "({})",
+                join_coordinates(coordinates, |(x, y)| format!("{} {}", x, y))
+            )
+        };
+        let polygon = |rings: &Vec<Vec<(f64, f64)>>| {
+            format!("({})", rings.iter().map(ring).collect::<Vec<_>>().join(","))
+        };
+        match self {
+            Geometry::LineString(coordinates) => format!("LINESTRING {}", ring(coordinates)),
+            Geometry::Polygon(rings) => format!("POLYGON {}", polygon(rings)),
+            Geometry::MultiPolygon(polygons) => format!(
+                "MULTIPOLYGON ({})",
+                polygons.iter().map(polygon).collect::<Vec<_>>().join(",")
+            ),
+        }
+    }
+
+    pub fn to_geojson(&self) -> String {
+        let ring = |coordinates: &Vec<(f64, f64)>| {
+            format!(
+                "[{}]",
+                join_coordinates(coordinates, |(x, y)| format!("[{},{}]", x, y))
+            )
+        };
+        let polygon = |rings: &Vec<Vec<(f64, f64)>>| {
+            format!("[{}]", rings.iter().map(ring).collect::<Vec<_>>().join(","))
+        };
+        let (geometry_type, coordinates) = match self {
+            Geometry::LineString(coordinates) => ("LineString", ring(coordinates)),
+            Geometry::Polygon(rings) => ("Polygon", polygon(rings)),
+            Geometry::MultiPolygon(polygons) => (
+                "MultiPolygon",
+                format!(
+                    "[{}]",
+                    polygons.iter().map(polygon).collect::<Vec<_>>().join(",")
+                ),
+            ),
+        };
+        format!(
+            "{{\"type\":\"{}\",\"coordinates\":{}}}",
+            geometry_type, coordinates
+        )
+    }
+}
+
+/// Property value of a mesh feature
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Text(String),
+    Integer(i64),
+}
+
+impl PropertyValue {
+    fn to_geojson(&self) -> String {
+        match self {
+            PropertyValue::Text(text) => json_string(text),
+            PropertyValue::Integer(value) => value.to_string(),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        match self {
+            PropertyValue::Text(text) => csv_field(text),
+            PropertyValue::Integer(value) => value.to_string(),
+        }
+    }
+}
+
+/// A geometry with its properties
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feature {
+    pub geometry: Geometry,
+    pub properties: LinkedHashMap<String, PropertyValue>,
+}
+
+impl Feature {
+    fn new(geometry: Geometry, properties: &[(&str, PropertyValue)]) -> Feature {
+        Feature {
+            geometry,
+            properties: properties
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn to_geojson(&self) -> String {
+        let properties = self
+            .properties
+            .iter()
+            .map(|(name, value)| format!("{}:{}", json_string(name), value.to_geojson()))
+            .collect::<Vec<String>>()
+            .join(",");
+        format!(
+            "{{\"type\":\"Feature\",\"properties\":{{{}}},\"geometry\":{}}}",
+            properties,
+            self.geometry.to_geojson()
+        )
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn csv_field(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+fn boundary_type_name(boundary_type: &BoundaryType) -> &'static str {
+    match boundary_type {
+        BoundaryType::Open => "open",
+        BoundaryType::Land => "land",
+        BoundaryType::Interior => "island",
+        BoundaryType::Flow => "flow",
+        BoundaryType::ExternalBarrier => "external_barrier",
+        BoundaryType::InternalBarrier => "internal_barrier",
+        BoundaryType::Culvert => "culvert",
+    }
+}
+
+impl Hgrid {
+    fn ring_coordinates(&self, node_ids: &[u32], is_closed: bool) -> Vec<(f64, f64)> {
+        let nodes = self.nodes();
+        let mut coordinates: Vec<(f64, f64)> = node_ids
+            .iter()
+            .map(|node_id| nodes.get_node(*node_id).unwrap_or((f64::NAN, f64::NAN)))
+            .collect();
+        if is_closed && node_ids.first() != node_ids.last() {
+            coordinates.push(coordinates[0]);
+        }
+        coordinates
+    }
+
+    /// The mesh domain as a (multi)polygon with islands as holes.
+    pub fn domain_feature(&self) -> Feature {
+        let mut polygons: Vec<Vec<Vec<(f64, f64)>>> = self
+            .domain_polygons()
+            .iter()
+            .map(|rings| {
+                rings
+                    .iter()
+                    .map(|ring| self.ring_coordinates(ring, true))
+                    .collect()
+            })
+            .collect();
+        let geometry = if polygons.len() == 1 {
+            Geometry::Polygon(polygons.remove(0))
+        } else {
+            Geometry::MultiPolygon(polygons)
+        };
+        Feature::new(
+            geometry,
+            &[("feature", PropertyValue::Text("domain".to_string()))],
+        )
+    }
+
+    /// One LineString per open, land, island and flow boundary segment, with
+    /// the boundary type and its 1-based index within that type.
+    pub fn boundary_features(&self) -> Vec<Feature> {
+        let Some(boundaries) = self.boundaries() else {
+            return Vec::new();
+        };
+        let mut features = Vec::new();
+        for (boundary_type, segments) in boundaries.to_boundary_type_map() {
+            let is_closed = boundary_type == BoundaryType::Interior;
+            for (index, segment) in segments.iter().enumerate() {
+                features.push(Feature::new(
+                    Geometry::LineString(self.ring_coordinates(segment, is_closed)),
+                    &[
+                        ("feature", PropertyValue::Text("boundary".to_string())),
+                        (
+                            "type",
+                            PropertyValue::Text(boundary_type_name(&boundary_type).to_string()),
+                        ),
+                        ("index", PropertyValue::Integer(index as i64 + 1)),
+                    ],
+                ));
+            }
+        }
+        features
+    }
+
+    /// One Polygon per element, with its element id.
+    pub fn element_features(&self) -> Vec<Feature> {
+        self.elements()
+            .iter()
+            .map(|(elem_id, element)| {
+                Feature::new(
+                    Geometry::Polygon(vec![self.ring_coordinates(element, true)]),
+                    &[
+                        ("feature", PropertyValue::Text("element".to_string())),
+                        ("element_id", PropertyValue::Integer(elem_id as i64)),
+                    ],
+                )
+            })
+            .collect()
+    }
+
+    /// The domain and boundary features, followed by the elements if requested.
+    pub fn features(&self, include_elements: bool) -> Vec<Feature> {
+        let mut features = vec![self.domain_feature()];
+        features.extend(self.boundary_features());
+        if include_elements {
+            features.extend(self.element_features());
+        }
+        features
+    }
+
+    /// GeoJSON FeatureCollection of [`Hgrid::features`].
+    pub fn to_geojson(&self, include_elements: bool) -> String {
+        let crs = match self.crs() {
+            Some(crs) if !is_wgs84(crs) => {
+                let name = match crs.strip_prefix("EPSG:") {
+                    Some(code) => format!("urn:ogc:def:crs:EPSG::{}", code),
+                    None => crs.to_string(),
+                };
+                format!(
+                    "\"crs\":{{\"type\":\"name\",\"properties\":{{\"name\":{}}}}},",
+                    json_string(&name)
+                )
+            }
+            _ => String::new(),
+        };
+        let features = self
+            .features(include_elements)
+            .iter()
+            .map(Feature::to_geojson)
+            .collect::<Vec<String>>()
+            .join(",\n");
+        format!(
+            "{{\"type\":\"FeatureCollection\",{}\"features\":[\n{}\n]}}",
+            crs, features
+        )
+    }
+
+    pub fn write_geojson(&self, path: &Path, include_elements: bool) -> std::io::Result<()> {
+        let mut tmpfile = NamedTempFile::new()?;
+        log::debug!("Will write to tmpfile: {:?}", tmpfile);
+        writeln!(tmpfile, "{}", self.to_geojson(include_elements))?;
+        tmpfile.persist(path)?;
+        Ok(())
+    }
+
+    /// Write [`Hgrid::features`] as CSV with a `WKT` column, the feature
+    /// properties and the grid's CRS.
+    pub fn write_wkt(&self, path: &Path, include_elements: bool) -> std::io::Result<()> {
+        let crs = csv_field(self.crs().unwrap_or_default());
+        let mut lines = vec!["WKT,feature,type,index,element_id,crs".to_string()];
+        for feature in self.features(include_elements) {
+            let property = |name: &str| {
+                feature
+                    .properties
+                    .get(name)
+                    .map(PropertyValue::to_csv)
+                    .unwrap_or_default()
+            };
+            lines.push(format!(
+                "{},{},{},{},{},{}",
+                csv_field(&feature.geometry.to_wkt()),
+                property("feature"),
+                property("type"),
+                property("index"),
+                property("element_id"),
+                crs
+            ));
+        }
+        let mut tmpfile = NamedTempFile::new()?;
+        log::debug!("Will write to tmpfile: {:?}", tmpfile);
+        writeln!(tmpfile, "{}", lines.join("\n"))?;
+        tmpfile.persist(path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{hgrid_from_gr3, UNIT_SQUARE_WITH_BOUNDARIES};
+
+    // Two triangles in the unit square, with an open and a land boundary
+    #[test]
+    fn test_geometry_to_wkt() {
+        let polygon = Geometry::Polygon(vec![vec![(0.0, 0.0), (1.0, 0.0), (0.5, 1.5), (0.0, 0.0)]]);
+        assert_eq!(polygon.to_wkt(), "POLYGON ((0 0,1 0,0.5 1.5,0 0))");
+        assert_eq!(
+            Geometry::LineString(vec![(0.0, 0.0), (1.0, 2.0)]).to_geojson(),
+            "{\"type\":\"LineString\",\"coordinates\":[[0,0],[1,2]]}"
+        );
+    }
+
+    #[test]
+    fn test_features() {
+        let hgrid = hgrid_from_gr3(UNIT_SQUARE_WITH_BOUNDARIES);
+        let features = hgrid.features(true);
+        // Domain, two boundaries and two elements
+        assert_eq!(features.len(), 5);
+        let Geometry::Polygon(rings) = &features[0].geometry else {
+            panic!("expected the domain to be a single polygon");
+        };
+        assert_eq!(rings.len(), 1);
+        assert_eq!(rings[0].len(), 5);
+        assert_eq!(
+            features[2].properties.get("type"),
+            Some(&PropertyValue::Text("land".to_string()))
+        );
+        assert_eq!(
+            features[2].geometry,
+            Geometry::LineString(vec![(1.0, 1.0), (0.0, 1.0), (0.0, 0.0)])
+        );
+
+        let geojson = hgrid.to_geojson(false);
+        assert!(geojson.contains("\"name\":\"urn:ogc:def:crs:EPSG::32619\""));
+        assert!(geojson.contains(
+            "{\"type\":\"Feature\",\"properties\":{\"feature\":\"boundary\",\"type\":\"open\",\"index\":1},\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[1,0],[1,1]]}}"
+        ));
+    }
+}
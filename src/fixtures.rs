@@ -0,0 +1,69 @@
+//! Meshes shared by the unit tests
+
+use crate::gr3;
+use crate::Hgrid;
+use std::io::BufReader;
+
+/// Unit square split into two triangles along the 1-3 diagonal
+pub(crate) const UNIT_SQUARE: &str = "test mesh
+2 4
+1 0 0 5
+2 1 0 6
+3 1 1 7
+4 0 1 8
+1 3 1 2 3
+2 3 1 3 4";
+
+/// [`UNIT_SQUARE`] in UTM 19N, open along its right side and closed by a
+/// land boundary
+pub(crate) const UNIT_SQUARE_WITH_BOUNDARIES: &str = "EPSG:32619 test mesh
+2 4
+1 0 0 5
+2 1 0 6
+3 1 1 7
+4 0 1 8
+1 3 1 2 3
+2 3 1 3 4
+1 = Number of open boundaries
+2 = Total number of open boundary nodes
+2 = Number of nodes for open boundary 1
+2
+3
+1 = number of land boundaries
+3 = Total number of land boundary nodes
+3 0 = Number of nodes for land boundary 1
+3
+4
+1";
+
+/// A quad and a triangle in UTM 19N, with open, land and island boundaries,
+/// the island listed first
+pub(crate) const QUAD_AND_TRIANGLE: &str = "EPSG:32619 test mesh
+2 5
+1 0 0 5
+2 1 0 6
+5 1 1 7
+4 0 1 8
+7 2 0.5 9
+10 4 1 2 5 4
+20 3 2 7 5
+1 = Number of open boundaries
+2 = Total number of open boundary nodes
+2 = Number of nodes for open boundary 1
+2
+7
+2 = number of land boundaries
+6 = Total number of land boundary nodes
+2 1 = Number of nodes for land boundary 1
+1
+2
+4 0 = Number of nodes for land boundary 2
+7
+5
+4
+1";
+
+pub(crate) fn hgrid_from_gr3(text: &str) -> Hgrid {
+    let gr3 = gr3::parse_from_reader(BufReader::new(text.as_bytes()), "test").unwrap();
+    Hgrid::try_from(gr3).unwrap()
+}
@@ -0,0 +1,446 @@
+//! ADCIRC fort.13 nodal attribute files
+//!
+//! A fort.13 lists, for each attribute, a default value (or tuple of values) and
+//! the sparse set of nodes that differ from it. Attributes can be expanded onto an
+//! [`Hgrid`] and written out as SCHISM gr3 files (e.g. `manning.gr3`).
+
+use crate::nodes::{NodeAttribute, NodeAttributeError, Nodes};
+use crate::Hgrid;
+use linked_hash_map::LinkedHashMap;
+use ndarray::{Array1, Array2};
+use std::fmt;
+use std::fs::File;
+use std::io::{prelude::*, BufReader};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+use thiserror::Error;
+
+/// One nodal attribute of a fort.13 file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fort13NodalAttribute {
+    pub(crate) units: String,
+    pub(crate) default_values: Vec<f64>,
+    /// Values of the nodes that differ from the default, keyed by node id
+    pub(crate) non_default_values: LinkedHashMap<u32, Vec<f64>>,
+}
+
+impl Fort13NodalAttribute {
+    pub fn units(&self) -> &str {
+        &self.units
+    }
+
+    pub fn values_per_node(&self) -> usize {
+        self.default_values.len()
+    }
+
+    pub fn default_values(&self) -> &[f64] {
+        &self.default_values
+    }
+
+    pub fn non_default_values(&self) -> &LinkedHashMap<u32, Vec<f64>> {
+        &self.non_default_values
+    }
+
+    /// Expand the default and non-default values onto every node, in node order.
+    pub fn to_node_attribute(&self, nodes: &Nodes) -> Result<NodeAttribute, Fort13Error> {
+        let ncols = self.values_per_node();
+        let mut values =
+            Array2::from_shape_fn((nodes.len(), ncols), |(_, col)| self.default_values[col]);
+        for (node_id, node_values) in self.non_default_values.iter() {
+            let index = nodes
+                .index_of(*node_id)
+                .ok_or(Fort13Error::UnknownNode(*node_id))?;
+            values
+                .row_mut(index)
+                .assign(&Array1::from(node_values.clone()));
+        }
+        Ok(match ncols {
+            1 => NodeAttribute::Scalar(values.column(0).to_owned()),
+            _ => NodeAttribute::Multi(values),
+        })
+    }
+}
+
+/// Contents of an ADCIRC fort.13 file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fort13 {
+    pub(crate) description: String,
+    pub(crate) number_of_nodes: usize,
+    pub(crate) attributes: LinkedHashMap<String, Fort13NodalAttribute>,
+}
+
+impl Fort13 {
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn number_of_nodes(&self) -> usize {
+        self.number_of_nodes
+    }
+
+    pub fn attributes(&self) -> &LinkedHashMap<String, Fort13NodalAttribute> {
+        &self.attributes
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Fort13NodalAttribute> {
+        self.attributes.get(name)
+    }
+
+    /// Check that the file was written for `hgrid`: same node count and only known node ids.
+    pub fn validate_against(&self, hgrid: &Hgrid) -> Result<(), Fort13Error> {
+        let nodes = hgrid.nodes();
+        if self.number_of_nodes != nodes.len() {
+            return Err(Fort13Error::NodeCountMismatch(
+                self.number_of_nodes,
+                nodes.len(),
+            ));
+        }
+        for attribute in self.attributes.values() {
+            if let Some(node_id) = attribute
+                .non_default_values
+                .keys()
+                .find(|node_id| nodes.index_of(**node_id).is_none())
+            {
+                return Err(Fort13Error::UnknownNode(*node_id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Add every attribute to the nodes of `hgrid`, under its fort.13 name.
+    pub fn apply_to(&self, hgrid: &mut Hgrid) -> Result<(), Fort13Error> {
+        self.validate_against(hgrid)?;
+        for (name, attribute) in self.attributes.iter() {
+            let values = attribute.to_node_attribute(hgrid.nodes())?;
+            hgrid.add_node_attribute(name, values)?;
+        }
+        Ok(())
+    }
+
+    /// Write each attribute as a gr3 in `directory`, named by [`gr3_file_name`].
+    ///
+    /// Returns the paths written, in attribute order.
+    pub fn write_gr3s(&self, hgrid: &Hgrid, directory: &Path) -> Result<Vec<PathBuf>, Fort13Error> {
+        let mut hgrid = hgrid.clone();
+        self.apply_to(&mut hgrid)?;
+        let mut paths = Vec::with_capacity(self.attributes.len());
+        for name in self.attributes.keys() {
+            let path = directory.join(gr3_file_name(name));
+            hgrid.write_with_attribute(&path, name).map_err(|e| {
+                Fort13Error::IoError(format!("Failed to write {}: {}", path.display(), e))
+            })?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+/// SCHISM gr3 file name for a fort.13 attribute.
+///
+/// Attributes with a SCHISM equivalent get its conventional name; the rest are
+/// written as `<attribute name>.gr3`.
+pub fn gr3_file_name(attribute_name: &str) -> String {
+    match attribute_name {
+        "mannings_n_at_sea_floor" => "manning.gr3".to_string(),
+        "quadratic_friction_coefficient_at_sea_floor" => "drag.gr3".to_string(),
+        "bottom_roughness_length" => "rough.gr3".to_string(),
+        _ => format!("{}.gr3", attribute_name),
+    }
+}
+
+impl fmt::Display for Fort13 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: &[f64]| {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let mut lines = Vec::new();
+        lines.push(self.description.clone());
+        lines.push(self.number_of_nodes.to_string());
+        lines.push(self.attributes.len().to_string());
+        for (name, attribute) in self.attributes.iter() {
+            lines.push(name.clone());
+            lines.push(attribute.units.clone());
+            lines.push(attribute.values_per_node().to_string());
+            lines.push(join(&attribute.default_values));
+        }
+        for (name, attribute) in self.attributes.iter() {
+            lines.push(name.clone());
+            lines.push(attribute.non_default_values.len().to_string());
+            for (node_id, values) in attribute.non_default_values.iter() {
+                lines.push(format!("{} {}", node_id, join(values)));
+            }
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Fort13Error {
+    #[error("I/O error: {0}")]
+    IoError(String),
+
+    #[error("Empty file error: {0}")]
+    EmptyFile(String),
+
+    #[error("Line read error: file {0}, error: {1}")]
+    LineReadError(String, String),
+
+    #[error("fort.13 defines {0} nodes but the mesh has {1}")]
+    NodeCountMismatch(usize, usize),
+
+    #[error("fort.13 references node {0} which is not in the mesh")]
+    UnknownNode(u32),
+
+    #[error(transparent)]
+    NodeAttributeError(#[from] NodeAttributeError),
+}
+
+pub fn parse_from_path_ref(path: &Path) -> Result<Fort13, Fort13Error> {
+    let fname = &path.display().to_string();
+    let file = match File::open(fname) {
+        Ok(file) => file,
+        Err(e) => {
+            return Err(Fort13Error::IoError(format!(
+                "Failed to open {}: {}",
+                fname, e
+            )));
+        }
+    };
+    let reader = BufReader::new(file);
+    parse_from_reader(reader, fname)
+}
+
+pub fn parse_from_reader<R: Read>(
+    reader: BufReader<R>,
+    fname: &str, // Passed separately for error messages
+) -> Result<Fort13, Fort13Error> {
+    let mut lines = reader.lines().peekable();
+    if lines.peek().is_none() {
+        return Err(Fort13Error::EmptyFile(fname.to_string()));
+    }
+    let mut next_line = |expected: &str| -> Result<String, Fort13Error> {
+        match lines.next() {
+            Some(Ok(line)) => Ok(line),
+            Some(Err(e)) => Err(Fort13Error::LineReadError(fname.to_string(), e.to_string())),
+            None => Err(Fort13Error::LineReadError(
+                fname.to_string(),
+                format!(
+                    "Error reading fort.13 file: {}. Expected {} but reached the end of the file.",
+                    fname, expected
+                ),
+            )),
+        }
+    };
+
+    let description = next_line("a description")?.trim().to_string();
+    let number_of_nodes = parse_fort13_item::<usize>(
+        next_line("the number of nodes")?.split_whitespace().next(),
+        fname,
+        "the number of nodes",
+    )?;
+    let number_of_attributes = parse_fort13_item::<usize>(
+        next_line("the number of attributes")?
+            .split_whitespace()
+            .next(),
+        fname,
+        "the number of attributes",
+    )?;
+
+    let mut attributes = LinkedHashMap::new();
+    for _ in 0..number_of_attributes {
+        let name = next_line("an attribute name")?.trim().to_string();
+        let units = next_line("attribute units")?.trim().to_string();
+        let values_per_node = parse_fort13_item::<usize>(
+            next_line("the number of values per node")?
+                .split_whitespace()
+                .next(),
+            fname,
+            "the number of values per node",
+        )?;
+        let default_values = parse_fort13_values(
+            next_line("default values")?.split_whitespace(),
+            values_per_node,
+            fname,
+            &format!("default values of {}", name),
+        )?;
+        attributes.insert(
+            name,
+            Fort13NodalAttribute {
+                units,
+                default_values,
+                non_default_values: LinkedHashMap::new(),
+            },
+        );
+    }
+
+    for _ in 0..number_of_attributes {
+        let name = next_line("an attribute name")?.trim().to_string();
+        let attribute = match attributes.get_mut(&name) {
+            Some(attribute) => attribute,
+            None => {
+                return Err(Fort13Error::LineReadError(
+                    fname.to_string(),
+                    format!("Error reading fort.13 file: {}. Attribute {} has values but is not declared in the header.", fname, name)
+                ))
+            }
+        };
+        let number_of_non_default = parse_fort13_item::<usize>(
+            next_line("the number of non-default nodes")?
+                .split_whitespace()
+                .next(),
+            fname,
+            "the number of non-default nodes",
+        )?;
+        let values_per_node = attribute.values_per_node();
+        for _ in 0..number_of_non_default {
+            let line = next_line("a non-default node line")?;
+            let mut split_line = line.split_whitespace();
+            let node_id = parse_fort13_item::<u32>(split_line.next(), fname, "a node id")?;
+            let values = parse_fort13_values(
+                split_line,
+                values_per_node,
+                fname,
+                &format!("values of {} at node {}", name, node_id),
+            )?;
+            attribute.non_default_values.insert(node_id, values);
+        }
+    }
+
+    Ok(Fort13 {
+        description,
+        number_of_nodes,
+        attributes,
+    })
+}
+
+pub fn write_to_path(path: &Path, fort13: &Fort13) -> std::io::Result<()> {
+    let mut tmpfile = NamedTempFile::new()?;
+    log::debug!("Will write to tmpfile: {:?}", tmpfile);
+    writeln!(tmpfile, "{}", fort13)?;
+    tmpfile.persist(path)?;
+    Ok(())
+}
+
+fn parse_fort13_item<T: std::str::FromStr>(
+    item: Option<&str>,
+    fname: &str,
+    expected: &str,
+) -> Result<T, Fort13Error> {
+    match item {
+        // Fortran writers may use D exponents (1.0D-3)
+        Some(value) => value.replace(['d', 'D'], "e").parse::<T>().map_err(|_| {
+            Fort13Error::LineReadError(
+                fname.to_string(),
+                format!(
+                    "Error reading fort.13 file: {}. Expected {} but found {}.",
+                    fname, expected, value
+                ),
+            )
+        }),
+        None => Err(Fort13Error::LineReadError(
+            fname.to_string(),
+            format!(
+                "Error reading fort.13 file: {}. Expected {} but the line is empty.",
+                fname, expected
+            ),
+        )),
+    }
+}
+
+fn parse_fort13_values<'a>(
+    mut items: impl Iterator<Item = &'a str>,
+    number_of_values: usize,
+    fname: &str,
+    expected: &str,
+) -> Result<Vec<f64>, Fort13Error> {
+    (0..number_of_values)
+        .map(|_| parse_fort13_item::<f64>(items.next(), fname, expected))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{hgrid_from_gr3, UNIT_SQUARE};
+    use crate::gr3;
+    use tempfile::tempdir;
+
+    const FORT13: &str = "test nodal attributes
+4
+2
+mannings_n_at_sea_floor
+unitless
+1
+0.02
+surface_canopy_coefficient
+unitless
+2
+1 0
+mannings_n_at_sea_floor
+2
+2 0.035
+4 0.04
+surface_canopy_coefficient
+1
+3 0 1";
+
+    #[test]
+    fn test_parse_and_expand() {
+        let fort13 = parse_from_reader(BufReader::new(FORT13.as_bytes()), "test").unwrap();
+        assert_eq!(fort13.number_of_nodes(), 4);
+        let manning = fort13.attribute("mannings_n_at_sea_floor").unwrap();
+        assert_eq!(manning.default_values(), &[0.02]);
+        assert_eq!(manning.non_default_values().get(&4), Some(&vec![0.04]));
+
+        let hgrid = hgrid_from_gr3(UNIT_SQUARE);
+        fort13.validate_against(&hgrid).unwrap();
+        assert_eq!(
+            manning.to_node_attribute(hgrid.nodes()).unwrap(),
+            NodeAttribute::Scalar(ndarray::array![0.02, 0.035, 0.02, 0.04])
+        );
+        let canopy = fort13.attribute("surface_canopy_coefficient").unwrap();
+        assert_eq!(
+            canopy.to_node_attribute(hgrid.nodes()).unwrap(),
+            NodeAttribute::Multi(ndarray::array![
+                [1.0, 0.0],
+                [1.0, 0.0],
+                [0.0, 1.0],
+                [1.0, 0.0]
+            ])
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let fort13 = parse_from_reader(BufReader::new(FORT13.as_bytes()), "test").unwrap();
+        assert_eq!(fort13.to_string(), FORT13);
+    }
+
+    #[test]
+    fn test_node_count_mismatch() {
+        let text = FORT13.replacen("\n4\n", "\n5\n", 1);
+        let fort13 = parse_from_reader(BufReader::new(text.as_bytes()), "test").unwrap();
+        assert!(matches!(
+            fort13.validate_against(&hgrid_from_gr3(UNIT_SQUARE)),
+            Err(Fort13Error::NodeCountMismatch(5, 4))
+        ));
+    }
+
+    #[test]
+    fn test_write_gr3s() {
+        let fort13 = parse_from_reader(BufReader::new(FORT13.as_bytes()), "test").unwrap();
+        let temp_dir = tempdir().unwrap();
+        let paths = fort13
+            .write_gr3s(&hgrid_from_gr3(UNIT_SQUARE), temp_dir.path())
+            .unwrap();
+        assert_eq!(paths[0], temp_dir.path().join("manning.gr3"));
+
+        let manning = gr3::parse_from_path_ref(&paths[0]).unwrap();
+        assert_eq!(manning.nodes().get(&2).unwrap().1, Some(vec![0.035]));
+        assert_eq!(manning.nodes().get(&3).unwrap().1, Some(vec![0.02]));
+    }
+}
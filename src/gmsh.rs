@@ -0,0 +1,940 @@
+//! Gmsh ASCII `.msh` reader and writer (format versions 2.2 and 4.1)
+//!
+//! Triangles and quadrangles become mesh elements. Boundaries are stored as
+//! 2-node line elements, one physical curve group per segment, named
+//! `open_boundary_<n>`, `land_boundary_<n>` or `island_boundary_<n>`; on read,
+//! physical line groups whose names start with `open`, `land`, `island` or
+//! `interior` are chained back into boundary segments and other groups are
+//! ignored. A group holding several curves gives one segment per connected
+//! chain of lines. Depths (positive down) travel in a `$NodeData` block named
+//! `depth`, written only when the mesh has depths.
+//!
+//! The description, CRS and IBTYPE flags have no place in the format and are
+//! not preserved.
+
+use crate::compression::{open_reader, CompressedWriter, Compression};
+use crate::gr3::{Gr3ParserOutput, Gr3ParserOutputBuilder, Gr3ParserOutputBuilderError};
+use crate::nodes::DEPTH_ATTRIBUTE;
+use crate::Hgrid;
+use linked_hash_map::LinkedHashMap;
+use std::collections::HashMap;
+use std::io::{prelude::*, BufReader, Lines};
+use std::path::Path;
+use std::str::FromStr;
+use tempfile::NamedTempFile;
+use thiserror::Error;
+
+const LINE: u32 = 1;
+const TRIANGLE: u32 = 2;
+const QUADRANGLE: u32 = 3;
+const POINT: u32 = 15;
+
+/// Physical group of the mesh surface in files written by this module
+const DOMAIN_PHYSICAL_TAG: u32 = 1;
+
+/// Version of the Gmsh ASCII format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GmshVersion {
+    V2_2,
+    #[default]
+    V4_1,
+}
+
+impl GmshVersion {
+    fn as_str(&self) -> &'static str {
+        match self {
+            GmshVersion::V2_2 => "2.2",
+            GmshVersion::V4_1 => "4.1",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum GmshError {
+    #[error("I/O error: {0}")]
+    IoError(String),
+
+    #[error("Empty file error: {0}")]
+    EmptyFile(String),
+
+    #[error("Line read error: file {0}, error: {1}")]
+    LineReadError(String, String),
+
+    #[error("Unsupported msh file {0}: {1}")]
+    Unsupported(String, String),
+
+    #[error(transparent)]
+    Gr3ParserOutputBuilderError(#[from] Gr3ParserOutputBuilderError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BoundaryKind {
+    Open,
+    Land,
+    Island,
+}
+
+impl BoundaryKind {
+    fn from_physical_name(name: &str) -> Option<BoundaryKind> {
+        let name = name.to_lowercase();
+        if name.starts_with("open") {
+            Some(BoundaryKind::Open)
+        } else if name.starts_with("land") {
+            Some(BoundaryKind::Land)
+        } else if name.starts_with("island") || name.starts_with("interior") {
+            Some(BoundaryKind::Island)
+        } else {
+            None
+        }
+    }
+}
+
+impl Hgrid {
+    /// Write the mesh as a Gmsh ASCII `.msh` file.
+    pub fn write_gmsh(&self, path: &Path, version: GmshVersion) -> std::io::Result<()> {
+        write_to_path(path, self, version)
+    }
+}
+
+pub fn parse_from_path_ref(path: &Path) -> Result<Gr3ParserOutput, GmshError> {
+    let fname = &path.display().to_string();
+    // Decompresses .gz/.zst/.bz2 input on the fly
+    let reader = match open_reader(path) {
+        Ok(reader) => reader,
+        Err(e) => {
+            return Err(GmshError::IoError(format!(
+                "Failed to open {}: {}",
+                fname, e
+            )));
+        }
+    };
+    parse_from_reader(reader, fname)
+}
+
+/// Line source that skips blank lines and reports positions in errors.
+struct MshLines<'a, R: Read> {
+    lines: Lines<BufReader<R>>,
+    fname: &'a str,
+    line_number: usize,
+}
+
+impl<R: Read> MshLines<'_, R> {
+    fn next_line(&mut self) -> Result<Option<String>, GmshError> {
+        for line in self.lines.by_ref() {
+            self.line_number += 1;
+            let line =
+                line.map_err(|e| GmshError::LineReadError(self.fname.to_string(), e.to_string()))?;
+            if !line.trim().is_empty() {
+                return Ok(Some(line.trim().to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    fn expect_line(&mut self, what: &str) -> Result<String, GmshError> {
+        match self.next_line()? {
+            Some(line) => Ok(line),
+            None => Err(self.error(format!(
+                "Expected {} but reached the end of the file.",
+                what
+            ))),
+        }
+    }
+
+    /// Parse a line of at least `min_len` numbers.
+    fn expect_numbers<T: FromStr>(
+        &mut self,
+        what: &str,
+        min_len: usize,
+    ) -> Result<Vec<T>, GmshError> {
+        let line = self.expect_line(what)?;
+        let numbers = line
+            .split_whitespace()
+            .map(|item| self.parse(item, what))
+            .collect::<Result<Vec<T>, GmshError>>()?;
+        if numbers.len() < min_len {
+            return Err(self.error(format!(
+                "Expected {} to have at least {} items but found {}.",
+                what, min_len, line
+            )));
+        }
+        Ok(numbers)
+    }
+
+    fn parse<T: FromStr>(&self, item: &str, what: &str) -> Result<T, GmshError> {
+        item.parse::<T>().map_err(|_| {
+            self.error(format!(
+                "Expected {} to be numeric but found {}.",
+                what, item
+            ))
+        })
+    }
+
+    fn skip_section(&mut self, section: &str) -> Result<(), GmshError> {
+        let end = format!("$End{}", &section[1..]);
+        while self.expect_line(&end)? != end {}
+        Ok(())
+    }
+
+    fn error(&self, message: String) -> GmshError {
+        GmshError::LineReadError(
+            self.fname.to_string(),
+            format!(
+                "Error reading msh file: {}. Line {}: {}",
+                self.fname, self.line_number, message
+            ),
+        )
+    }
+}
+
+/// Parse a Gmsh ASCII mesh (format 2.2 or 4.1).
+pub fn parse_from_reader<R: Read>(
+    reader: BufReader<R>,
+    fname: &str, // Passed separately for error messages
+) -> Result<Gr3ParserOutput, GmshError> {
+    let mut lines = MshLines {
+        lines: reader.lines(),
+        fname,
+        line_number: 0,
+    };
+    let mut version = None;
+    let mut physical_names: LinkedHashMap<u32, String> = LinkedHashMap::new();
+    let mut entity_physical_tags: HashMap<(u32, u32), Vec<u32>> = HashMap::new();
+    let mut nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> = LinkedHashMap::new();
+    let mut elements: LinkedHashMap<u32, Vec<u32>> = LinkedHashMap::new();
+    // Boundary line elements per physical tag, in file order
+    let mut physical_lines: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
+    let mut depths: Option<HashMap<u32, f64>> = None;
+
+    while let Some(line) = lines.next_line()? {
+        match line.as_str() {
+            "$MeshFormat" => {
+                let header = lines.expect_line("the mesh format header")?;
+                let items: Vec<&str> = header.split_whitespace().collect();
+                let file_version = match items.first().copied() {
+                    Some(v) if v.starts_with("2.") => GmshVersion::V2_2,
+                    Some("4.1") => GmshVersion::V4_1,
+                    _ => {
+                        return Err(GmshError::Unsupported(
+                            fname.to_string(),
+                            format!("format version {} (expected 2.2 or 4.1)", header),
+                        ))
+                    }
+                };
+                if items.get(1) != Some(&"0") {
+                    return Err(GmshError::Unsupported(
+                        fname.to_string(),
+                        "binary msh files are not supported".to_string(),
+                    ));
+                }
+                version = Some(file_version);
+                lines.skip_section("$MeshFormat")?;
+            }
+            "$PhysicalNames" => {
+                let count: usize = lines.expect_numbers("the number of physical names", 1)?[0];
+                for _ in 0..count {
+                    let line = lines.expect_line("a physical name")?;
+                    let mut split_line = line.splitn(3, char::is_whitespace);
+                    let _dim = split_line.next();
+                    let tag: u32 =
+                        lines.parse(split_line.next().unwrap_or(""), "the physical tag")?;
+                    let name = split_line.next().unwrap_or("").trim().trim_matches('"');
+                    physical_names.insert(tag, name.to_string());
+                }
+                lines.skip_section("$PhysicalNames")?;
+            }
+            "$Entities" => {
+                let counts: Vec<usize> = lines.expect_numbers("the entity counts", 4)?;
+                for (dim, &count) in counts.iter().enumerate() {
+                    for _ in 0..count {
+                        let line = lines.expect_line("an entity")?;
+                        let items: Vec<&str> = line.split_whitespace().collect();
+                        // Points carry x y z, higher dimensions a bounding box
+                        let offset = if dim == 0 { 4 } else { 7 };
+                        let tag: u32 = lines.parse(items[0], "the entity tag")?;
+                        let num_physical_tags: usize = lines.parse(
+                            items.get(offset).copied().unwrap_or(""),
+                            "the number of physical tags",
+                        )?;
+                        let physical_tags = items
+                            .iter()
+                            .skip(offset + 1)
+                            .take(num_physical_tags)
+                            .map(|item| lines.parse::<i64>(item, "a physical tag"))
+                            .collect::<Result<Vec<i64>, GmshError>>()?;
+                        entity_physical_tags.insert(
+                            (dim as u32, tag),
+                            physical_tags
+                                .iter()
+                                .map(|t| t.unsigned_abs() as u32)
+                                .collect(),
+                        );
+                    }
+                }
+                lines.skip_section("$Entities")?;
+            }
+            "$Nodes" => {
+                match version {
+                    Some(GmshVersion::V4_1) => {
+                        let header: Vec<usize> = lines.expect_numbers("the nodes header", 4)?;
+                        for _ in 0..header[0] {
+                            let block: Vec<usize> =
+                                lines.expect_numbers("a node block header", 4)?;
+                            let count = block[3];
+                            let mut tags = Vec::with_capacity(count);
+                            for _ in 0..count {
+                                tags.push(lines.expect_numbers::<u32>("a node tag", 1)?[0]);
+                            }
+                            for tag in tags {
+                                let coords: Vec<f64> =
+                                    lines.expect_numbers("node coordinates", 2)?;
+                                nodes.insert(tag, (vec![coords[0], coords[1]], None));
+                            }
+                        }
+                    }
+                    _ => {
+                        let count: usize = lines.expect_numbers("the number of nodes", 1)?[0];
+                        for _ in 0..count {
+                            let line = lines.expect_line("a node")?;
+                            let items: Vec<&str> = line.split_whitespace().collect();
+                            if items.len() < 3 {
+                                return Err(lines.error(format!(
+                                    "Expected a node tag and coordinates but found {}.",
+                                    line
+                                )));
+                            }
+                            let tag: u32 = lines.parse(items[0], "the node tag")?;
+                            let x: f64 = lines.parse(items[1], "the node x coordinate")?;
+                            let y: f64 = lines.parse(items[2], "the node y coordinate")?;
+                            nodes.insert(tag, (vec![x, y], None));
+                        }
+                    }
+                }
+                lines.skip_section("$Nodes")?;
+            }
+            "$Elements" => {
+                let mut add_element = |lines: &MshLines<'_, R>,
+                                       tag: u32,
+                                       element_type: u32,
+                                       physical_tag: Option<u32>,
+                                       node_ids: &[u32]| {
+                    let node_count = match element_type {
+                        POINT => 1,
+                        LINE => 2,
+                        TRIANGLE => 3,
+                        QUADRANGLE => 4,
+                        other => return Err(lines.error(format!(
+                            "Element {} has type {}, only points, lines, triangles and quadrangles are supported.",
+                            tag, other
+                        ))),
+                    };
+                    if node_ids.len() < node_count {
+                        return Err(lines.error(format!(
+                            "Expected element {} to have {} nodes but found {}.",
+                            tag,
+                            node_count,
+                            node_ids.len()
+                        )));
+                    }
+                    match element_type {
+                        TRIANGLE | QUADRANGLE => {
+                            elements.insert(tag, node_ids[..node_count].to_vec());
+                        }
+                        LINE => {
+                            if let Some(physical_tag) = physical_tag {
+                                physical_lines
+                                    .entry(physical_tag)
+                                    .or_default()
+                                    .push((node_ids[0], node_ids[1]));
+                            }
+                        }
+                        _ => {}
+                    }
+                    Ok(())
+                };
+                match version {
+                    Some(GmshVersion::V4_1) => {
+                        let header: Vec<usize> = lines.expect_numbers("the elements header", 4)?;
+                        for _ in 0..header[0] {
+                            let block: Vec<u32> =
+                                lines.expect_numbers("an element block header", 4)?;
+                            let (dim, entity_tag, element_type, count) =
+                                (block[0], block[1], block[2], block[3]);
+                            let physical_tag = entity_physical_tags
+                                .get(&(dim, entity_tag))
+                                .and_then(|tags| tags.first().copied());
+                            for _ in 0..count {
+                                let items: Vec<u32> = lines.expect_numbers("an element", 3)?;
+                                add_element(
+                                    &lines,
+                                    items[0],
+                                    element_type,
+                                    physical_tag,
+                                    &items[1..],
+                                )?;
+                            }
+                        }
+                    }
+                    _ => {
+                        let count: usize = lines.expect_numbers("the number of elements", 1)?[0];
+                        for _ in 0..count {
+                            let items: Vec<u32> = lines.expect_numbers("an element", 3)?;
+                            let num_tags = items[2] as usize;
+                            let physical_tag = items.get(3).filter(|_| num_tags > 0).copied();
+                            add_element(
+                                &lines,
+                                items[0],
+                                items[1],
+                                physical_tag,
+                                items.get(3 + num_tags..).unwrap_or_default(),
+                            )?;
+                        }
+                    }
+                }
+                lines.skip_section("$Elements")?;
+            }
+            "$NodeData" => {
+                let mut names = Vec::new();
+                for _ in 0..lines.expect_numbers::<usize>("the number of string tags", 1)?[0] {
+                    names.push(
+                        lines
+                            .expect_line("a string tag")?
+                            .trim_matches('"')
+                            .to_string(),
+                    );
+                }
+                for _ in 0..lines.expect_numbers::<usize>("the number of real tags", 1)?[0] {
+                    lines.expect_line("a real tag")?;
+                }
+                let mut integer_tags = Vec::new();
+                for _ in 0..lines.expect_numbers::<usize>("the number of integer tags", 1)?[0] {
+                    integer_tags.push(lines.expect_numbers::<i64>("an integer tag", 1)?[0]);
+                }
+                let count = integer_tags.get(2).copied().unwrap_or(0) as usize;
+                let is_depth = depths.is_none()
+                    && names
+                        .first()
+                        .is_some_and(|name| name.eq_ignore_ascii_case(DEPTH_ATTRIBUTE));
+                let mut values = HashMap::with_capacity(count);
+                for _ in 0..count {
+                    let line = lines.expect_line("a node value")?;
+                    let mut split_line = line.split_whitespace();
+                    let tag: u32 = lines.parse(split_line.next().unwrap_or(""), "the node tag")?;
+                    let value: f64 =
+                        lines.parse(split_line.next().unwrap_or(""), "the node value")?;
+                    values.insert(tag, value);
+                }
+                if is_depth {
+                    depths = Some(values);
+                }
+                lines.skip_section("$NodeData")?;
+            }
+            section if section.starts_with('$') => lines.skip_section(section)?,
+            other => {
+                return Err(lines.error(format!("Expected a section header but found {}.", other)))
+            }
+        }
+    }
+    if nodes.is_empty() {
+        return Err(GmshError::EmptyFile(fname.to_string()));
+    }
+
+    if let Some(depths) = depths {
+        for (node_id, (_coord, value)) in nodes.iter_mut() {
+            *value = depths.get(node_id).map(|&depth| vec![depth]);
+        }
+    }
+
+    let mut open_boundaries = Vec::new();
+    let mut land_boundaries = Vec::new();
+    let mut interior_boundaries = Vec::new();
+    for (tag, name) in physical_names.iter() {
+        let (Some(kind), Some(segment_lines)) = (
+            BoundaryKind::from_physical_name(name),
+            physical_lines.get(tag),
+        ) else {
+            continue;
+        };
+        let segments = chain_lines(segment_lines, kind == BoundaryKind::Island);
+        match kind {
+            BoundaryKind::Open => open_boundaries.extend(segments),
+            BoundaryKind::Land => land_boundaries.extend(segments),
+            BoundaryKind::Island => interior_boundaries.extend(segments),
+        }
+    }
+
+    let mut builder = Gr3ParserOutputBuilder::default();
+    builder
+        .nodes(nodes)
+        .elements(Some(elements))
+        .open_boundaries(open_boundaries)
+        .land_boundaries(land_boundaries)
+        .interior_boundaries(interior_boundaries);
+    Ok(builder.build()?)
+}
+
+/// Join line elements sharing end nodes into node paths, one per connected
+/// chain, in the order their first line appears. Closed islands drop the
+/// repeated start node, matching the gr3 convention.
+fn chain_lines(segment_lines: &[(u32, u32)], is_closed: bool) -> Vec<Vec<u32>> {
+    let mut incident: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, &(a, b)) in segment_lines.iter().enumerate() {
+        incident.entry(a).or_default().push(index);
+        if b != a {
+            incident.entry(b).or_default().push(index);
+        }
+    }
+    let mut used = vec![false; segment_lines.len()];
+    // Follow an unused line from `node`, returning its other end
+    let step = |node: u32, used: &mut [bool]| {
+        let index = *incident.get(&node)?.iter().find(|&&index| !used[index])?;
+        used[index] = true;
+        let (a, b) = segment_lines[index];
+        Some(if a == node { b } else { a })
+    };
+
+    let mut segments = Vec::new();
+    for start in 0..segment_lines.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let (a, b) = segment_lines[start];
+        let mut segment = vec![a, b];
+        while let Some(next) = step(*segment.last().unwrap(), &mut used) {
+            segment.push(next);
+        }
+        if segment.first() != segment.last() {
+            let mut head = Vec::new();
+            let mut node = a;
+            while let Some(previous) = step(node, &mut used) {
+                head.push(previous);
+                node = previous;
+            }
+            head.reverse();
+            head.extend(segment);
+            segment = head;
+        }
+        if is_closed && segment.len() > 1 && segment.first() == segment.last() {
+            segment.pop();
+        }
+        segments.push(segment);
+    }
+    segments
+}
+
+/// Boundary segments as named chains of 2-node lines
+fn boundary_lines(hgrid: &Hgrid) -> Vec<(String, Vec<(u32, u32)>)> {
+    let Some(boundaries) = hgrid.boundaries() else {
+        return Vec::new();
+    };
+    let as_lines = |segment: &Vec<u32>, is_closed: bool| -> Vec<(u32, u32)> {
+        let mut lines: Vec<(u32, u32)> = segment.windows(2).map(|w| (w[0], w[1])).collect();
+        if is_closed && segment.len() > 2 && segment.first() != segment.last() {
+            lines.push((segment[segment.len() - 1], segment[0]));
+        }
+        lines
+    };
+    let mut named_lines = Vec::new();
+    for (prefix, segments, is_closed) in [
+        (
+            "open_boundary",
+            boundaries.open().map(|b| b.nodes_ids()),
+            false,
+        ),
+        (
+            "land_boundary",
+            boundaries.land().map(|b| b.nodes_ids()),
+            false,
+        ),
+        (
+            "island_boundary",
+            boundaries.interior().map(|b| b.nodes_ids()),
+            true,
+        ),
+    ] {
+        for (index, segment) in segments.into_iter().flatten().enumerate() {
+            named_lines.push((
+                format!("{}_{}", prefix, index + 1),
+                as_lines(segment, is_closed),
+            ));
+        }
+    }
+    named_lines
+}
+
+/// Consecutive elements of one type, as (element type, [(id, node ids)])
+type ElementRun<'a> = (u32, Vec<(u32, &'a [u32])>);
+
+fn element_type(node_count: usize) -> u32 {
+    if node_count == 3 {
+        TRIANGLE
+    } else {
+        QUADRANGLE
+    }
+}
+
+fn write_msh<W: Write>(writer: &mut W, hgrid: &Hgrid, version: GmshVersion) -> std::io::Result<()> {
+    let nodes = hgrid.nodes();
+    let elements = hgrid.elements();
+    let boundary_lines = boundary_lines(hgrid);
+    // Boundary line elements are numbered after the mesh elements
+    let first_line_tag = elements.ids().iter().max().map_or(1, |&max| max + 1);
+    let line_count: usize = boundary_lines.iter().map(|(_, lines)| lines.len()).sum();
+
+    writeln!(writer, "$MeshFormat")?;
+    writeln!(writer, "{} 0 8", version.as_str())?;
+    writeln!(writer, "$EndMeshFormat")?;
+
+    writeln!(writer, "$PhysicalNames")?;
+    writeln!(writer, "{}", boundary_lines.len() + 1)?;
+    writeln!(writer, "2 {} \"domain\"", DOMAIN_PHYSICAL_TAG)?;
+    for (index, (name, _)) in boundary_lines.iter().enumerate() {
+        writeln!(
+            writer,
+            "1 {} \"{}\"",
+            index as u32 + DOMAIN_PHYSICAL_TAG + 1,
+            name
+        )?;
+    }
+    writeln!(writer, "$EndPhysicalNames")?;
+
+    match version {
+        GmshVersion::V4_1 => {
+            let bounds = |node_ids: &mut dyn Iterator<Item = u32>| {
+                let mut bounds = [
+                    f64::INFINITY,
+                    f64::INFINITY,
+                    f64::NEG_INFINITY,
+                    f64::NEG_INFINITY,
+                ];
+                for (x, y) in node_ids.filter_map(|node_id| nodes.get_node(node_id)) {
+                    bounds = [
+                        bounds[0].min(x),
+                        bounds[1].min(y),
+                        bounds[2].max(x),
+                        bounds[3].max(y),
+                    ];
+                }
+                bounds
+            };
+            writeln!(writer, "$Entities")?;
+            writeln!(writer, "0 {} 1 0", boundary_lines.len())?;
+            for (index, (_, lines)) in boundary_lines.iter().enumerate() {
+                let [min_x, min_y, max_x, max_y] =
+                    bounds(&mut lines.iter().flat_map(|&(a, b)| [a, b]));
+                let tag = index as u32 + 1;
+                writeln!(
+                    writer,
+                    "{} {} {} 0 {} {} 0 1 {} 0",
+                    tag,
+                    min_x,
+                    min_y,
+                    max_x,
+                    max_y,
+                    tag + DOMAIN_PHYSICAL_TAG
+                )?;
+            }
+            let [min_x, min_y, max_x, max_y] = bounds(&mut nodes.ids().iter().copied());
+            writeln!(
+                writer,
+                "1 {} {} 0 {} {} 0 1 {} 0",
+                min_x, min_y, max_x, max_y, DOMAIN_PHYSICAL_TAG
+            )?;
+            writeln!(writer, "$EndEntities")?;
+
+            let ids = nodes.ids();
+            writeln!(writer, "$Nodes")?;
+            writeln!(
+                writer,
+                "1 {} {} {}",
+                ids.len(),
+                ids.iter().min().unwrap_or(&0),
+                ids.iter().max().unwrap_or(&0)
+            )?;
+            writeln!(writer, "2 1 0 {}", ids.len())?;
+            for node_id in ids {
+                writeln!(writer, "{}", node_id)?;
+            }
+            for (x, y) in hgrid.x().iter().zip(hgrid.y().iter()) {
+                writeln!(writer, "{} {} 0", x, y)?;
+            }
+            writeln!(writer, "$EndNodes")?;
+
+            // One block per run of same-type elements keeps the element order
+            let mut runs: Vec<ElementRun> = Vec::new();
+            for (elem_id, element) in elements.iter() {
+                let element_type = element_type(element.len());
+                match runs.last_mut() {
+                    Some((run_type, run)) if *run_type == element_type => {
+                        run.push((elem_id, element))
+                    }
+                    _ => runs.push((element_type, vec![(elem_id, element)])),
+                }
+            }
+            let all_tags = elements
+                .ids()
+                .iter()
+                .copied()
+                .chain(first_line_tag..first_line_tag + line_count as u32);
+            writeln!(writer, "$Elements")?;
+            writeln!(
+                writer,
+                "{} {} {} {}",
+                runs.len() + boundary_lines.len(),
+                elements.len() + line_count,
+                all_tags.clone().min().unwrap_or(0),
+                all_tags.max().unwrap_or(0)
+            )?;
+            for (element_type, run) in &runs {
+                writeln!(writer, "2 1 {} {}", element_type, run.len())?;
+                for (elem_id, element) in run {
+                    writeln!(writer, "{} {}", elem_id, join(element))?;
+                }
+            }
+            let mut line_tag = first_line_tag;
+            for (index, (_, lines)) in boundary_lines.iter().enumerate() {
+                writeln!(writer, "1 {} {} {}", index + 1, LINE, lines.len())?;
+                for (a, b) in lines {
+                    writeln!(writer, "{} {} {}", line_tag, a, b)?;
+                    line_tag += 1;
+                }
+            }
+            writeln!(writer, "$EndElements")?;
+        }
+        GmshVersion::V2_2 => {
+            writeln!(writer, "$Nodes")?;
+            writeln!(writer, "{}", nodes.len())?;
+            for ((node_id, x), y) in nodes
+                .ids()
+                .iter()
+                .zip(hgrid.x().iter())
+                .zip(hgrid.y().iter())
+            {
+                writeln!(writer, "{} {} {} 0", node_id, x, y)?;
+            }
+            writeln!(writer, "$EndNodes")?;
+
+            writeln!(writer, "$Elements")?;
+            writeln!(writer, "{}", elements.len() + line_count)?;
+            for (elem_id, element) in elements.iter() {
+                writeln!(
+                    writer,
+                    "{} {} 2 {} 1 {}",
+                    elem_id,
+                    element_type(element.len()),
+                    DOMAIN_PHYSICAL_TAG,
+                    join(element)
+                )?;
+            }
+            let mut line_tag = first_line_tag;
+            for (index, (_, lines)) in boundary_lines.iter().enumerate() {
+                let physical_tag = index as u32 + DOMAIN_PHYSICAL_TAG + 1;
+                for (a, b) in lines {
+                    writeln!(
+                        writer,
+                        "{} {} 2 {} {} {} {}",
+                        line_tag,
+                        LINE,
+                        physical_tag,
+                        index + 1,
+                        a,
+                        b
+                    )?;
+                    line_tag += 1;
+                }
+            }
+            writeln!(writer, "$EndElements")?;
+        }
+    }
+
+    if hgrid.has_depths() {
+        writeln!(writer, "$NodeData")?;
+        writeln!(writer, "1\n\"{}\"", DEPTH_ATTRIBUTE)?;
+        writeln!(writer, "1\n0.0")?;
+        writeln!(writer, "3\n0\n1\n{}", nodes.len())?;
+        for (node_id, depth) in nodes.ids().iter().zip(hgrid.depths_positive_down().iter()) {
+            writeln!(writer, "{} {}", node_id, depth)?;
+        }
+        writeln!(writer, "$EndNodeData")?;
+    }
+    Ok(())
+}
+
+fn join(node_ids: &[u32]) -> String {
+    node_ids
+        .iter()
+        .map(|node_id| node_id.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn write_to_path(path: &Path, hgrid: &Hgrid, version: GmshVersion) -> std::io::Result<()> {
+    let mut tmpfile = NamedTempFile::new()?;
+    log::debug!("Will write to tmpfile: {:?}", tmpfile);
+    let mut writer =
+        CompressedWriter::new(tmpfile.as_file_mut(), Compression::from_extension(path))?;
+    write_msh(&mut writer, hgrid, version)?;
+    writer.finish()?;
+    tmpfile.persist(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gr3;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    // A quad and two triangles with an open boundary, a land boundary and an
+    // island (1 = IBTYPE of island boundaries)
+    const GR3: &str = "test mesh
+3 6
+1 0 0 5
+2 1 0 6
+3 1 1 7
+4 0 1 8
+5 2 0.5 9
+6 2 1.5 10
+1 4 1 2 3 4
+2 3 2 5 3
+3 3 3 5 6
+1 = Number of open boundaries
+2 = Total number of open boundary nodes
+2 = Number of nodes for open boundary 1
+2
+5
+2 = number of land boundaries
+7 = Total number of land boundary nodes
+4 0 = Number of nodes for land boundary 1
+3
+4
+1
+2
+3 1 = Number of nodes for land boundary 2
+3
+5
+6";
+
+    fn gr3_lines(hgrid: &Hgrid, path: &Path) -> Vec<String> {
+        hgrid.write(path).unwrap();
+        let text = std::fs::read_to_string(path).unwrap();
+        // The description line is not stored in msh files
+        text.lines().skip(1).map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_gmsh_round_trip() {
+        let gr3 = gr3::parse_from_reader(BufReader::new(GR3.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+        let temp_dir = tempdir().unwrap();
+        let expected = gr3_lines(&hgrid, &temp_dir.path().join("expected.gr3"));
+        for version in [GmshVersion::V2_2, GmshVersion::V4_1] {
+            let path = temp_dir.path().join("hgrid.msh");
+            hgrid.write_gmsh(&path, version).unwrap();
+            let reloaded = Hgrid::try_from(&PathBuf::from(&path)).unwrap();
+            assert_eq!(
+                gr3_lines(&reloaded, &temp_dir.path().join("reloaded.gr3")),
+                expected,
+                "version {}",
+                version.as_str()
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_gmsh_physical_lines() {
+        let msh = "$MeshFormat
+2.2 0 8
+$EndMeshFormat
+$PhysicalNames
+3
+1 7 \"Open Ocean\"
+1 8 \"coastline\"
+1 9 \"land\"
+$EndPhysicalNames
+$Nodes
+4
+1 0 0 0
+2 1 0 0
+3 1 1 0
+4 0 1 0
+$EndNodes
+$Elements
+6
+1 15 2 0 1 1
+2 1 2 7 1 1 2
+3 1 2 9 2 3 4
+4 1 2 9 2 4 1
+5 2 2 0 1 1 2 3
+6 2 2 0 1 1 3 4
+$EndElements";
+        let parsed = parse_from_reader(BufReader::new(msh.as_bytes()), "test").unwrap();
+        assert_eq!(parsed.elements().unwrap().len(), 2);
+        assert_eq!(parsed.open_boundaries().unwrap(), vec![vec![1, 2]]);
+        assert_eq!(parsed.land_boundaries().unwrap(), vec![vec![3, 4, 1]]);
+        assert_eq!(parsed.nodes()[&1].1, None);
+    }
+
+    #[test]
+    fn test_multi_curve_group_without_depth() {
+        // Land group made of two separate curves, listed out of order
+        let msh = "$MeshFormat
+2.2 0 8
+$EndMeshFormat
+$PhysicalNames
+1
+1 9 \"land\"
+$EndPhysicalNames
+$Nodes
+6
+1 0 0 0
+2 1 0 0
+3 2 0 0
+4 2 1 0
+5 1 1 0
+6 0 1 0
+$EndNodes
+$Elements
+8
+1 1 2 9 1 1 2
+2 1 2 9 2 5 4
+3 1 2 9 1 2 3
+4 1 2 9 2 6 5
+5 2 2 0 1 1 2 5
+6 2 2 0 1 1 5 6
+7 2 2 0 1 2 3 4
+8 2 2 0 1 2 4 5
+$EndElements";
+        let parsed = parse_from_reader(BufReader::new(msh.as_bytes()), "test").unwrap();
+        assert_eq!(
+            parsed.land_boundaries().unwrap(),
+            vec![vec![1, 2, 3], vec![6, 5, 4]]
+        );
+
+        let hgrid = Hgrid::try_from(parsed).unwrap();
+        assert!(!hgrid.has_depths());
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.msh");
+        hgrid.write_gmsh(&path, GmshVersion::V4_1).unwrap();
+        assert!(!std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("$NodeData"));
+        let reloaded = Hgrid::try_from(&PathBuf::from(&path)).unwrap();
+        assert!(!reloaded.has_depths());
+        assert_eq!(
+            reloaded.boundaries().unwrap().land().unwrap().nodes_ids(),
+            &vec![vec![1, 2, 3], vec![6, 5, 4]]
+        );
+    }
+
+    #[test]
+    fn test_binary_msh_is_rejected() {
+        let msh = "$MeshFormat\n4.1 1 8\n$EndMeshFormat\n";
+        assert!(matches!(
+            parse_from_reader(BufReader::new(msh.as_bytes()), "test"),
+            Err(GmshError::Unsupported(_, _))
+        ));
+    }
+}
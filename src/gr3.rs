@@ -1,7 +1,14 @@
+use crate::boundaries::{
+    BarrierAttributes, BoundaryType, CulvertAttributes, CulvertSegment, ExternalBarrierSegment,
+    InternalBarrierSegment, WeirAttributes, FLOW_IBTYPE, ISLAND_IBTYPE, LAND_IBTYPE,
+};
+use crate::compression::{open_reader, CompressedWriter, Compression};
 use derive_builder::Builder;
 use linked_hash_map::LinkedHashMap;
-use log;
+use memmap2::Mmap;
 use proj::Proj;
+use rayon::prelude::*;
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
 use std::io::{prelude::*, BufReader};
@@ -9,6 +16,7 @@ use std::path::Path;
 use tempfile::Builder;
 use tempfile::NamedTempFile;
 use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt};
 use url::Url;
 
 #[derive(Builder, Default, Debug)]
@@ -26,8 +34,40 @@ pub struct Gr3ParserOutput {
     pub(crate) open_boundaries: Option<Vec<Vec<u32>>>,
     #[builder(default)]
     pub(crate) land_boundaries: Option<Vec<Vec<u32>>>,
+    /// IBTYPE flag of each land boundary segment (0 unless stated otherwise)
+    #[builder(default)]
+    pub(crate) land_boundaries_ibtypes: Option<Vec<u8>>,
     #[builder(default)]
     pub(crate) interior_boundaries: Option<Vec<Vec<u32>>>,
+    /// IBTYPE flag of each interior (island) boundary segment (1 unless stated otherwise)
+    #[builder(default)]
+    pub(crate) interior_boundaries_ibtypes: Option<Vec<u8>>,
+    /// Specified normal flow boundary segments (ADCIRC fort.14)
+    #[builder(default)]
+    pub(crate) flow_boundaries: Option<Vec<Vec<u32>>>,
+    #[builder(default)]
+    pub(crate) flow_boundaries_ibtypes: Option<Vec<u8>>,
+    /// External barrier segments (ADCIRC fort.14 IBTYPE 3/13/23)
+    #[builder(default)]
+    pub(crate) external_barrier_boundaries: Option<Vec<ExternalBarrierSegment>>,
+    /// Internal barrier (weir pair) segments (ADCIRC fort.14 IBTYPE 4/24/64)
+    #[builder(default)]
+    pub(crate) internal_barrier_boundaries: Option<Vec<InternalBarrierSegment>>,
+    /// Culvert segments (ADCIRC fort.14 IBTYPE 5/25)
+    #[builder(default)]
+    pub(crate) culvert_boundaries: Option<Vec<CulvertSegment>>,
+    /// Kind of each non-ocean boundary segment, in file order
+    #[builder(default)]
+    pub(crate) non_ocean_boundaries_order: Option<Vec<BoundaryType>>,
+    /// SMS 2DM `MESHNAME` card
+    #[builder(default)]
+    pub(crate) mesh_name: Option<String>,
+    /// SMS 2DM `NUM_MATERIALS_PER_ELEM` card
+    #[builder(default)]
+    pub(crate) num_materials_per_elem: Option<u32>,
+    /// Material ids trailing each SMS 2DM element card, keyed by element id
+    #[builder(default)]
+    pub(crate) element_materials: Option<LinkedHashMap<u32, Vec<u32>>>,
 }
 
 impl Gr3ParserOutput {
@@ -74,6 +114,193 @@ impl Gr3ParserOutput {
         self.interior_boundaries.clone()
     }
 
+    pub fn land_boundaries_ibtypes(&self) -> Option<Vec<u8>> {
+        self.land_boundaries_ibtypes.clone()
+    }
+
+    pub fn interior_boundaries_ibtypes(&self) -> Option<Vec<u8>> {
+        self.interior_boundaries_ibtypes.clone()
+    }
+
+    pub fn flow_boundaries(&self) -> Option<Vec<Vec<u32>>> {
+        self.flow_boundaries.clone()
+    }
+
+    pub fn flow_boundaries_ibtypes(&self) -> Option<Vec<u8>> {
+        self.flow_boundaries_ibtypes.clone()
+    }
+
+    pub fn external_barrier_boundaries(&self) -> Option<Vec<ExternalBarrierSegment>> {
+        self.external_barrier_boundaries.clone()
+    }
+
+    pub fn internal_barrier_boundaries(&self) -> Option<Vec<InternalBarrierSegment>> {
+        self.internal_barrier_boundaries.clone()
+    }
+
+    pub fn culvert_boundaries(&self) -> Option<Vec<CulvertSegment>> {
+        self.culvert_boundaries.clone()
+    }
+
+    pub fn mesh_name(&self) -> Option<&str> {
+        self.mesh_name.as_deref()
+    }
+
+    pub fn num_materials_per_elem(&self) -> Option<u32> {
+        self.num_materials_per_elem
+    }
+
+    pub fn element_materials(&self) -> Option<&LinkedHashMap<u32, Vec<u32>>> {
+        self.element_materials.as_ref()
+    }
+
+    pub fn non_ocean_boundaries_order(&self) -> Option<&[BoundaryType]> {
+        self.non_ocean_boundaries_order.as_deref()
+    }
+
+    /// Kind and per-kind index of each non-ocean boundary segment, in file
+    /// order when it is known and grouped by kind otherwise.
+    fn non_ocean_segments(&self) -> Vec<(BoundaryType, usize)> {
+        let count = |kind: BoundaryType| match kind {
+            BoundaryType::Open => 0,
+            BoundaryType::Land => self.land_boundaries.as_ref().map_or(0, Vec::len),
+            BoundaryType::Interior => self.interior_boundaries.as_ref().map_or(0, Vec::len),
+            BoundaryType::Flow => self.flow_boundaries.as_ref().map_or(0, Vec::len),
+            BoundaryType::ExternalBarrier => self
+                .external_barrier_boundaries
+                .as_ref()
+                .map_or(0, Vec::len),
+            BoundaryType::InternalBarrier => self
+                .internal_barrier_boundaries
+                .as_ref()
+                .map_or(0, Vec::len),
+            BoundaryType::Culvert => self.culvert_boundaries.as_ref().map_or(0, Vec::len),
+        };
+        let kinds = [
+            BoundaryType::Land,
+            BoundaryType::Interior,
+            BoundaryType::Flow,
+            BoundaryType::ExternalBarrier,
+            BoundaryType::InternalBarrier,
+            BoundaryType::Culvert,
+        ];
+        // Segments missing from the recorded order are written after it
+        let recorded = self.non_ocean_boundaries_order.iter().flatten().copied();
+        let grouped = kinds
+            .into_iter()
+            .flat_map(|kind| std::iter::repeat_n(kind, count(kind)));
+        let mut next = HashMap::<BoundaryType, usize>::new();
+        let mut segments = Vec::new();
+        for kind in recorded.chain(grouped) {
+            let index = next.entry(kind).or_default();
+            if *index < count(kind) {
+                segments.push((kind, *index));
+                *index += 1;
+            }
+        }
+        segments
+    }
+
+    /// Checks that barrier and culvert segments have one paired node and one
+    /// set of attributes per node, since they are written one line per node.
+    fn check_barrier_segments(&self) -> std::io::Result<()> {
+        let check = |name: &str, index: usize, nodes: usize, what: &str, found: usize| {
+            if found == nodes {
+                return Ok(());
+            }
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "{}_{} has {} nodes but {} {}",
+                    name,
+                    index + 1,
+                    nodes,
+                    found,
+                    what
+                ),
+            ))
+        };
+        for (index, segment) in self
+            .external_barrier_boundaries
+            .iter()
+            .flatten()
+            .enumerate()
+        {
+            let nodes = segment.nodes_ids.len();
+            check(
+                "external_barrier",
+                index,
+                nodes,
+                "attributes",
+                segment.attributes.len(),
+            )?;
+        }
+        for (index, segment) in self
+            .internal_barrier_boundaries
+            .iter()
+            .flatten()
+            .enumerate()
+        {
+            let nodes = segment.nodes_ids.len();
+            check(
+                "internal_barrier",
+                index,
+                nodes,
+                "paired nodes",
+                segment.paired_nodes_ids.len(),
+            )?;
+            check(
+                "internal_barrier",
+                index,
+                nodes,
+                "attributes",
+                segment.attributes.len(),
+            )?;
+        }
+        for (index, segment) in self.culvert_boundaries.iter().flatten().enumerate() {
+            let nodes = segment.nodes_ids.len();
+            check(
+                "culvert",
+                index,
+                nodes,
+                "paired nodes",
+                segment.paired_nodes_ids.len(),
+            )?;
+            check(
+                "culvert",
+                index,
+                nodes,
+                "attributes",
+                segment.attributes.len(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// IBTYPE flag of the land boundary segment at `index`.
+    fn land_boundary_ibtype(&self, index: usize) -> u8 {
+        self.land_boundaries_ibtypes
+            .as_ref()
+            .and_then(|ibtypes| ibtypes.get(index).copied())
+            .unwrap_or(LAND_IBTYPE)
+    }
+
+    /// IBTYPE flag of the interior boundary segment at `index`.
+    fn interior_boundary_ibtype(&self, index: usize) -> u8 {
+        self.interior_boundaries_ibtypes
+            .as_ref()
+            .and_then(|ibtypes| ibtypes.get(index).copied())
+            .unwrap_or(ISLAND_IBTYPE)
+    }
+
+    /// IBTYPE flag of the flow boundary segment at `index`.
+    fn flow_boundary_ibtype(&self, index: usize) -> u8 {
+        self.flow_boundaries_ibtypes
+            .as_ref()
+            .and_then(|ibtypes| ibtypes.get(index).copied())
+            .unwrap_or(FLOW_IBTYPE)
+    }
+
     // Reference accessors (avoid cloning)
     pub fn nodes_ref(&self) -> &LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> {
         &self.nodes
@@ -95,9 +322,24 @@ impl Gr3ParserOutput {
         self.interior_boundaries.as_ref()
     }
 
+    pub fn flow_boundaries_ref(&self) -> Option<&Vec<Vec<u32>>> {
+        self.flow_boundaries.as_ref()
+    }
+
+    pub fn external_barrier_boundaries_ref(&self) -> Option<&Vec<ExternalBarrierSegment>> {
+        self.external_barrier_boundaries.as_ref()
+    }
+
+    pub fn internal_barrier_boundaries_ref(&self) -> Option<&Vec<InternalBarrierSegment>> {
+        self.internal_barrier_boundaries.as_ref()
+    }
+
+    pub fn culvert_boundaries_ref(&self) -> Option<&Vec<CulvertSegment>> {
+        self.culvert_boundaries.as_ref()
+    }
+
     pub fn get_full_string(&self) -> String {
-        let mut lines = Vec::new();
-        lines.push(self.description().unwrap_or("".to_owned()));
+        let lines = [self.description().unwrap_or("".to_owned())];
         lines.join("\n")
     }
 }
@@ -173,6 +415,10 @@ impl fmt::Display for Gr3ParserOutput {
         if self.open_boundaries.is_some()
             || self.land_boundaries.is_some()
             || self.interior_boundaries.is_some()
+            || self.flow_boundaries.is_some()
+            || self.external_barrier_boundaries.is_some()
+            || self.internal_barrier_boundaries.is_some()
+            || self.culvert_boundaries.is_some()
         {
             // Handle open_boundaries if it's Some
             if let Some(open) = &self.open_boundaries {
@@ -216,7 +462,36 @@ impl fmt::Display for Gr3ParserOutput {
             if let Some(interior) = &self.interior_boundaries {
                 for interior_bnd in interior.iter() {
                     total_number_of_non_ocean_boundaries += 1;
-                    total_number_of_non_ocean_boundaries += interior_bnd.len();
+                    total_number_of_non_ocean_boundaries_nodes += interior_bnd.len();
+                }
+            }
+
+            if let Some(flow) = &self.flow_boundaries {
+                for flow_bnd in flow.iter() {
+                    total_number_of_non_ocean_boundaries += 1;
+                    total_number_of_non_ocean_boundaries_nodes += flow_bnd.len();
+                }
+            }
+
+            if let Some(external) = &self.external_barrier_boundaries {
+                for external_bnd in external.iter() {
+                    total_number_of_non_ocean_boundaries += 1;
+                    total_number_of_non_ocean_boundaries_nodes += external_bnd.nodes_ids.len();
+                }
+            }
+
+            // Weir pairs count both the front and the back face node
+            if let Some(internal) = &self.internal_barrier_boundaries {
+                for internal_bnd in internal.iter() {
+                    total_number_of_non_ocean_boundaries += 1;
+                    total_number_of_non_ocean_boundaries_nodes += 2 * internal_bnd.nodes_ids.len();
+                }
+            }
+
+            if let Some(culverts) = &self.culvert_boundaries {
+                for culvert_bnd in culverts.iter() {
+                    total_number_of_non_ocean_boundaries += 1;
+                    total_number_of_non_ocean_boundaries_nodes += 2 * culvert_bnd.nodes_ids.len();
                 }
             }
             lines.push(format!(
@@ -228,35 +503,125 @@ impl fmt::Display for Gr3ParserOutput {
                 total_number_of_non_ocean_boundaries_nodes
             ));
 
-            if let Some(land) = &self.land_boundaries {
-                for (local_index, this_land_bound) in land.iter().enumerate() {
-                    let fortran_index = local_index + 1;
-                    lines.push(format!(
-                        "{} ! number of nodes for land_boundary_{}",
-                        this_land_bound.len(),
-                        fortran_index
-                    ));
-                    for this_land_bound_index in this_land_bound.iter() {
-                        let this_land_bound_fortran_index =
-                            fort_index_from_node_id[this_land_bound_index];
-                        lines.push(format!("{}", this_land_bound_fortran_index));
+            for (kind, local_index) in self.non_ocean_segments() {
+                let fortran_index = local_index + 1;
+                match kind {
+                    BoundaryType::Land => {
+                        // Present since the segment was just listed
+                        let this_land_bound = &self.land_boundaries.as_ref().unwrap()[local_index];
+                        lines.push(format!(
+                            "{} {} ! number of nodes for land_boundary_{}",
+                            this_land_bound.len(),
+                            self.land_boundary_ibtype(local_index),
+                            fortran_index
+                        ));
+                        for this_land_bound_index in this_land_bound.iter() {
+                            let this_land_bound_fortran_index =
+                                fort_index_from_node_id[this_land_bound_index];
+                            lines.push(format!("{}", this_land_bound_fortran_index));
+                        }
                     }
-                }
-            };
-
-            if let Some(interior) = &self.interior_boundaries {
-                for (local_index, this_interior_bound) in interior.iter().enumerate() {
-                    let fortran_index = local_index + 1;
-                    lines.push(format!(
-                        "{} ! number of nodes for interior_boundary_{}",
-                        this_interior_bound.len(),
-                        fortran_index
-                    ));
-                    for this_interior_bound_index in this_interior_bound.iter() {
-                        let this_interior_bound_fortran_index =
-                            fort_index_from_node_id[this_interior_bound_index];
-                        lines.push(format!("{}", this_interior_bound_fortran_index));
+                    BoundaryType::Interior => {
+                        let this_interior_bound =
+                            &self.interior_boundaries.as_ref().unwrap()[local_index];
+                        lines.push(format!(
+                            "{} {} ! number of nodes for interior_boundary_{}",
+                            this_interior_bound.len(),
+                            self.interior_boundary_ibtype(local_index),
+                            fortran_index
+                        ));
+                        for this_interior_bound_index in this_interior_bound.iter() {
+                            let this_interior_bound_fortran_index =
+                                fort_index_from_node_id[this_interior_bound_index];
+                            lines.push(format!("{}", this_interior_bound_fortran_index));
+                        }
                     }
+                    BoundaryType::Flow => {
+                        let this_flow_bound = &self.flow_boundaries.as_ref().unwrap()[local_index];
+                        lines.push(format!(
+                            "{} {} ! number of nodes for flow_boundary_{}",
+                            this_flow_bound.len(),
+                            self.flow_boundary_ibtype(local_index),
+                            fortran_index
+                        ));
+                        for this_flow_bound_index in this_flow_bound.iter() {
+                            lines.push(format!(
+                                "{}",
+                                fort_index_from_node_id[this_flow_bound_index]
+                            ));
+                        }
+                    }
+                    BoundaryType::ExternalBarrier => {
+                        let segment =
+                            &self.external_barrier_boundaries.as_ref().unwrap()[local_index];
+                        lines.push(format!(
+                            "{} {} ! number of nodes for external_barrier_{}",
+                            segment.nodes_ids.len(),
+                            segment.ibtype,
+                            fortran_index
+                        ));
+                        for (node_id, attrs) in segment.nodes_ids.iter().zip(&segment.attributes) {
+                            lines.push(format!(
+                                "{} {} {}",
+                                fort_index_from_node_id[node_id],
+                                attrs.height,
+                                attrs.supercritical_coefficient
+                            ));
+                        }
+                    }
+                    BoundaryType::InternalBarrier => {
+                        let segment =
+                            &self.internal_barrier_boundaries.as_ref().unwrap()[local_index];
+                        lines.push(format!(
+                            "{} {} ! number of nodes for internal_barrier_{}",
+                            segment.nodes_ids.len(),
+                            segment.ibtype,
+                            fortran_index
+                        ));
+                        for ((node_id, paired_node_id), attrs) in segment
+                            .nodes_ids
+                            .iter()
+                            .zip(&segment.paired_nodes_ids)
+                            .zip(&segment.attributes)
+                        {
+                            lines.push(format!(
+                                "{} {} {} {} {}",
+                                fort_index_from_node_id[node_id],
+                                fort_index_from_node_id[paired_node_id],
+                                attrs.height,
+                                attrs.subcritical_coefficient,
+                                attrs.supercritical_coefficient
+                            ));
+                        }
+                    }
+                    BoundaryType::Culvert => {
+                        let segment = &self.culvert_boundaries.as_ref().unwrap()[local_index];
+                        lines.push(format!(
+                            "{} {} ! number of nodes for culvert_{}",
+                            segment.nodes_ids.len(),
+                            segment.ibtype,
+                            fortran_index
+                        ));
+                        for ((node_id, paired_node_id), attrs) in segment
+                            .nodes_ids
+                            .iter()
+                            .zip(&segment.paired_nodes_ids)
+                            .zip(&segment.attributes)
+                        {
+                            lines.push(format!(
+                                "{} {} {} {} {} {} {} {}",
+                                fort_index_from_node_id[node_id],
+                                fort_index_from_node_id[paired_node_id],
+                                attrs.weir.height,
+                                attrs.weir.subcritical_coefficient,
+                                attrs.weir.supercritical_coefficient,
+                                attrs.pipe_height,
+                                attrs.pipe_coefficient,
+                                attrs.pipe_diameter
+                            ));
+                        }
+                    }
+                    BoundaryType::Open => unreachable!("open boundaries are written above"),
                 }
             }
         }
@@ -284,8 +649,9 @@ pub enum Gr3ParserError {
 
 pub fn parse_from_path_ref(path: &Path) -> Result<Gr3ParserOutput, Gr3ParserError> {
     let fname = &path.display().to_string();
-    let file = match File::open(&fname) {
-        Ok(file) => file,
+    // Decompresses .gz/.zst/.bz2 input on the fly
+    let reader = match open_reader(path) {
+        Ok(reader) => reader,
         Err(e) => {
             return Err(Gr3ParserError::IoError(format!(
                 "Failed to open {}: {}",
@@ -293,7 +659,6 @@ pub fn parse_from_path_ref(path: &Path) -> Result<Gr3ParserOutput, Gr3ParserErro
             )));
         }
     };
-    let reader = BufReader::new(file);
     parse_from_reader(reader, fname)
 }
 
@@ -305,7 +670,54 @@ pub fn parse_from_url(url: &Url) -> Result<Gr3ParserOutput, Gr3ParserError> {
         .text()
         .map_err(|err| Gr3ParserError::RequestFromUrlError(url.to_string(), err.to_string()))?;
     let reader = BufReader::new(body.as_bytes());
-    parse_from_reader(reader, &url.to_string())
+    parse_from_reader(reader, url.as_str())
+}
+
+/// Async counterpart of [`parse_from_url`], safe to call from inside a tokio
+/// runtime. The response body is parsed as it arrives.
+pub async fn parse_from_url_async(url: &Url) -> Result<Gr3ParserOutput, Gr3ParserError> {
+    let request_error =
+        |err: reqwest::Error| Gr3ParserError::RequestFromUrlError(url.to_string(), err.to_string());
+    let mut response = reqwest::get(url.to_string())
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(request_error)?;
+    let (mut writer, reader) = tokio::io::duplex(64 * 1024);
+    let download = async move {
+        while let Some(chunk) = response.chunk().await.map_err(request_error)? {
+            // The parser dropped its end after an error; that error is reported below.
+            if writer.write_all(&chunk).await.is_err() {
+                break;
+            }
+        }
+        Ok::<(), Gr3ParserError>(())
+    };
+    let (downloaded, parsed) = tokio::join!(
+        download,
+        parse_from_async_reader(tokio::io::BufReader::new(reader), url.as_str())
+    );
+    downloaded?;
+    parsed
+}
+
+/// Async counterpart of [`parse_from_reader`] for any [`AsyncBufRead`].
+///
+/// Nodes and elements are parsed line by line as they are read; the boundary
+/// section is buffered before parsing.
+pub async fn parse_from_async_reader<R: AsyncBufRead + Unpin>(
+    reader: R,
+    fname: &str,
+) -> Result<Gr3ParserOutput, Gr3ParserError> {
+    let mut lines = reader.lines();
+    let mut parser = LineParser::new(fname);
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| Gr3ParserError::LineReadError(fname.to_string(), e.to_string()))?
+    {
+        parser.push(line)?;
+    }
+    parser.finish()
 }
 
 use gag::Gag;
@@ -332,10 +744,8 @@ fn get_crs_from_description(description: &str) -> Option<String> {
     // This avoids expensive multi-pass parsing for typical gr3 files
     for word in description.split_whitespace() {
         let word_upper = word.to_uppercase();
-        if word_upper.starts_with("EPSG:") {
-            if proj_new_silent(word).is_ok() {
-                return Some(word.to_string());
-            }
+        if word_upper.starts_with("EPSG:") && proj_new_silent(word).is_ok() {
+            return Some(word.to_string());
         }
     }
 
@@ -419,7 +829,281 @@ pub fn parse_from_reader<R: Read>(
     reader: BufReader<R>,
     fname: &str, // Passed separately for error messages
 ) -> Result<Gr3ParserOutput, Gr3ParserError> {
-    let mut buf = reader.lines();
+    let mut parser = LineParser::new(fname);
+    for line in reader.lines() {
+        let line =
+            line.map_err(|e| Gr3ParserError::LineReadError(fname.to_string(), e.to_string()))?;
+        parser.push(line)?;
+    }
+    parser.finish()
+}
+
+/// Section of a gr3 file that the next line belongs to
+enum Section {
+    Description,
+    Counts(String),
+    Nodes,
+    Elements,
+    Boundaries,
+}
+
+/// Line-by-line gr3 parser, fed by both the sync and async readers.
+///
+/// Nodes and elements are parsed as their lines are pushed; the boundary
+/// section is buffered and parsed by [`LineParser::finish`].
+struct LineParser<'a> {
+    fname: &'a str,
+    section: Section,
+    description: String,
+    crs: Option<String>,
+    ne: usize,
+    np: usize,
+    /// Lines pushed so far in the node or element section
+    read: usize,
+    nodemap: LinkedHashMap<u32, NodeRow>,
+    elemmap: LinkedHashMap<u32, Vec<u32>>,
+    boundary_lines: Vec<String>,
+}
+
+impl<'a> LineParser<'a> {
+    fn new(fname: &'a str) -> Self {
+        LineParser {
+            fname,
+            section: Section::Description,
+            description: String::new(),
+            crs: None,
+            ne: 0,
+            np: 0,
+            read: 0,
+            nodemap: LinkedHashMap::new(),
+            elemmap: LinkedHashMap::new(),
+            boundary_lines: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, line: String) -> Result<(), Gr3ParserError> {
+        match std::mem::replace(&mut self.section, Section::Boundaries) {
+            Section::Description => self.section = Section::Counts(line),
+            Section::Counts(description) => {
+                let mut header = [Ok(description), Ok(line)].into_iter();
+                let (description, crs, ne, np) = parse_header(&mut header, self.fname)?;
+                (self.description, self.crs) = (description, crs);
+                (self.ne, self.np) = (ne as usize, np as usize);
+                self.section = Section::Nodes;
+                self.skip_empty_sections();
+            }
+            Section::Nodes => {
+                self.read += 1;
+                let (node_id, data) = parse_node_line(&line, self.read, self.fname)?;
+                self.nodemap.insert(node_id, data);
+                self.section = Section::Nodes;
+                self.skip_empty_sections();
+            }
+            Section::Elements => {
+                self.read += 1;
+                let (element_id, element) = parse_element_line(&line, self.read, self.fname)?;
+                self.elemmap.insert(element_id, element);
+                self.section = Section::Elements;
+                self.skip_empty_sections();
+            }
+            Section::Boundaries => self.boundary_lines.push(line),
+        }
+        Ok(())
+    }
+
+    /// Move past the node and element sections once all their lines are read.
+    fn skip_empty_sections(&mut self) {
+        if matches!(self.section, Section::Nodes) && self.read == self.np {
+            log::debug!("Start reading elements...");
+            (self.section, self.read) = (Section::Elements, 0);
+        }
+        if matches!(self.section, Section::Elements) && self.read == self.ne {
+            log::debug!("Done reading elements!");
+            self.section = Section::Boundaries;
+        }
+    }
+
+    fn finish(self) -> Result<Gr3ParserOutput, Gr3ParserError> {
+        let fname = self.fname;
+        let missing = |message: String| Gr3ParserError::LineReadError(fname.to_string(), message);
+        match self.section {
+            // An incomplete header, which `parse_header` reports
+            Section::Description => {
+                parse_header(&mut std::iter::empty(), fname)?;
+            }
+            Section::Counts(description) => {
+                parse_header(&mut std::iter::once(Ok(description)), fname)?;
+            }
+            Section::Nodes => {
+                return Err(missing(format!(
+                    "Expected {} lines with node data but found only {}.",
+                    self.np,
+                    self.nodemap.len()
+                )))
+            }
+            Section::Elements => {
+                return Err(missing(format!(
+                    "Expected {} lines with element data but found only {}.",
+                    self.ne,
+                    self.elemmap.len()
+                )))
+            }
+            Section::Boundaries => {}
+        }
+        parse_boundaries(
+            &mut self.boundary_lines.into_iter().map(Ok),
+            fname,
+            self.description,
+            self.crs,
+            self.nodemap,
+            self.elemmap,
+        )
+    }
+}
+
+/// Lines per work unit when parsing node and element blocks in parallel.
+const PARALLEL_CHUNK_LINES: usize = 64 * 1024;
+
+/// Parse a gr3 file by mapping it into memory and parsing the node and element
+/// blocks on the rayon thread pool.
+///
+/// Compressed files are decompressed into memory first. The output and error
+/// messages are the same as [`parse_from_path_ref`].
+pub fn parse_parallel_from_path_ref(path: &Path) -> Result<Gr3ParserOutput, Gr3ParserError> {
+    let fname = &path.display().to_string();
+    let file = File::open(path)
+        .map_err(|e| Gr3ParserError::IoError(format!("Failed to open {}: {}", fname, e)))?;
+    // SAFETY: the map is only read while parsing, which copies everything out.
+    let mmap = unsafe { Mmap::map(&file) }
+        .map_err(|e| Gr3ParserError::IoError(format!("Failed to map {}: {}", fname, e)))?;
+    let compressed = Compression::from_magic_bytes(&mmap).is_some()
+        || !matches!(Compression::from_extension(path), Compression::None);
+    if !compressed {
+        return parse_from_bytes_parallel(&mmap, fname);
+    }
+    let mut bytes = Vec::new();
+    open_reader(path)
+        .and_then(|mut reader| reader.read_to_end(&mut bytes))
+        .map_err(|e| Gr3ParserError::IoError(format!("Failed to open {}: {}", fname, e)))?;
+    parse_from_bytes_parallel(&bytes, fname)
+}
+
+/// Parallel counterpart of [`parse_from_reader`] for a gr3 file held in memory.
+pub fn parse_from_bytes_parallel(
+    bytes: &[u8],
+    fname: &str,
+) -> Result<Gr3ParserOutput, Gr3ParserError> {
+    parse_from_bytes_chunked(bytes, fname, PARALLEL_CHUNK_LINES)
+}
+
+fn parse_from_bytes_chunked(
+    bytes: &[u8],
+    fname: &str,
+    chunk_lines: usize,
+) -> Result<Gr3ParserOutput, Gr3ParserError> {
+    let lines = split_lines(bytes);
+    let mut header = lines.iter().take(2).map(|line| line_to_string(line));
+    let (description, crs, ne, np) = parse_header(&mut header, fname)?;
+    let (np, ne) = (np as usize, ne as usize);
+    log::debug!("Start reading nodes...");
+    let node_lines = &lines[2.min(lines.len())..(2 + np).min(lines.len())];
+    let node_chunks: Vec<Result<Vec<_>, Gr3ParserError>> = node_lines
+        .par_chunks(chunk_lines)
+        .enumerate()
+        .map(|(chunk, block)| {
+            block
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let line = line_to_string(line).map_err(|e| {
+                        Gr3ParserError::LineReadError(fname.to_string(), e.to_string())
+                    })?;
+                    parse_node_line(&line, chunk * chunk_lines + i + 1, fname)
+                })
+                .collect()
+        })
+        .collect();
+    // Inserting in file order keeps the first error and duplicate-id handling
+    // the same as the sequential parser.
+    let mut nodemap = LinkedHashMap::new();
+    for chunk in node_chunks {
+        nodemap.extend(chunk?);
+    }
+    if node_lines.len() < np {
+        return Err(Gr3ParserError::LineReadError(
+            fname.to_string(),
+            format!(
+                "Expected {} lines with node data but found only {}.",
+                np,
+                nodemap.len()
+            ),
+        ));
+    }
+    log::debug!("Start reading elements...");
+    let element_lines = &lines[2 + np..(2 + np + ne).min(lines.len())];
+    let element_chunks: Vec<Result<Vec<_>, Gr3ParserError>> = element_lines
+        .par_chunks(chunk_lines)
+        .enumerate()
+        .map(|(chunk, block)| {
+            block
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let line = line_to_string(line).map_err(|e| {
+                        Gr3ParserError::LineReadError(fname.to_string(), e.to_string())
+                    })?;
+                    parse_element_line(&line, chunk * chunk_lines + i + 1, fname)
+                })
+                .collect()
+        })
+        .collect();
+    let mut elemmap = LinkedHashMap::new();
+    for chunk in element_chunks {
+        elemmap.extend(chunk?);
+    }
+    if element_lines.len() < ne {
+        return Err(Gr3ParserError::LineReadError(
+            fname.to_string(),
+            format!(
+                "Expected {} lines with element data but found only {}.",
+                ne,
+                elemmap.len()
+            ),
+        ));
+    }
+    log::debug!("Done reading elements!");
+    let mut rest = lines[2 + np + ne..].iter().map(|line| line_to_string(line));
+    parse_boundaries(&mut rest, fname, description, crs, nodemap, elemmap)
+}
+
+/// Split on `\n` and drop a trailing `\r`, the same way `BufRead::lines` does.
+fn split_lines(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut lines: Vec<&[u8]> = bytes
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .collect();
+    if bytes.is_empty() || bytes.ends_with(b"\n") {
+        lines.pop();
+    }
+    lines
+}
+
+fn line_to_string(line: &[u8]) -> std::io::Result<String> {
+    std::str::from_utf8(line).map(str::to_string).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    })
+}
+
+/// Parse the description line and the NE NP line.
+///
+/// Returns the description without its CRS, the CRS, NE and NP.
+fn parse_header<I: Iterator<Item = std::io::Result<String>>>(
+    buf: &mut I,
+    fname: &str,
+) -> Result<(String, Option<String>, u32, u32), Gr3ParserError> {
     let description_raw_str: String = match buf.next() {
         Some(Ok(line)) => line,
         Some(Err(e)) => {
@@ -484,207 +1168,186 @@ pub fn parse_from_reader<R: Read>(
             ));
         }
     };
-    let mut nodemap = LinkedHashMap::new();
-    for _ in 0..np {
-        let line = match buf.next() {
-            Some(Ok(line)) => line,
-            Some(Err(e)) => {
-                return Err(Gr3ParserError::LineReadError(
-                    fname.to_string(),
-                    e.to_string(),
-                ));
-            }
-            None => {
-                return Err(Gr3ParserError::LineReadError(
-                    fname.to_string(),
-                    format!(
-                        "Expected {} lines with node data but found only {}.",
-                        np,
-                        nodemap.len()
-                    ),
-                ))
-            }
-        };
-        let mut line = line.split_whitespace();
-        let node_id: u32 =  match line.next() {
-            Some(node_id_str) => match node_id_str.parse::<u32>() {
-                Ok(node_id) => node_id,
-                Err(_) => {
-                    return Err(
-                        Gr3ParserError::LineReadError(
-                            fname.to_string(),
-                            format!("Expected first item in line {} (node id) to be castable to an u32 but found {}.", nodemap.len() + 1, node_id_str)
-                            )
-                        )
-                }
-            },
-            None => {
+    Ok((description, crs, ne, np))
+}
+
+type NodeRow = (Vec<f64>, Option<Vec<f64>>);
+
+/// Parse a node line; `line_number` counts from 1 within the node block.
+fn parse_node_line(
+    line: &str,
+    line_number: usize,
+    fname: &str,
+) -> Result<(u32, NodeRow), Gr3ParserError> {
+    let mut line = line.split_whitespace();
+    let node_id: u32 =  match line.next() {
+        Some(node_id_str) => match node_id_str.parse::<u32>() {
+            Ok(node_id) => node_id,
+            Err(_) => {
                 return Err(
                     Gr3ParserError::LineReadError(
                         fname.to_string(),
-                        format!("Expected line {} to contain node data but found an empty line.", nodemap.len() + 1)
+                        format!("Expected first item in line {} (node id) to be castable to an u32 but found {}.", line_number, node_id_str)
                         )
                     )
             }
-        };
-        let mut coords = Vec::new();
-        let x: f64 = match line.next() {
-            Some(x_str) => match x_str.parse::<f64>() {
-                Ok(x) => x,
-                Err(_) => {
-                    return Err(
-                        Gr3ParserError::LineReadError(
-                            fname.to_string(),
-                            format!("Expected second item in line {} (node x coordinate) to be castable to an f64 but found {}.", nodemap.len() + 1, x_str)
-                            )
-                        )
-                }
-            },
-            None => {
+        },
+        None => {
+            return Err(
+                Gr3ParserError::LineReadError(
+                    fname.to_string(),
+                    format!("Expected line {} to contain node data but found an empty line.", line_number)
+                    )
+                )
+        }
+    };
+    let mut coords = Vec::new();
+    let x: f64 = match line.next() {
+        Some(x_str) => match x_str.parse::<f64>() {
+            Ok(x) => x,
+            Err(_) => {
                 return Err(
                     Gr3ParserError::LineReadError(
-                            fname.to_string(),
-                            format!("Expected line {} to contain node data but found only one item.", nodemap.len() + 1)
+                        fname.to_string(),
+                        format!("Expected second item in line {} (node x coordinate) to be castable to an f64 but found {}.", line_number, x_str)
                         )
                     )
             }
-        };
-        let y: f64 = match line.next() {
-            Some(y_str) => match y_str.parse::<f64>() {
-                Ok(y) => y,
-                Err(_) => {
-                    return Err(
-                        Gr3ParserError::LineReadError(
-                            fname.to_string(),
-                            format!("Expected third item in line {} (node y coordinate) to be castable to an f64 but found {}.", nodemap.len() + 1, y_str)
-                            )
-                        )
-                }
-            },
-            None => {
+        },
+        None => {
+            return Err(
+                Gr3ParserError::LineReadError(
+                        fname.to_string(),
+                        format!("Expected line {} to contain node data but found only one item.", line_number)
+                    )
+                )
+        }
+    };
+    let y: f64 = match line.next() {
+        Some(y_str) => match y_str.parse::<f64>() {
+            Ok(y) => y,
+            Err(_) => {
                 return Err(
                     Gr3ParserError::LineReadError(
                         fname.to_string(),
-                        format!("Expected line {} to contain node data but found only two items.", nodemap.len() + 1)
+                        format!("Expected third item in line {} (node y coordinate) to be castable to an f64 but found {}.", line_number, y_str)
                         )
-
                     )
             }
+        },
+        None => {
+            return Err(
+                Gr3ParserError::LineReadError(
+                    fname.to_string(),
+                    format!("Expected line {} to contain node data but found only two items.", line_number)
+                    )
+
+                )
+        }
+    };
+    coords.push(x);
+    coords.push(y);
+    let mut values = Vec::new();
+    for val in line {
+        let val: f64 = match val.parse() {
+            Ok(val) => val,
+            Err(_) => {
+                return Err(
+                    Gr3ParserError::LineReadError(
+                        fname.to_string(),
+                        format!("Expected item in line {} (node value) to be castable to an f64 but found {}.", line_number, val)
+                    ));
+            }
         };
-        coords.push(x);
-        coords.push(y);
-        let mut values = Vec::new();
-        for val in line {
-            let val: f64 = match val.parse() {
-                Ok(val) => val,
+        values.push(val);
+    }
+    Ok((node_id, (coords, Some(values))))
+}
+
+/// Parse an element line; `line_number` counts from 1 within the element block.
+fn parse_element_line(
+    line: &str,
+    line_number: usize,
+    fname: &str,
+) -> Result<(u32, Vec<u32>), Gr3ParserError> {
+    let mut line = line.split_whitespace();
+    let element_id: u32 = match line.next() {
+            Some(element_id_str) => match element_id_str.parse::<u32>() {
+                Ok(element_id) => element_id,
                 Err(_) => {
                     return Err(
                         Gr3ParserError::LineReadError(
                             fname.to_string(),
-                            format!("Expected item in line {} (node value) to be castable to an f64 but found {}.", nodemap.len() + 1, val)
-                        ));
-                }
-            };
-            values.push(val);
-        }
-        let data = (coords, Some(values));
-        nodemap.insert(node_id, data);
-    }
-    // let nodes = Nodes::new(nodemap, crs);
-    log::debug!("Start reading elements...");
-    let mut elemmap = LinkedHashMap::new();
-    for _ in 0..ne {
-        let line = match buf.next() {
-            Some(Ok(line)) => line,
-            Some(Err(e)) => {
-                return Err(Gr3ParserError::LineReadError(
-                    fname.to_string(),
-                    e.to_string(),
-                ));
-            }
-            None => {
-                return Err(Gr3ParserError::LineReadError(
-                    fname.to_string(),
-                    format!(
-                        "Expected {} lines with element data but found only {}.",
-                        ne,
-                        elemmap.len()
-                    ),
-                ))
-            }
-        };
-        let mut line = line.split_whitespace();
-        let element_id: u32 = match line.next() {
-                Some(element_id_str) => match element_id_str.parse::<u32>() {
-                    Ok(element_id) => element_id,
-                    Err(_) => {
-                        return Err(
-                            Gr3ParserError::LineReadError(
-                                fname.to_string(),
-                                format!("Expected first item in line {} (element id) to be castable to an u32 but found {}.", elemmap.len() + 1, element_id_str)
-                                )
-                            )
-                    }
-                },
-                None => {
-                    return Err(
-                        Gr3ParserError::LineReadError(fname.to_string(),
-                        format!("Expected line {} to contain element data but found an empty line.", elemmap.len() + 1)
-                            )
-                        )
-                }
-            };
-        let element_len: u8 = match line.next() {
-                Some(element_len_str) => match element_len_str.parse::<u8>() {
-                    Ok(element_len) => element_len,
-                    Err(_) => {
-                        return Err(
-                            Gr3ParserError::LineReadError(
-                                fname.to_string(),
-                                format!("Expected second item in line {} (element length) to be castable to an u8 but found {}.", elemmap.len() + 1, element_len_str)
-                                )
-                            )
-                    }
-                },
-                None => {
-                    return Err(
-                        Gr3ParserError::LineReadError(fname.to_string(),
-                        format!("Expected line {} to contain element data but found only one item.", elemmap.len() + 1)
+                            format!("Expected first item in line {} (element id) to be castable to an u32 but found {}.", line_number, element_id_str)
                             )
                         )
                 }
-            };
-        let mut element_vec = Vec::<u32>::new();
-        for _ in 0..element_len {
-            let line = match line.next() {
-                Some(line) => match line.parse::<u32>() {
-                    Ok(line) => line,
-                    Err(_) => {
-                        return Err(
-                            Gr3ParserError::LineReadError(
-                                fname.to_string(),
-                                format!("Expected item in line {} (element node id) to be castable to an u32 but found {}.", elemmap.len() + 1, line)
-                            )
+            },
+            None => {
+                return Err(
+                    Gr3ParserError::LineReadError(fname.to_string(),
+                    format!("Expected line {} to contain element data but found an empty line.", line_number)
                         )
-                    }
-                },
-                None => {
+                    )
+            }
+        };
+    let element_len: u8 = match line.next() {
+            Some(element_len_str) => match element_len_str.parse::<u8>() {
+                Ok(element_len) => element_len,
+                Err(_) => {
                     return Err(
                         Gr3ParserError::LineReadError(
                             fname.to_string(),
-                            format!("Expected line {} to contain element data but found only {} items.", elemmap.len() + 1, element_vec.len())
+                            format!("Expected second item in line {} (element length) to be castable to an u8 but found {}.", line_number, element_len_str)
                             )
                         )
                 }
-            };
-            element_vec.push(line);
-        }
-        elemmap.insert(element_id, element_vec);
+            },
+            None => {
+                return Err(
+                    Gr3ParserError::LineReadError(fname.to_string(),
+                    format!("Expected line {} to contain element data but found only one item.", line_number)
+                        )
+                    )
+            }
+        };
+    let mut element_vec = Vec::<u32>::new();
+    for _ in 0..element_len {
+        let line = match line.next() {
+            Some(line) => match line.parse::<u32>() {
+                Ok(line) => line,
+                Err(_) => {
+                    return Err(
+                        Gr3ParserError::LineReadError(
+                            fname.to_string(),
+                            format!("Expected item in line {} (element node id) to be castable to an u32 but found {}.", line_number, line)
+                        )
+                    )
+                }
+            },
+            None => {
+                return Err(
+                    Gr3ParserError::LineReadError(
+                        fname.to_string(),
+                        format!("Expected line {} to contain element data but found only {} items.", line_number, element_vec.len())
+                        )
+                    )
+            }
+        };
+        element_vec.push(line);
     }
-    log::debug!("Done reading elements!");
-    // let elements = Elements::new(&nodes, elemmap).map_err(|e| Gr3ParserError::ElementsConstructorError(e))?;
-    // log::debug!("Done crating elements object");
-    // parse boundaries
+    Ok((element_id, element_vec))
+}
+
+/// Parse the boundary section that follows the element block.
+fn parse_boundaries<I: Iterator<Item = std::io::Result<String>>>(
+    buf: &mut I,
+    fname: &str,
+    description: String,
+    crs: Option<String>,
+    nodemap: LinkedHashMap<u32, NodeRow>,
+    elemmap: LinkedHashMap<u32, Vec<u32>>,
+) -> Result<Gr3ParserOutput, Gr3ParserError> {
     let line = match buf.next() {
         Some(Ok(line)) => line,
         Some(Err(e)) => {
@@ -926,8 +1589,16 @@ pub fn parse_from_reader<R: Read>(
         }
     };
     let mut land_boundaries_vec = Vec::<Vec<u32>>::new();
+    let mut land_boundaries_ibtypes = Vec::<u8>::new();
     let mut interior_boundaries_vec = Vec::<Vec<u32>>::new();
-    for _ in 0..number_of_land_boundaries {
+    let mut interior_boundaries_ibtypes = Vec::<u8>::new();
+    let mut flow_boundaries_vec = Vec::<Vec<u32>>::new();
+    let mut flow_boundaries_ibtypes = Vec::<u8>::new();
+    let mut external_barriers_vec = Vec::<ExternalBarrierSegment>::new();
+    let mut internal_barriers_vec = Vec::<InternalBarrierSegment>::new();
+    let mut culverts_vec = Vec::<CulvertSegment>::new();
+    let mut non_ocean_boundaries_order = Vec::<BoundaryType>::new();
+    for land_boundary_index in 0..number_of_land_boundaries {
         let line = match buf.next() {
             Some(Ok(line)) => line,
             Some(Err(e)) => {
@@ -941,8 +1612,7 @@ pub fn parse_from_reader<R: Read>(
                     fname.to_string(),
                     format!(
                         "Expected {} lines with land boundary data but found only {}.",
-                        number_of_land_boundaries,
-                        land_boundaries_vec.len()
+                        number_of_land_boundaries, land_boundary_index
                     ),
                 ))
             }
@@ -977,7 +1647,24 @@ pub fn parse_from_reader<R: Read>(
             ))
         };
 
+        let boundary_type = match BoundaryType::from_ibtype(boundary_id_type) {
+            Some(boundary_type) => boundary_type,
+            None => return Err(Gr3ParserError::LineReadError(
+                fname.to_string(),
+                format!("Error reading gr3 file: {}. Expected boundary_id_type to be a known IBTYPE (land, island, flow, barrier, weir or culvert) but found {}.", fname, boundary_id_type)
+            ))
+        };
+        // Node lines carry extra columns for barrier-type boundaries
+        let (number_of_ids, number_of_values) = match boundary_type {
+            BoundaryType::ExternalBarrier => (1, 2),
+            BoundaryType::InternalBarrier => (2, 3),
+            BoundaryType::Culvert => (2, 6),
+            _ => (1, 0),
+        };
+
         let mut this_boundary_vec = Vec::<u32>::new();
+        let mut this_paired_vec = Vec::<u32>::new();
+        let mut this_values_vec = Vec::<Vec<f64>>::new();
         for _ in 0..number_of_nodes_for_this_boundary {
             let line = match buf.next() {
                 Some(Ok(line)) => line,
@@ -992,37 +1679,82 @@ pub fn parse_from_reader<R: Read>(
                         fname.to_string(),
                         format!(
                             "Expected {} lines with land boundary data but found only {}.",
-                            number_of_land_boundaries,
-                            land_boundaries_vec.len()
+                            number_of_land_boundaries, land_boundary_index
                         ),
                     ))
                 }
             };
-            let mut split_line = line.split_whitespace();
-            let node_id = match split_line.next() {
-                    Some(value) => match value.parse::<u32>() {
-                        Ok(value) => value,
-                        Err(_) => return Err(Gr3ParserError::LineReadError(
-                            fname.to_string(),
-                            format!("Error reading gr3 file: {}. Expected first item in line to be castable to an u32.", fname)
-                        ))
-                    },
-                    None => return Err(Gr3ParserError::LineReadError(
-                        fname.to_string(),
-                        format!("Error reading gr3 file: {}. Expected line to contain node_id but found an empty line.", fname)
-                    ))
-                };
-            this_boundary_vec.push(node_id);
+            let (node_ids, values) =
+                parse_boundary_node_line(&line, fname, number_of_ids, number_of_values)?;
+            this_boundary_vec.push(node_ids[0]);
+            if number_of_ids == 2 {
+                this_paired_vec.push(node_ids[1]);
+            }
+            this_values_vec.push(values);
         }
-        if boundary_id_type == 0 {
-            land_boundaries_vec.push(this_boundary_vec);
-        } else if boundary_id_type == 1 {
-            interior_boundaries_vec.push(this_boundary_vec);
-        } else {
-            return Err(Gr3ParserError::LineReadError(
-                fname.to_string(),
-                format!("Error reading gr3 file: {}. Expected boundary_id_type to be 0 or 1 but found {}.", fname, boundary_id_type)
-            ));
+        non_ocean_boundaries_order.push(boundary_type);
+        match boundary_type {
+            BoundaryType::Land => {
+                land_boundaries_vec.push(this_boundary_vec);
+                land_boundaries_ibtypes.push(boundary_id_type);
+            }
+            BoundaryType::Interior => {
+                interior_boundaries_vec.push(this_boundary_vec);
+                interior_boundaries_ibtypes.push(boundary_id_type);
+            }
+            BoundaryType::Flow => {
+                flow_boundaries_vec.push(this_boundary_vec);
+                flow_boundaries_ibtypes.push(boundary_id_type);
+            }
+            BoundaryType::ExternalBarrier => {
+                external_barriers_vec.push(ExternalBarrierSegment {
+                    ibtype: boundary_id_type,
+                    nodes_ids: this_boundary_vec,
+                    attributes: this_values_vec
+                        .iter()
+                        .map(|v| BarrierAttributes {
+                            height: v[0],
+                            supercritical_coefficient: v[1],
+                        })
+                        .collect(),
+                });
+            }
+            BoundaryType::InternalBarrier => {
+                internal_barriers_vec.push(InternalBarrierSegment {
+                    ibtype: boundary_id_type,
+                    nodes_ids: this_boundary_vec,
+                    paired_nodes_ids: this_paired_vec,
+                    attributes: this_values_vec
+                        .iter()
+                        .map(|v| WeirAttributes {
+                            height: v[0],
+                            subcritical_coefficient: v[1],
+                            supercritical_coefficient: v[2],
+                        })
+                        .collect(),
+                });
+            }
+            BoundaryType::Culvert => {
+                culverts_vec.push(CulvertSegment {
+                    ibtype: boundary_id_type,
+                    nodes_ids: this_boundary_vec,
+                    paired_nodes_ids: this_paired_vec,
+                    attributes: this_values_vec
+                        .iter()
+                        .map(|v| CulvertAttributes {
+                            weir: WeirAttributes {
+                                height: v[0],
+                                subcritical_coefficient: v[1],
+                                supercritical_coefficient: v[2],
+                            },
+                            pipe_height: v[3],
+                            pipe_coefficient: v[4],
+                            pipe_diameter: v[5],
+                        })
+                        .collect(),
+                });
+            }
+            BoundaryType::Open => unreachable!("IBTYPE never maps to an open boundary"),
         }
     }
 
@@ -1047,27 +1779,108 @@ pub fn parse_from_reader<R: Read>(
 
     if !land_boundaries_vec.is_empty() {
         parsed_gr3_builder.land_boundaries(land_boundaries_vec);
+        parsed_gr3_builder.land_boundaries_ibtypes(land_boundaries_ibtypes);
     } else {
         parsed_gr3_builder.land_boundaries(None);
     }
 
     if !interior_boundaries_vec.is_empty() {
         parsed_gr3_builder.interior_boundaries(interior_boundaries_vec);
+        parsed_gr3_builder.interior_boundaries_ibtypes(interior_boundaries_ibtypes);
     } else {
         parsed_gr3_builder.interior_boundaries(None);
     }
+
+    if !flow_boundaries_vec.is_empty() {
+        parsed_gr3_builder.flow_boundaries(flow_boundaries_vec);
+        parsed_gr3_builder.flow_boundaries_ibtypes(flow_boundaries_ibtypes);
+    }
+
+    if !external_barriers_vec.is_empty() {
+        parsed_gr3_builder.external_barrier_boundaries(external_barriers_vec);
+    }
+
+    if !internal_barriers_vec.is_empty() {
+        parsed_gr3_builder.internal_barrier_boundaries(internal_barriers_vec);
+    }
+
+    if !culverts_vec.is_empty() {
+        parsed_gr3_builder.culvert_boundaries(culverts_vec);
+    }
+
+    if !non_ocean_boundaries_order.is_empty() {
+        parsed_gr3_builder.non_ocean_boundaries_order(non_ocean_boundaries_order);
+    }
     log::debug!("Done with parsing full file!");
     Ok(parsed_gr3_builder.build()?)
 }
 
+/// Parse one node line of a non-ocean boundary segment.
+///
+/// Returns the leading `number_of_ids` node ids (NBVV, plus IBCONN for weir pairs)
+/// followed by `number_of_values` barrier attributes.
+fn parse_boundary_node_line(
+    line: &str,
+    fname: &str,
+    number_of_ids: usize,
+    number_of_values: usize,
+) -> Result<(Vec<u32>, Vec<f64>), Gr3ParserError> {
+    let mut split_line = line.split_whitespace();
+    let mut node_ids = Vec::with_capacity(number_of_ids);
+    for _ in 0..number_of_ids {
+        let node_id = match split_line.next() {
+            Some(value) => match value.parse::<u32>() {
+                Ok(value) => value,
+                Err(_) => return Err(Gr3ParserError::LineReadError(
+                    fname.to_string(),
+                    format!("Error reading gr3 file: {}. Expected item {} in line to be castable to an u32 but found {}.", fname, node_ids.len() + 1, value)
+                ))
+            },
+            None => return Err(Gr3ParserError::LineReadError(
+                fname.to_string(),
+                format!("Error reading gr3 file: {}. Expected line to contain {} node id(s) but found only {}.", fname, number_of_ids, node_ids.len())
+            ))
+        };
+        node_ids.push(node_id);
+    }
+    let mut values = Vec::with_capacity(number_of_values);
+    for _ in 0..number_of_values {
+        let value = match split_line.next() {
+            Some(value) => match value.parse::<f64>() {
+                Ok(value) => value,
+                Err(_) => return Err(Gr3ParserError::LineReadError(
+                    fname.to_string(),
+                    format!("Error reading gr3 file: {}. Expected barrier attribute in line to be castable to an f64 but found {}.", fname, value)
+                ))
+            },
+            None => return Err(Gr3ParserError::LineReadError(
+                fname.to_string(),
+                format!("Error reading gr3 file: {}. Expected line to contain {} barrier attributes but found only {}.", fname, number_of_values, values.len())
+            ))
+        };
+        values.push(value);
+    }
+    Ok((node_ids, values))
+}
+
+/// Write a gr3, compressed if `path` ends in `.gz`, `.zst` or `.bz2`.
 pub fn write_to_path(path: &Path, gr3: &Gr3ParserOutput) -> std::io::Result<()> {
+    gr3.check_barrier_segments()?;
     let mut tmpfile = NamedTempFile::new()?;
     log::debug!("Will write to tmpfile: {:?}", tmpfile);
-    writeln!(tmpfile, "{}", gr3)?;
+    let mut writer =
+        CompressedWriter::new(tmpfile.as_file_mut(), Compression::from_extension(path))?;
+    writeln!(writer, "{}", gr3)?;
+    writer.finish()?;
     tmpfile.persist(path)?;
     Ok(())
 }
 
+/// Name of the element attribute holding the `column`-th (from 1) 2DM material id.
+pub fn material_attribute_name(column: usize) -> String {
+    format!("material_{}", column)
+}
+
 impl Gr3ParserOutput {
     /// Write the mesh data as a 2DM (SMS) format file
     pub fn write_as_2dm(&self, path: &Path) -> std::io::Result<()> {
@@ -1089,14 +1902,24 @@ impl Gr3ParserOutput {
 
         // Start with MESH2D header
         output.push_str("MESH2D\n");
+        if let Some(mesh_name) = &self.mesh_name {
+            output.push_str(&format!("MESHNAME \"{}\"\n", mesh_name));
+        }
+        if let Some(num_materials) = self.num_materials_per_elem {
+            output.push_str(&format!("NUM_MATERIALS_PER_ELEM {}\n", num_materials));
+        }
 
         // Add triangular elements (E3T)
         if let Some(elements) = &self.elements {
             for (element_id, element_nodes) in elements.iter() {
                 if element_nodes.len() == 3 {
                     output.push_str(&format!(
-                        "E3T {} {} {} {}\n",
-                        element_id, element_nodes[0], element_nodes[1], element_nodes[2]
+                        "E3T {} {} {} {}{}\n",
+                        element_id,
+                        element_nodes[0],
+                        element_nodes[1],
+                        element_nodes[2],
+                        self.element_materials_to_2dm_string(*element_id)
                     ));
                 }
             }
@@ -1107,12 +1930,13 @@ impl Gr3ParserOutput {
             for (element_id, element_nodes) in elements.iter() {
                 if element_nodes.len() == 4 {
                     output.push_str(&format!(
-                        "E4Q {} {} {} {} {}\n",
+                        "E4Q {} {} {} {} {}{}\n",
                         element_id,
                         element_nodes[0],
                         element_nodes[1],
                         element_nodes[2],
-                        element_nodes[3]
+                        element_nodes[3],
+                        self.element_materials_to_2dm_string(*element_id)
                     ));
                 }
             }
@@ -1145,6 +1969,18 @@ impl Gr3ParserOutput {
         output
     }
 
+    /// Trailing material id columns of an element card (empty if none)
+    fn element_materials_to_2dm_string(&self, element_id: u32) -> String {
+        match self
+            .element_materials
+            .as_ref()
+            .and_then(|materials| materials.get(&element_id))
+        {
+            Some(materials) => materials.iter().map(|m| format!(" {}", m)).collect(),
+            None => String::new(),
+        }
+    }
+
     /// Helper function to format coordinates in the SMS 2DM style
     fn _format_coordinate(value: f64) -> String {
         // Use Rust's standard scientific notation
@@ -1183,8 +2019,8 @@ impl Gr3ParserOutput {
             for boundary in open_boundaries.iter() {
                 if !boundary.is_empty() {
                     output.push_str("NS ");
-                    for i in 0..(boundary.len() - 1) {
-                        output.push_str(&format!("{} ", boundary[i]));
+                    for node_id in &boundary[..boundary.len() - 1] {
+                        output.push_str(&format!("{} ", node_id));
                     }
                     output.push_str(&format!("-{}\n", boundary[boundary.len() - 1]));
                 }
@@ -1196,8 +2032,8 @@ impl Gr3ParserOutput {
             for boundary in land_boundaries.iter() {
                 if !boundary.is_empty() {
                     output.push_str("NS ");
-                    for i in 0..(boundary.len() - 1) {
-                        output.push_str(&format!("{} ", boundary[i]));
+                    for node_id in &boundary[..boundary.len() - 1] {
+                        output.push_str(&format!("{} ", node_id));
                     }
                     output.push_str(&format!("-{}\n", boundary[boundary.len() - 1]));
                 }
@@ -1209,8 +2045,8 @@ impl Gr3ParserOutput {
             for boundary in interior_boundaries.iter() {
                 if !boundary.is_empty() {
                     output.push_str("NS ");
-                    for i in 0..(boundary.len() - 1) {
-                        output.push_str(&format!("{} ", boundary[i]));
+                    for node_id in &boundary[..boundary.len() - 1] {
+                        output.push_str(&format!("{} ", node_id));
                     }
                     output.push_str(&format!("-{}\n", boundary[boundary.len() - 1]));
                 }
@@ -1221,6 +2057,180 @@ impl Gr3ParserOutput {
     }
 }
 
+pub fn parse_2dm_from_path_ref(path: &Path) -> Result<Gr3ParserOutput, Gr3ParserError> {
+    let fname = &path.display().to_string();
+    // Decompresses .gz/.zst/.bz2 input on the fly
+    let reader = match open_reader(path) {
+        Ok(reader) => reader,
+        Err(e) => {
+            return Err(Gr3ParserError::IoError(format!(
+                "Failed to open {}: {}",
+                fname, e
+            )));
+        }
+    };
+    parse_2dm_from_reader(reader, fname)
+}
+
+/// Parse an SMS 2DM mesh (the counterpart of [`Gr3ParserOutput::to_2dm_string`]).
+///
+/// Reads the `E3T`, `E4Q`, `ND` and `NS` cards along with `MESHNAME` and
+/// `NUM_MATERIALS_PER_ELEM`. Columns trailing the element node ids are kept as
+/// material ids. 2DM does not record what a nodestring represents, so all of them
+/// come back as open boundaries. Other cards (e.g. model parameters) are skipped.
+pub fn parse_2dm_from_reader<R: Read>(
+    reader: BufReader<R>,
+    fname: &str, // Passed separately for error messages
+) -> Result<Gr3ParserOutput, Gr3ParserError> {
+    let mut nodes = LinkedHashMap::new();
+    let mut elements = LinkedHashMap::new();
+    let mut element_materials = LinkedHashMap::new();
+    let mut nodestrings = Vec::new();
+    let mut current_nodestring = Vec::new();
+    let mut mesh_name = None;
+    let mut num_materials_per_elem = None;
+    let mut is_empty = true;
+    for (line_index, line) in reader.lines().enumerate() {
+        let line =
+            line.map_err(|e| Gr3ParserError::LineReadError(fname.to_string(), e.to_string()))?;
+        let line_number = line_index + 1;
+        let mut split_line = line.split_whitespace();
+        let card = match split_line.next() {
+            Some(card) => card,
+            None => continue,
+        };
+        is_empty = false;
+        match card {
+            "MESH2D" => {}
+            "MESHNAME" => {
+                let name = line.trim_start()[card.len()..].trim().trim_matches('"');
+                mesh_name = Some(name.to_string());
+            }
+            "NUM_MATERIALS_PER_ELEM" => {
+                num_materials_per_elem = Some(parse_2dm_item::<u32>(
+                    split_line.next(),
+                    fname,
+                    line_number,
+                    "the number of materials per element",
+                )?);
+            }
+            "E3T" | "E4Q" => {
+                let number_of_vertices = if card == "E3T" { 3 } else { 4 };
+                let element_id =
+                    parse_2dm_item::<u32>(split_line.next(), fname, line_number, "an element id")?;
+                let mut element_nodes = Vec::with_capacity(number_of_vertices);
+                for _ in 0..number_of_vertices {
+                    element_nodes.push(parse_2dm_item::<u32>(
+                        split_line.next(),
+                        fname,
+                        line_number,
+                        "an element node id",
+                    )?);
+                }
+                let materials = split_line
+                    .map(|item| {
+                        parse_2dm_item::<u32>(Some(item), fname, line_number, "a material id")
+                    })
+                    .collect::<Result<Vec<u32>, Gr3ParserError>>()?;
+                if !materials.is_empty() {
+                    element_materials.insert(element_id, materials);
+                }
+                elements.insert(element_id, element_nodes);
+            }
+            "ND" => {
+                let node_id =
+                    parse_2dm_item::<u32>(split_line.next(), fname, line_number, "a node id")?;
+                let x = parse_2dm_item::<f64>(
+                    split_line.next(),
+                    fname,
+                    line_number,
+                    "an x coordinate",
+                )?;
+                let y =
+                    parse_2dm_item::<f64>(split_line.next(), fname, line_number, "a y coordinate")?;
+                let z = parse_2dm_item::<f64>(split_line.next(), fname, line_number, "a z value")?;
+                nodes.insert(node_id, (vec![x, y], Some(vec![z])));
+            }
+            "NS" => {
+                // Nodestrings may wrap over several NS cards; a negative id ends one.
+                for item in split_line {
+                    let node_id = parse_2dm_item::<i64>(
+                        Some(item),
+                        fname,
+                        line_number,
+                        "a nodestring node id",
+                    )?;
+                    current_nodestring.push(node_id.unsigned_abs() as u32);
+                    if node_id < 0 {
+                        nodestrings.push(std::mem::take(&mut current_nodestring));
+                        break;
+                    }
+                }
+            }
+            "E2L" | "E3L" | "E6T" | "E8Q" | "E9Q" => {
+                return Err(Gr3ParserError::LineReadError(
+                    fname.to_string(),
+                    format!(
+                        "Error reading 2dm file: {}. Unsupported element card {} on line {}.",
+                        fname, card, line_number
+                    ),
+                ));
+            }
+            _ => log::debug!("Skipping 2dm card {} on line {}", card, line_number),
+        }
+    }
+    if is_empty {
+        return Err(Gr3ParserError::EmptyFile(fname.to_string()));
+    }
+    if !current_nodestring.is_empty() {
+        return Err(Gr3ParserError::LineReadError(
+            fname.to_string(),
+            format!("Error reading 2dm file: {}. Last nodestring is not terminated by a negative node id.", fname)
+        ));
+    }
+    let mut parsed_gr3_builder = Gr3ParserOutputBuilder::default();
+    parsed_gr3_builder.description(mesh_name.clone());
+    parsed_gr3_builder.mesh_name(mesh_name);
+    parsed_gr3_builder.num_materials_per_elem(num_materials_per_elem);
+    parsed_gr3_builder.nodes(nodes);
+    if !elements.is_empty() {
+        parsed_gr3_builder.elements(elements);
+    }
+    if !element_materials.is_empty() {
+        parsed_gr3_builder.element_materials(element_materials);
+    }
+    if !nodestrings.is_empty() {
+        parsed_gr3_builder.open_boundaries(nodestrings);
+    }
+    Ok(parsed_gr3_builder.build()?)
+}
+
+fn parse_2dm_item<T: std::str::FromStr>(
+    item: Option<&str>,
+    fname: &str,
+    line_number: usize,
+    expected: &str,
+) -> Result<T, Gr3ParserError> {
+    match item {
+        Some(value) => value.parse::<T>().map_err(|_| {
+            Gr3ParserError::LineReadError(
+                fname.to_string(),
+                format!(
+                    "Error reading 2dm file: {}. Expected {} on line {} but found {}.",
+                    fname, expected, line_number, value
+                ),
+            )
+        }),
+        None => Err(Gr3ParserError::LineReadError(
+            fname.to_string(),
+            format!(
+                "Error reading 2dm file: {}. Expected {} on line {} but the line ended.",
+                fname, expected, line_number
+            ),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests_2dm {
     use super::*;
@@ -1340,6 +2350,82 @@ mod tests_2dm {
 
         println!("Mixed element 2DM string:\n{}", sms2dm_string);
     }
+
+    #[test]
+    fn test_2dm_round_trip() {
+        let mut nodes = LinkedHashMap::new();
+        for i in 1..=6 {
+            nodes.insert(
+                i,
+                (
+                    vec![i as f64 * 0.1, 0.3],
+                    Some(vec![-10.0 - i as f64 / 3.0]),
+                ),
+            );
+        }
+
+        let mut elements = LinkedHashMap::new();
+        elements.insert(1, vec![1, 2, 3]);
+        elements.insert(2, vec![2, 4, 5, 3]);
+        elements.insert(3, vec![4, 6, 5]);
+
+        let mut element_materials = LinkedHashMap::new();
+        element_materials.insert(1, vec![1, 7]);
+        element_materials.insert(2, vec![2, 7]);
+        element_materials.insert(3, vec![1, 8]);
+
+        let gr3 = Gr3ParserOutputBuilder::default()
+            .nodes(nodes)
+            .elements(elements)
+            .open_boundaries(vec![vec![1, 2, 4, 6]])
+            .mesh_name(Some("estuary".to_string()))
+            .num_materials_per_elem(Some(2))
+            .element_materials(element_materials)
+            .build()
+            .expect("Failed to build test GR3");
+
+        let sms2dm_string = gr3.to_2dm_string();
+        assert!(sms2dm_string.contains("MESHNAME \"estuary\""));
+        assert!(sms2dm_string.contains("E4Q 2 2 4 5 3 2 7"));
+
+        let parsed = parse_2dm_from_reader(BufReader::new(sms2dm_string.as_bytes()), "test")
+            .expect("Failed to parse 2DM string");
+        assert_eq!(parsed.mesh_name(), Some("estuary"));
+        assert_eq!(parsed.num_materials_per_elem(), Some(2));
+        assert_eq!(
+            parsed.element_materials().unwrap().get(&3),
+            Some(&vec![1, 8])
+        );
+        assert_eq!(parsed.open_boundaries(), Some(vec![vec![1, 2, 4, 6]]));
+        assert_eq!(parsed.nodes(), gr3.nodes());
+        assert_eq!(parsed.to_2dm_string(), sms2dm_string);
+    }
+
+    #[test]
+    fn test_2dm_wrapped_nodestring() {
+        let sms2dm_string = "MESH2D
+E3T 1 1 2 3 1
+ND 1 0.0 0.0 -1.0
+ND 2 1.0 0.0 -2.0
+ND 3 0.0 1.0 -3.0
+NS 1 2
+NS -3
+";
+        let parsed = parse_2dm_from_reader(BufReader::new(sms2dm_string.as_bytes()), "test")
+            .expect("Failed to parse 2DM string");
+        assert_eq!(parsed.open_boundaries(), Some(vec![vec![1, 2, 3]]));
+        assert_eq!(parsed.element_materials().unwrap().get(&1), Some(&vec![1]));
+
+        let hgrid = crate::Hgrid::try_from(parsed).expect("Failed to build Hgrid");
+        assert_eq!(hgrid.nodes().len(), 3);
+    }
+
+    #[test]
+    fn test_2dm_unterminated_nodestring_is_rejected() {
+        let sms2dm_string = "MESH2D\nND 1 0.0 0.0 -1.0\nNS 1 1\n";
+        let result = parse_2dm_from_reader(BufReader::new(sms2dm_string.as_bytes()), "test");
+        assert!(result.is_err());
+    }
 }
 
 #[cfg(test)]
@@ -1414,3 +2500,440 @@ mod tests_proj_parsing {
         assert_eq!(result, description);
     }
 }
+
+#[cfg(test)]
+mod tests_boundaries {
+    use super::*;
+    use crate::Hgrid;
+    use tempfile::tempdir;
+
+    const GR3_WITH_ISLAND: &str = "roundtrip mesh
+2 4
+1 0 0 10
+2 1 0 10.5
+3 0 1 11
+4 1 1 12.25
+1 3 1 2 3
+2 3 2 4 3
+1 ! total number of open boundaries
+2 ! total number of open boundary nodes
+2 ! number of nodes for ocean_boundary_1
+1
+2
+2 ! total number of non-ocean boundaries
+5 ! total number of non-ocean boundaries nodes
+3 0 ! number of nodes for land_boundary_1
+2
+4
+3
+2 1 ! number of nodes for interior_boundary_1
+3
+1
+";
+
+    const FORT14_WITH_BARRIERS: &str = "adcirc mesh
+2 6
+1 0 0 5
+2 1 0 5
+3 2 0 5
+4 0 1 5
+5 1 1 5
+6 2 1 5
+1 3 1 2 4
+2 3 2 5 4
+0 ! total number of open boundaries
+0 ! total number of open boundary nodes
+5 ! total number of non-ocean boundaries
+10 ! total number of non-ocean boundaries nodes
+2 20 ! number of nodes for land_boundary_1
+1
+2
+2 22 ! number of nodes for flow_boundary_1
+3
+6
+2 13 ! number of nodes for external_barrier_1
+4 1.5 1
+5 1.5 1
+1 24 ! number of nodes for internal_barrier_1
+2 5 2.5 1 0.8
+1 25 ! number of nodes for culvert_1
+3 6 2.5 1 0.8 0.5 0.9 1.2
+";
+
+    #[test]
+    fn test_parse_keeps_ibtypes() {
+        let gr3 = parse_from_reader(BufReader::new(GR3_WITH_ISLAND.as_bytes()), "test").unwrap();
+        assert_eq!(gr3.land_boundaries(), Some(vec![vec![2, 4, 3]]));
+        assert_eq!(gr3.land_boundaries_ibtypes(), Some(vec![LAND_IBTYPE]));
+        assert_eq!(gr3.interior_boundaries(), Some(vec![vec![3, 1]]));
+        assert_eq!(gr3.interior_boundaries_ibtypes(), Some(vec![ISLAND_IBTYPE]));
+    }
+
+    #[test]
+    fn test_hgrid_write_round_trip_is_byte_identical() {
+        let gr3 = parse_from_reader(BufReader::new(GR3_WITH_ISLAND.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.gr3");
+        hgrid.write(&path).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, GR3_WITH_ISLAND);
+    }
+
+    #[test]
+    fn test_interleaved_land_and_island_order_is_kept() {
+        let text = "interleaved mesh
+2 4
+1 0 0 10
+2 1 0 10.5
+3 0 1 11
+4 1 1 12.25
+1 3 1 2 3
+2 3 2 4 3
+0 ! total number of open boundaries
+0 ! total number of open boundary nodes
+4 ! total number of non-ocean boundaries
+9 ! total number of non-ocean boundaries nodes
+3 1 ! number of nodes for interior_boundary_1
+1
+2
+3
+2 0 ! number of nodes for land_boundary_1
+2
+4
+2 1 ! number of nodes for interior_boundary_2
+4
+3
+2 0 ! number of nodes for land_boundary_2
+3
+1
+";
+        let gr3 = parse_from_reader(BufReader::new(text.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+        assert_eq!(
+            hgrid.boundaries().unwrap().non_ocean_order(),
+            Some(
+                &[
+                    BoundaryType::Interior,
+                    BoundaryType::Land,
+                    BoundaryType::Interior,
+                    BoundaryType::Land
+                ][..]
+            )
+        );
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.gr3");
+        hgrid.write(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+    }
+
+    #[test]
+    fn test_parse_adcirc_barrier_boundaries() {
+        let gr3 =
+            parse_from_reader(BufReader::new(FORT14_WITH_BARRIERS.as_bytes()), "test").unwrap();
+        assert_eq!(gr3.land_boundaries_ibtypes(), Some(vec![20]));
+        assert_eq!(gr3.flow_boundaries(), Some(vec![vec![3, 6]]));
+        assert_eq!(gr3.flow_boundaries_ibtypes(), Some(vec![22]));
+
+        let external = gr3.external_barrier_boundaries().unwrap();
+        assert_eq!(external[0].ibtype, 13);
+        assert_eq!(external[0].nodes_ids, vec![4, 5]);
+        assert_eq!(
+            external[0].attributes[1],
+            BarrierAttributes {
+                height: 1.5,
+                supercritical_coefficient: 1.0
+            }
+        );
+
+        let internal = gr3.internal_barrier_boundaries().unwrap();
+        assert_eq!(internal[0].nodes_ids, vec![2]);
+        assert_eq!(internal[0].paired_nodes_ids, vec![5]);
+        assert_eq!(internal[0].attributes[0].subcritical_coefficient, 1.0);
+
+        let culverts = gr3.culvert_boundaries().unwrap();
+        assert_eq!(culverts[0].ibtype, 25);
+        assert_eq!(culverts[0].paired_nodes_ids, vec![6]);
+        assert_eq!(
+            culverts[0].attributes[0].weir.supercritical_coefficient,
+            0.8
+        );
+        assert_eq!(culverts[0].attributes[0].pipe_diameter, 1.2);
+    }
+
+    #[test]
+    fn test_adcirc_barrier_round_trip_is_byte_identical() {
+        let gr3 =
+            parse_from_reader(BufReader::new(FORT14_WITH_BARRIERS.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+        assert!(hgrid.check_validity().is_structurally_valid());
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("fort.14");
+        hgrid.write(&path).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, FORT14_WITH_BARRIERS);
+    }
+
+    #[test]
+    fn test_interleaved_fort14_order_is_kept() {
+        let text = "adcirc mesh
+2 6
+1 0 0 5
+2 1 0 5
+3 2 0 5
+4 0 1 5
+5 1 1 5
+6 2 1 5
+1 3 1 2 4
+2 3 2 5 4
+0 ! total number of open boundaries
+0 ! total number of open boundary nodes
+6 ! total number of non-ocean boundaries
+12 ! total number of non-ocean boundaries nodes
+1 25 ! number of nodes for culvert_1
+3 6 2.5 1 0.8 0.5 0.9 1.2
+2 22 ! number of nodes for flow_boundary_1
+3
+6
+2 13 ! number of nodes for external_barrier_1
+4 1.5 1
+5 1.5 1
+2 20 ! number of nodes for land_boundary_1
+1
+2
+1 24 ! number of nodes for internal_barrier_1
+2 5 2.5 1 0.8
+2 2 ! number of nodes for flow_boundary_2
+4
+1
+";
+        let gr3 = parse_from_reader(BufReader::new(text.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("fort.14");
+        hgrid.write(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+    }
+
+    #[test]
+    fn test_merge_keeps_single_pair_barriers() {
+        // Node 7 duplicates node 1, which no barrier uses
+        let text = FORT14_WITH_BARRIERS.replacen("2 6\n", "2 7\n", 1);
+        let text = text.replacen("6 2 1 5\n", "6 2 1 5\n7 0 0 5\n", 1);
+        let gr3 = parse_from_reader(BufReader::new(text.as_bytes()), "test").unwrap();
+        let merged = Hgrid::try_from(gr3).unwrap().merge_coincident_nodes(0.01);
+        assert_eq!(merged.nodes().len(), 6);
+        let boundaries = merged.boundaries().unwrap();
+        assert_eq!(boundaries.external_barrier().unwrap().segments().len(), 1);
+        assert_eq!(boundaries.internal_barrier().unwrap().segments().len(), 1);
+        assert_eq!(boundaries.culvert().unwrap().segments().len(), 1);
+        assert_eq!(boundaries.non_ocean_order().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_mismatched_barrier_lengths_are_rejected() {
+        let mut gr3 =
+            parse_from_reader(BufReader::new(FORT14_WITH_BARRIERS.as_bytes()), "test").unwrap();
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("fort.14");
+        gr3.internal_barrier_boundaries.as_mut().unwrap()[0]
+            .paired_nodes_ids
+            .push(6);
+        let error = write_to_path(&path, &gr3).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("internal_barrier_1"));
+
+        gr3.internal_barrier_boundaries.as_mut().unwrap()[0]
+            .paired_nodes_ids
+            .pop();
+        gr3.culvert_boundaries.as_mut().unwrap()[0]
+            .attributes
+            .clear();
+        let error = write_to_path(&path, &gr3).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("culvert_1 has 1 nodes but 0 attributes"));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_unknown_ibtype_is_rejected() {
+        let text = FORT14_WITH_BARRIERS.replace("2 22 !", "2 99 !");
+        let result = parse_from_reader(BufReader::new(text.as_bytes()), "test");
+        assert!(result.is_err());
+    }
+}
+
+#[cfg(test)]
+mod tests_parallel {
+    use super::*;
+
+    /// Structured grid of `n` x `n` nodes split into triangles, with one open
+    /// and one land boundary, optionally with CRLF line endings.
+    fn synthetic_gr3(n: u32, crlf: bool) -> String {
+        let eol = if crlf { "\r\n" } else { "\n" };
+        let mut text = format!("synthetic EPSG:4326{}", eol);
+        text += &format!("{} {}{}", 2 * (n - 1) * (n - 1), n * n, eol);
+        for j in 0..n {
+            for i in 0..n {
+                text += &format!(
+                    "{} {} {} {:.3}{}",
+                    j * n + i + 1,
+                    i,
+                    j,
+                    (i + j) as f64 * 0.5,
+                    eol
+                );
+            }
+        }
+        let mut id = 1;
+        for j in 0..n - 1 {
+            for i in 0..n - 1 {
+                let a = j * n + i + 1;
+                text += &format!("{} 3 {} {} {}{}", id, a, a + 1, a + n + 1, eol);
+                text += &format!("{} 3 {} {} {}{}", id + 1, a, a + n + 1, a + n, eol);
+                id += 2;
+            }
+        }
+        text += &format!("1 ! open{}{} ! nodes{}{} ! open 1{}", eol, n, eol, n, eol);
+        for i in 0..n {
+            text += &format!("{}{}", i + 1, eol);
+        }
+        text += &format!("1 ! land{}{} ! nodes{}{} 0 ! land 1{}", eol, n, eol, n, eol);
+        for j in 0..n {
+            text += &format!("{}{}", j * n + n, eol);
+        }
+        text
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        for crlf in [false, true] {
+            let text = synthetic_gr3(12, crlf);
+            let sequential = parse_from_reader(BufReader::new(text.as_bytes()), "test").unwrap();
+            let parallel = parse_from_bytes_chunked(text.as_bytes(), "test", 7).unwrap();
+            assert_eq!(format!("{:?}", sequential), format!("{:?}", parallel));
+        }
+    }
+
+    #[test]
+    fn test_parallel_error_messages_match_sequential() {
+        let text = synthetic_gr3(12, false);
+        let bad_node = text.replacen("100 3 8 ", "100 3 eight ", 1);
+        let bad_element = text.replacen("\n150 3 ", "\n150 3 x", 1);
+        let truncated: String = text
+            .lines()
+            .take(2 + 144 + 50)
+            .collect::<Vec<_>>()
+            .join("\n");
+        for (case, text) in [bad_node, bad_element, truncated, String::new()]
+            .iter()
+            .enumerate()
+        {
+            let sequential = parse_from_reader(BufReader::new(text.as_bytes()), "test")
+                .unwrap_err()
+                .to_string();
+            let parallel = parse_from_bytes_chunked(text.as_bytes(), "test", 7)
+                .unwrap_err()
+                .to_string();
+            assert_eq!(sequential, parallel, "case {}", case);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_async {
+    use super::*;
+    use crate::Hgrid;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    const GR3: &str = "async mesh EPSG:4326
+2 4
+1 0 0 10
+2 1 0 10.5
+3 0 1 11
+4 1 1 12.25
+1 3 1 2 3
+2 3 2 4 3
+1 ! total number of open boundaries
+2 ! total number of open boundary nodes
+2 ! number of nodes for ocean_boundary_1
+1
+2
+1 ! total number of land boundaries
+3 ! total number of land boundary nodes
+3 0 ! number of nodes for land_boundary_1
+2
+4
+3
+";
+
+    /// Serve `body` with `status` to the first connection on a local port.
+    async fn serve_once(status: &'static str, body: &'static str) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        Url::parse(&format!("http://{}/hgrid.gr3", addr)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_async_reader_matches_sync() {
+        let sequential = parse_from_reader(BufReader::new(GR3.as_bytes()), "test").unwrap();
+        let streamed = parse_from_async_reader(tokio::io::BufReader::new(GR3.as_bytes()), "test")
+            .await
+            .unwrap();
+        assert_eq!(format!("{:?}", sequential), format!("{:?}", streamed));
+
+        let broken = GR3.replace("2 3 2 4 3", "2 3 2 four 3");
+        let truncated: String = GR3.lines().take(5).collect::<Vec<_>>().join("\n");
+        for text in [broken.as_str(), &truncated, "async mesh\n", ""] {
+            let sequential =
+                parse_from_reader(BufReader::new(text.as_bytes()), "test").unwrap_err();
+            let streamed = parse_from_async_reader(text.as_bytes(), "test")
+                .await
+                .unwrap_err();
+            assert_eq!(sequential.to_string(), streamed.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_from_url_async() {
+        let url = serve_once("200 OK", GR3).await;
+        let hgrid = Hgrid::from_url_async(&url).await.unwrap();
+        assert_eq!(hgrid.nodes().len(), 4);
+        assert_eq!(hgrid.crs(), Some("EPSG:4326"));
+        assert!(hgrid.boundaries().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_from_url_async_http_error() {
+        let url = serve_once("404 Not Found", "").await;
+        let err = parse_from_url_async(&url).await.unwrap_err();
+        assert!(matches!(err, Gr3ParserError::RequestFromUrlError(_, _)));
+    }
+}
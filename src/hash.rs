@@ -5,6 +5,7 @@
 //! Provides deterministic hashing for Hgrid objects to enable
 //! efficient change detection and caching.
 
+use crate::nodes::DEPTH_ATTRIBUTE;
 use crate::Hgrid;
 use sha2::{Digest, Sha256};
 use std::collections::hash_map::DefaultHasher;
@@ -15,23 +16,21 @@ impl Hash for Hgrid {
         // Hash the description
         self.description().hash(state);
 
-        // Hash nodes in insertion order (LinkedHashMap preserves order)
+        // Hash nodes in node order
         // No sorting needed - iteration order is deterministic
-        for (node_id, (coords, values)) in self.nodes().hash_map().iter() {
+        for_each_node(self, |node_id, coords, values| {
             node_id.hash(state);
             // f64 doesn't impl Hash, so hash the bytes
             for coord in coords {
                 state.write(&coord.to_le_bytes());
             }
-            if let Some(vals) = values {
-                for val in vals {
-                    state.write(&val.to_le_bytes());
-                }
+            for val in values {
+                state.write(&val.to_le_bytes());
             }
-        }
+        });
 
-        // Hash elements in insertion order (LinkedHashMap preserves order)
-        for (element_id, node_list) in self.elements().hash_map().iter() {
+        // Hash elements in element order
+        for (element_id, node_list) in self.elements().iter() {
             element_id.hash(state);
             node_list.hash(state);
         }
@@ -60,24 +59,22 @@ impl Hgrid {
         // Hash description
         hasher.update(self.description().unwrap_or(&String::new()).as_bytes());
 
-        // Hash node data directly in insertion order (LinkedHashMap is deterministic)
-        for (node_id, (coords, values)) in self.nodes().hash_map().iter() {
-            hasher.update(&node_id.to_le_bytes());
+        // Hash node data directly in node order
+        for_each_node(self, |node_id, coords, values| {
+            hasher.update(node_id.to_le_bytes());
             for coord in coords {
-                hasher.update(&coord.to_le_bytes());
+                hasher.update(coord.to_le_bytes());
             }
-            if let Some(vals) = values {
-                for val in vals {
-                    hasher.update(&val.to_le_bytes());
-                }
+            for val in values {
+                hasher.update(val.to_le_bytes());
             }
-        }
+        });
 
-        // Hash element data directly in insertion order
-        for (element_id, node_list) in self.elements().hash_map().iter() {
-            hasher.update(&element_id.to_le_bytes());
+        // Hash element data directly in element order
+        for (element_id, node_list) in self.elements().iter() {
+            hasher.update(element_id.to_le_bytes());
             for node_id in node_list {
-                hasher.update(&node_id.to_le_bytes());
+                hasher.update(node_id.to_le_bytes());
             }
         }
 
@@ -86,7 +83,7 @@ impl Hgrid {
             if let Some(open) = boundaries.open() {
                 for boundary in open.nodes_ids() {
                     for node_id in boundary {
-                        hasher.update(&node_id.to_le_bytes());
+                        hasher.update(node_id.to_le_bytes());
                     }
                 }
             }
@@ -111,6 +108,27 @@ impl Hgrid {
     }
 }
 
+/// Visit each node's id, coordinates and gr3 values in node order.
+///
+/// Values are skipped for nodes without any (all-NaN depth row), matching how
+/// the node map was hashed before the arrays layout.
+fn for_each_node(hgrid: &Hgrid, mut visit: impl FnMut(u32, [f64; 2], &[f64])) {
+    let nodes = hgrid.nodes();
+    let depth = nodes.attribute(DEPTH_ATTRIBUTE);
+    let xy = nodes.xy();
+    let mut row = Vec::new();
+    for (index, &node_id) in nodes.ids().iter().enumerate() {
+        row.clear();
+        if let Some(depth) = depth {
+            row.extend(depth.row(index));
+            if row.iter().all(|v| v.is_nan()) {
+                row.clear();
+            }
+        }
+        visit(node_id, [xy[[index, 0]], xy[[index, 1]]], &row);
+    }
+}
+
 // We need to implement Hash for the component types as well
 use crate::boundaries::{Boundaries, OpenBoundaries};
 
@@ -1,21 +1,32 @@
+use super::compression::strip_compression_extension;
 use super::gr3::{self, Gr3ParserOutputBuilder};
 use super::{
     boundaries::{
-        Boundaries, BoundariesBuilder, BoundariesBuilderError, BoundaryType,
-        InteriorBoundariesBuilder, InteriorBoundariesBuilderError, LandBoundariesBuilder,
-        LandBoundariesBuilderError, OpenBoundariesBuilder, OpenBoundariesBuilderError,
+        Boundaries, BoundariesBuilder, BoundariesBuilderError, CulvertBoundariesBuilder,
+        CulvertBoundariesBuilderError, ExternalBarrierBoundariesBuilder,
+        ExternalBarrierBoundariesBuilderError, FlowBoundariesBuilder, FlowBoundariesBuilderError,
+        InteriorBoundariesBuilder, InteriorBoundariesBuilderError,
+        InternalBarrierBoundariesBuilder, InternalBarrierBoundariesBuilderError,
+        LandBoundariesBuilder, LandBoundariesBuilderError, OpenBoundariesBuilder,
+        OpenBoundariesBuilderError,
     },
-    elements::{Elements, ElementsBuilder, ElementsBuilderError},
+    elements::{ElementAttributeError, Elements, ElementsBuilder, ElementsBuilderError},
     gr3::{write_to_path, Gr3ParserOutput},
-    nodes::{Nodes, NodesBuilder, NodesBuilderError},
+    nodes::{
+        NodeAttribute, NodeAttributeError, Nodes, NodesBuilder, NodesBuilderError, DEPTH_ATTRIBUTE,
+    },
+    topology::Topology,
 };
+use super::{gmsh, selafin, triangle, ugrid};
 use derive_builder::Builder;
 use linked_hash_map::LinkedHashMap;
-use ndarray::{Array1, Array2};
+use ndarray::{Array1, ArrayView1, ArrayView2};
 use proj::Proj;
+#[cfg(feature = "serde")]
+use std::borrow::Cow;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use thiserror::Error;
 use url::Url;
 
@@ -24,6 +35,7 @@ use url::Url;
 /// SCHISM gr3 files use positive-down convention (positive values = below surface).
 /// This is now the default internal storage convention.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DepthConvention {
     /// Positive values = down (SCHISM file convention: 10.0 means 10m below surface)
     #[default]
@@ -53,6 +65,9 @@ pub struct Hgrid {
     /// Default is PositiveDown (SCHISM file convention).
     #[builder(default)]
     depth_convention: DepthConvention,
+    /// Side table, built on first use by `topology()`
+    #[builder(setter(skip))]
+    topology: OnceLock<Arc<Topology>>,
 }
 
 impl Hgrid {
@@ -64,6 +79,11 @@ impl Hgrid {
         &self.elements
     }
 
+    /// Side table of the mesh, built on first use and cached.
+    pub fn topology(&self) -> &Topology {
+        self.topology.get_or_init(|| Topology::new(self).into())
+    }
+
     pub fn boundaries(&self) -> Option<&Boundaries> {
         self.boundaries.as_ref()
     }
@@ -72,11 +92,11 @@ impl Hgrid {
         self.description.as_ref()
     }
 
-    pub fn x(&self) -> Array1<f64> {
+    pub fn x(&self) -> ArrayView1<'_, f64> {
         self.nodes.x()
     }
 
-    pub fn y(&self) -> Array1<f64> {
+    pub fn y(&self) -> ArrayView1<'_, f64> {
         self.nodes.y()
     }
 
@@ -87,17 +107,93 @@ impl Hgrid {
     ///
     /// Use `depth_convention()` to check the current convention.
     pub fn depths(&self) -> Array1<f64> {
-        let node_hashmap = self.nodes.hash_map();
-        let depths: Vec<f64> = node_hashmap
-            .values()
-            .filter_map(|(_feats, depth_opt)| {
-                depth_opt
-                    .as_ref()
-                    .and_then(|depths| depths.first().copied())
-            })
-            .collect();
+        self.nodes
+            .attribute(DEPTH_ATTRIBUTE)
+            .and_then(|depths| depths.column(0))
+            .unwrap_or_default()
+    }
+
+    /// Whether the nodes carry a depth at all.
+    ///
+    /// Meshes imported from Gmsh or Triangle may have none, in which case
+    /// `depths()` is empty.
+    pub fn has_depths(&self) -> bool {
+        self.nodes.attribute(DEPTH_ATTRIBUTE).is_some()
+    }
+
+    /// Get a named nodal attribute (see [`Nodes::attribute`]).
+    pub fn node_attribute(&self, name: &str) -> Option<&NodeAttribute> {
+        self.nodes.attribute(name)
+    }
+
+    /// Add (or replace) a named nodal attribute, returning the previous one.
+    ///
+    /// Values given for `depth` are taken to be in the current depth convention.
+    pub fn add_node_attribute(
+        &mut self,
+        name: &str,
+        attribute: NodeAttribute,
+    ) -> Result<Option<NodeAttribute>, NodeAttributeError> {
+        let mut nodes = (*self.nodes).clone();
+        let previous = nodes.add_attribute(name, attribute)?;
+        self.relink_nodes(nodes);
+        Ok(previous)
+    }
+
+    /// Remove a named nodal attribute, returning it if it was defined.
+    pub fn remove_node_attribute(&mut self, name: &str) -> Option<NodeAttribute> {
+        self.nodes.attribute(name)?;
+        let mut nodes = (*self.nodes).clone();
+        let previous = nodes.remove_attribute(name);
+        self.relink_nodes(nodes);
+        previous
+    }
+
+    /// Get a named per-element attribute (see [`Elements::attribute`]).
+    pub fn element_attribute(&self, name: &str) -> Option<ArrayView1<'_, f64>> {
+        self.elements.attribute(name)
+    }
+
+    /// Add (or replace) a named per-element attribute, returning the previous one.
+    pub fn add_element_attribute(
+        &mut self,
+        name: &str,
+        values: Array1<f64>,
+    ) -> Result<Option<Array1<f64>>, ElementAttributeError> {
+        self.elements.add_attribute(name, values)
+    }
+
+    /// Remove a named per-element attribute, returning it if it was defined.
+    pub fn remove_element_attribute(&mut self, name: &str) -> Option<Array1<f64>> {
+        self.elements.remove_attribute(name)
+    }
 
-        depths.into()
+    /// Replace the nodes, pointing elements and boundaries at the new ones.
+    fn relink_nodes(&mut self, nodes: Nodes) {
+        self.nodes = Arc::new(nodes);
+        self.elements = self.elements.with_nodes(self.nodes.clone());
+        self.boundaries = self
+            .boundaries
+            .as_ref()
+            .map(|boundaries| boundaries.with_nodes(self.nodes.clone()));
+    }
+
+    /// Copy of the grid with a new mesh, keeping the description and depth
+    /// convention. `elements` and `boundaries` must reference `nodes`.
+    pub(crate) fn with_mesh(
+        &self,
+        nodes: Arc<Nodes>,
+        elements: Elements,
+        boundaries: Option<Boundaries>,
+    ) -> Hgrid {
+        Hgrid {
+            nodes,
+            elements,
+            boundaries,
+            description: self.description.clone(),
+            depth_convention: self.depth_convention,
+            topology: OnceLock::new(),
+        }
     }
 
     /// Get depths as positive-down values (matching gr3 file convention).
@@ -143,32 +239,21 @@ impl Hgrid {
     /// - If convention was PositiveUp, it becomes PositiveDown
     /// - All depth values are negated
     pub fn flip_depths(&mut self) {
-        // Get the current nodes hash map
-        let old_nodes = self.nodes.hash_map();
-
-        // Create new hash map with negated depth values
-        let new_nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> = old_nodes
-            .iter()
-            .map(|(&node_id, (coord, value))| {
-                let negated_value = value.as_ref().map(|v| v.iter().map(|&x| -x).collect());
-                (node_id, (coord.clone(), negated_value))
-            })
-            .collect();
-
-        // Rebuild the nodes with the new values
-        let new_nodes_struct = NodesBuilder::default()
-            .hash_map(new_nodes)
-            .crs(self.nodes.crs().map(|s| s.to_string()))
-            .build()
-            .expect("Failed to rebuild nodes after flip_depths");
+        // Negate the depth values on a copy of the nodes
+        let mut new_nodes = (*self.nodes).clone();
+        if let Some(depth) = new_nodes.remove_attribute(DEPTH_ATTRIBUTE) {
+            new_nodes
+                .add_attribute(DEPTH_ATTRIBUTE, depth.mapv(|x| -x))
+                .expect("Negated depths have one row per node");
+        }
 
         // Update the Hgrid
-        self.nodes = Arc::new(new_nodes_struct);
+        self.relink_nodes(new_nodes);
         self.depth_convention = self.depth_convention.flip();
     }
 
 
-    pub fn xy(&self) -> Array2<f64> {
+    pub fn xy(&self) -> ArrayView2<'_, f64> {
         self.nodes.xy()
     }
 
@@ -185,15 +270,14 @@ impl Hgrid {
     }
 
     pub fn write(&self, path: &Path) -> std::io::Result<()> {
-        let mut gr3_parser_output_builder = Gr3ParserOutputBuilder::default();
-        gr3_parser_output_builder.description(self.description.clone());
+        self.write_with_node_values(path, self.gr3_nodes())
+    }
 
-        // gr3 files use positive-down convention
+    /// Nodes with their depths in the positive-down convention of gr3 files
+    fn gr3_nodes(&self) -> LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> {
         // If we're in positive-down, write as-is; if positive-up, negate
-        let output_nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> = match self
-            .depth_convention
-        {
-            DepthConvention::PositiveDown => self.nodes.hash_map().clone(),
+        match self.depth_convention {
+            DepthConvention::PositiveDown => self.nodes.hash_map(),
             DepthConvention::PositiveUp => self
                 .nodes
                 .hash_map()
@@ -203,33 +287,134 @@ impl Hgrid {
                     (node_id, (coord.clone(), negated_value))
                 })
                 .collect(),
-        };
+        }
+    }
+
+    /// Write as gr3 with a named nodal attribute as the value column(s).
+    ///
+    /// `depth` is written exactly as by `write()`; other attributes are written as stored.
+    pub fn write_with_attribute(&self, path: &Path, name: &str) -> std::io::Result<()> {
+        if name == DEPTH_ATTRIBUTE {
+            return self.write(path);
+        }
+        let attribute = self.nodes.attribute(name).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Node attribute {} not found", name),
+            )
+        })?;
+        let output_nodes = self
+            .nodes
+            .hash_map()
+            .iter()
+            .enumerate()
+            .map(|(index, (&node_id, (coord, _value)))| {
+                (node_id, (coord.clone(), Some(attribute.row(index))))
+            })
+            .collect();
+        self.write_with_node_values(path, output_nodes)
+    }
+
+    /// Write as an SMS 2DM file.
+    ///
+    /// Element attributes named `material_1`, `material_2`, ... are written as
+    /// the material id columns of the element cards, and the description as
+    /// the mesh name.
+    pub fn write_2dm(&self, path: &Path) -> std::io::Result<()> {
+        let mut output = self.to_parser_output(self.gr3_nodes());
+        output.mesh_name = self.description.clone();
+        let columns: Vec<ArrayView1<'_, f64>> = (1..)
+            .map_while(|column| {
+                self.elements
+                    .attribute(&gr3::material_attribute_name(column))
+            })
+            .collect();
+        if !columns.is_empty() {
+            output.num_materials_per_elem = Some(columns.len() as u32);
+            output.element_materials = Some(
+                self.elements
+                    .ids()
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &elem_id)| {
+                        let materials = columns
+                            .iter()
+                            .map(|column| column[index])
+                            .filter(|material| material.is_finite())
+                            .map(|material| material as u32)
+                            .collect();
+                        (elem_id, materials)
+                    })
+                    .collect(),
+            );
+        }
+        output.write_as_2dm(path)
+    }
+
+    fn write_with_node_values(
+        &self,
+        path: &Path,
+        output_nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)>,
+    ) -> std::io::Result<()> {
+        write_to_path(path, &self.to_parser_output(output_nodes))
+    }
+
+    fn to_parser_output(
+        &self,
+        output_nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)>,
+    ) -> Gr3ParserOutput {
+        let mut gr3_parser_output_builder = Gr3ParserOutputBuilder::default();
+        gr3_parser_output_builder.description(self.description.clone());
         gr3_parser_output_builder.nodes(output_nodes);
-        gr3_parser_output_builder.elements(self.elements.hash_map().clone());
+        gr3_parser_output_builder.elements(self.elements.hash_map());
         gr3_parser_output_builder.crs(self.crs().map(|s| s.to_string()));
         if let Some(boundaries) = &self.boundaries {
-            let the_type_map = boundaries.to_boundary_type_map();
-            gr3_parser_output_builder.open_boundaries(the_type_map[&BoundaryType::Open].clone());
-            gr3_parser_output_builder.land_boundaries(the_type_map[&BoundaryType::Land].clone());
             gr3_parser_output_builder
-                .interior_boundaries(the_type_map[&BoundaryType::Interior].clone());
+                .open_boundaries(boundaries.open().map(|open| open.nodes_ids().clone()));
+            if let Some(land) = boundaries.land() {
+                gr3_parser_output_builder.land_boundaries(land.nodes_ids().clone());
+                gr3_parser_output_builder.land_boundaries_ibtypes(land.ibtypes());
+            }
+            if let Some(interior) = boundaries.interior() {
+                gr3_parser_output_builder.interior_boundaries(interior.nodes_ids().clone());
+                gr3_parser_output_builder.interior_boundaries_ibtypes(interior.ibtypes());
+            }
+            if let Some(flow) = boundaries.flow() {
+                gr3_parser_output_builder.flow_boundaries(flow.nodes_ids().clone());
+                gr3_parser_output_builder.flow_boundaries_ibtypes(flow.ibtypes());
+            }
+            gr3_parser_output_builder.external_barrier_boundaries(
+                boundaries
+                    .external_barrier()
+                    .map(|external| external.segments().clone()),
+            );
+            gr3_parser_output_builder.internal_barrier_boundaries(
+                boundaries
+                    .internal_barrier()
+                    .map(|internal| internal.segments().clone()),
+            );
+            gr3_parser_output_builder.culvert_boundaries(
+                boundaries
+                    .culvert()
+                    .map(|culvert| culvert.segments().clone()),
+            );
+            gr3_parser_output_builder
+                .non_ocean_boundaries_order(boundaries.non_ocean_order().map(<[_]>::to_vec));
         } else {
             gr3_parser_output_builder.open_boundaries(Vec::new());
             gr3_parser_output_builder.land_boundaries(Vec::new());
             gr3_parser_output_builder.interior_boundaries(Vec::new());
         }
-        let gr3_parser_output = gr3_parser_output_builder.build().unwrap();
-        write_to_path(path, &gr3_parser_output)
+        gr3_parser_output_builder.build().unwrap()
     }
 
+    /// Number of elements sharing each node, in node order.
     pub fn get_number_of_elements_connected_to_each_node(&self) -> Array1<usize> {
-        let mut counts = vec![0; self.nodes.len() + 1];
-        for (_element, node_ids) in self.elements.hash_map().iter() {
-            for node_id in node_ids {
-                counts[*node_id as usize] += 1;
-            }
-        }
-        Array1::from(counts)
+        self.topology()
+            .node_elements_csr()
+            .iter()
+            .map(<[usize]>::len)
+            .collect()
     }
 
     /// Check if the CRS is geographic (lon/lat based, e.g., EPSG:4326)
@@ -355,65 +540,31 @@ impl Hgrid {
             .map_err(|e| HgridTryFromError::ProjError(e.to_string()))?;
 
         // Transform all node coordinates
-        let mut new_nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> = LinkedHashMap::new();
-
-        for (node_id, (coords, values)) in self.nodes.hash_map().iter() {
+        let mut new_coords = self.nodes.xy().to_owned();
+        for mut coords in new_coords.rows_mut() {
             let (new_x, new_y) = transformer
                 .convert((coords[0], coords[1]))
                 .map_err(|e| HgridTryFromError::TransformError(e.to_string()))?;
-
-            new_nodes.insert(*node_id, (vec![new_x, new_y], values.clone()));
+            coords[0] = new_x;
+            coords[1] = new_y;
         }
 
         // Build new Nodes with transformed coordinates (store CRS as string)
-        let new_nodes_struct = NodesBuilder::default()
-            .hash_map(new_nodes)
-            .crs(Some(dst_crs.to_string()))
-            .build()?;
+        let new_nodes_struct = self
+            .nodes
+            .with_coords(new_coords, Some(dst_crs.to_string()));
 
         // Build new Hgrid with the same elements and boundaries but new nodes
         let new_nodes_arc = Arc::new(new_nodes_struct);
 
         // Rebuild elements with reference to new nodes
-        let new_elements = ElementsBuilder::default()
-            .nodes(new_nodes_arc.clone())
-            .hash_map(self.elements.hash_map().clone())
-            .build()?;
+        let new_elements = self.elements.with_nodes(new_nodes_arc.clone());
 
         // Rebuild boundaries if present
-        let new_boundaries = if let Some(boundaries) = &self.boundaries {
-            let type_map = boundaries.to_boundary_type_map();
-
-            let mut boundaries_builder = BoundariesBuilder::default();
-
-            if !type_map[&BoundaryType::Open].is_empty() {
-                let open = OpenBoundariesBuilder::default()
-                    .nodes_ids(type_map[&BoundaryType::Open].clone())
-                    .nodes(new_nodes_arc.clone())
-                    .build()?;
-                boundaries_builder.open(Some(open));
-            }
-
-            if !type_map[&BoundaryType::Land].is_empty() {
-                let land = LandBoundariesBuilder::default()
-                    .nodes_ids(type_map[&BoundaryType::Land].clone())
-                    .nodes(new_nodes_arc.clone())
-                    .build()?;
-                boundaries_builder.land(Some(land));
-            }
-
-            if !type_map[&BoundaryType::Interior].is_empty() {
-                let interior = InteriorBoundariesBuilder::default()
-                    .nodes_ids(type_map[&BoundaryType::Interior].clone())
-                    .nodes(new_nodes_arc.clone())
-                    .build()?;
-                boundaries_builder.interior(Some(interior));
-            }
-
-            Some(boundaries_builder.build()?)
-        } else {
-            None
-        };
+        let new_boundaries = self
+            .boundaries
+            .as_ref()
+            .map(|boundaries| boundaries.with_nodes(new_nodes_arc.clone()));
 
         Ok(Hgrid {
             nodes: new_nodes_arc,
@@ -421,6 +572,7 @@ impl Hgrid {
             boundaries: new_boundaries,
             description: self.description.clone(),
             depth_convention: self.depth_convention, // Preserve depth convention
+            topology: self.topology.clone(),
         })
     }
 
@@ -429,14 +581,104 @@ impl Hgrid {
     /// Convenience method for generating hgrid.ll.
     /// If the mesh is already in EPSG:4326, returns a clone.
     pub fn to_lonlat(&self) -> Result<Hgrid, HgridTryFromError> {
-        if self.is_geographic() {
-            // Check if it's specifically EPSG:4326 or another geographic CRS
-            // For simplicity, if it's any geographic CRS we clone (they're all lon/lat)
-            Ok(self.clone())
-        } else {
-            self.transform_to("EPSG:4326")
+        match self.crs() {
+            // Other geographic CRSs (NAD83, ...) still need a datum shift
+            Some(crs) if is_wgs84(crs) => Ok(self.clone()),
+            Some(_) => self.transform_to("EPSG:4326"),
+            // Nothing to transform from; assume lon/lat if it looks like it
+            None if self.coords_look_geographic() => Ok(self.clone()),
+            None => Err(HgridTryFromError::NoCrsDefined),
         }
     }
+
+    /// Write the SCHISM grid pair: `hgrid.gr3` in the native CRS and `hgrid.ll`
+    /// in WGS84 lon/lat, with identical numbering and boundaries.
+    ///
+    /// Both files are written to temporary files in `directory` and read back
+    /// with [`verify_grid_set`]; they replace `hgrid.gr3` and `hgrid.ll` only
+    /// once the pair is known to match.
+    pub fn write_schism_grid_set(&self, directory: &Path) -> Result<(), HgridTryFromError> {
+        let lonlat = self.to_lonlat()?;
+        let io_error = |path: &Path, e: std::io::Error| {
+            HgridTryFromError::IoError(format!("Failed to write {}: {}", path.display(), e))
+        };
+        let mut written = Vec::new();
+        for (hgrid, extension) in [(self, "gr3"), (&lonlat, "ll")] {
+            let path = directory.join("hgrid").with_extension(extension);
+            // Same extension as the target, so it is read back the same way
+            let written_to = tempfile::Builder::new()
+                .prefix(".hgrid")
+                .suffix(&format!(".{}", extension))
+                .tempfile_in(directory)
+                .and_then(|tmpfile| hgrid.write(tmpfile.path()).map(|()| tmpfile))
+                .map_err(|e| io_error(&path, e))?;
+            written.push((written_to, path));
+        }
+        verify_grid_set(written[0].0.path(), written[1].0.path())?;
+        for (tmpfile, path) in written {
+            tmpfile
+                .persist(&path)
+                .map_err(|e| io_error(&path, e.error))?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if both grids have the same node and element numbering,
+    /// connectivity and boundaries (coordinates and values may differ).
+    pub fn has_same_topology(&self, other: &Hgrid) -> bool {
+        self.topology_mismatch(other).is_none()
+    }
+
+    /// Describe the first topological difference between two grids, if any.
+    fn topology_mismatch(&self, other: &Hgrid) -> Option<String> {
+        if self.nodes.ids() != other.nodes.ids() {
+            return Some("node numbering differs".to_string());
+        }
+        if self.elements.ids() != other.elements.ids()
+            || self.elements.vertex_counts() != other.elements.vertex_counts()
+            || self.elements.connectivity() != other.elements.connectivity()
+        {
+            return Some("element connectivity differs".to_string());
+        }
+        let boundaries_match = match (&self.boundaries, &other.boundaries) {
+            (Some(a), Some(b)) => {
+                a.to_boundary_type_map() == b.to_boundary_type_map()
+                    && a.land().map(|l| l.ibtypes()) == b.land().map(|l| l.ibtypes())
+                    && a.interior().map(|i| i.ibtypes()) == b.interior().map(|i| i.ibtypes())
+                    && a.flow().map(|f| f.ibtypes()) == b.flow().map(|f| f.ibtypes())
+                    && a.external_barrier().map(|e| e.segments())
+                        == b.external_barrier().map(|e| e.segments())
+                    && a.internal_barrier().map(|i| i.segments())
+                        == b.internal_barrier().map(|i| i.segments())
+                    && a.culvert().map(|c| c.segments()) == b.culvert().map(|c| c.segments())
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        if !boundaries_match {
+            return Some("boundaries differ".to_string());
+        }
+        None
+    }
+}
+
+/// Check that an `hgrid.gr3` / `hgrid.ll` pair on disk share topology.
+pub fn verify_grid_set(gr3_path: &Path, ll_path: &Path) -> Result<(), HgridTryFromError> {
+    let gr3 = Hgrid::try_from(&gr3_path.to_path_buf())?;
+    let ll = Hgrid::try_from(&ll_path.to_path_buf())?;
+    match gr3.topology_mismatch(&ll) {
+        Some(mismatch) => Err(HgridTryFromError::TopologyMismatch(mismatch)),
+        None => Ok(()),
+    }
+}
+
+/// Whether a CRS string names WGS84 lon/lat (what SCHISM expects in hgrid.ll)
+pub(crate) fn is_wgs84(crs: &str) -> bool {
+    let crs = crs.trim().to_uppercase();
+    matches!(
+        crs.as_str(),
+        "EPSG:4326" | "OGC:CRS84" | "CRS:84" | "WGS84" | "WGS 84"
+    )
 }
 
 #[derive(Error, Debug)]
@@ -465,6 +707,18 @@ pub enum HgridTryFromError {
     #[error(transparent)]
     InteriorBoundariesBuilderError(#[from] InteriorBoundariesBuilderError),
 
+    #[error(transparent)]
+    FlowBoundariesBuilderError(#[from] FlowBoundariesBuilderError),
+
+    #[error(transparent)]
+    ExternalBarrierBoundariesBuilderError(#[from] ExternalBarrierBoundariesBuilderError),
+
+    #[error(transparent)]
+    InternalBarrierBoundariesBuilderError(#[from] InternalBarrierBoundariesBuilderError),
+
+    #[error(transparent)]
+    CulvertBoundariesBuilderError(#[from] CulvertBoundariesBuilderError),
+
     #[error("No CRS defined for hgrid - cannot perform coordinate transformation")]
     NoCrsDefined,
 
@@ -473,14 +727,33 @@ pub enum HgridTryFromError {
 
     #[error("PROJ error: {0}")]
     ProjError(String),
+
+    #[error("Grids do not share topology: {0}")]
+    TopologyMismatch(String),
+
+    #[error("I/O error: {0}")]
+    IoError(String),
 }
 
 impl TryFrom<&PathBuf> for Hgrid {
     type Error = HgridTryFromError;
     fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
-        let parsed_gr3 = gr3::parse_from_path_ref(path).map_err(|e| {
-            HgridTryFromError::TryFromPathBufError(path.display().to_string(), e.to_string())
-        })?;
+        let extension = strip_compression_extension(path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        let parsed_gr3 = match extension.as_deref() {
+            Some("2dm") => gr3::parse_2dm_from_path_ref(path).map_err(|e| e.to_string()),
+            Some("msh") => gmsh::parse_from_path_ref(path).map_err(|e| e.to_string()),
+            Some("nc") => ugrid::parse_from_path_ref(path).map_err(|e| e.to_string()),
+            Some("slf" | "selafin") => {
+                selafin::parse_from_path_ref(path).map_err(|e| e.to_string())
+            }
+            Some("node" | "ele" | "poly") => {
+                triangle::parse_from_path_ref(path).map_err(|e| e.to_string())
+            }
+            _ => gr3::parse_from_path_ref(path).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| HgridTryFromError::TryFromPathBufError(path.display().to_string(), e))?;
         // Use consuming TryFrom to avoid cloning
         Hgrid::try_from(parsed_gr3)
     }
@@ -497,6 +770,18 @@ impl TryFrom<&Url> for Hgrid {
     }
 }
 
+impl Hgrid {
+    /// Load a gr3 from a URL without blocking the async runtime.
+    ///
+    /// Unlike `Hgrid::try_from(&Url)`, this may be awaited from inside tokio.
+    pub async fn from_url_async(url: &Url) -> Result<Self, HgridTryFromError> {
+        let parsed_gr3 = gr3::parse_from_url_async(url)
+            .await
+            .map_err(|e| HgridTryFromError::TryFromUrlError(url.to_string(), e.to_string()))?;
+        Hgrid::try_from(parsed_gr3)
+    }
+}
+
 /// Consuming conversion from Gr3ParserOutput to Hgrid
 /// This avoids cloning the large data structures by taking ownership.
 /// No validation is performed during construction - use `check_validity()` if needed.
@@ -512,7 +797,19 @@ impl TryFrom<Gr3ParserOutput> for Hgrid {
             elements: elements_map,
             open_boundaries,
             land_boundaries,
+            land_boundaries_ibtypes,
             interior_boundaries,
+            interior_boundaries_ibtypes,
+            flow_boundaries,
+            flow_boundaries_ibtypes,
+            external_barrier_boundaries,
+            internal_barrier_boundaries,
+            culvert_boundaries,
+            non_ocean_boundaries_order,
+            // The 2DM mesh name is already the description
+            mesh_name: _,
+            num_materials_per_elem,
+            element_materials,
         } = parsed_gr3;
 
         // Build nodes (no clone needed - we own the data)
@@ -523,58 +820,131 @@ impl TryFrom<Gr3ParserOutput> for Hgrid {
             .map(Arc::new)?;
 
         // Build elements
-        let elements = ElementsBuilder::default()
+        let mut elements = ElementsBuilder::default()
             .nodes(nodes.clone())
             .hash_map(elements_map.unwrap_or_default())
             .build()?;
 
+        // Keep each 2DM material column as an element attribute, NaN where missing
+        if let Some(materials) = element_materials {
+            let columns = materials
+                .values()
+                .map(Vec::len)
+                .chain(num_materials_per_elem.map(|n| n as usize))
+                .max()
+                .unwrap_or(0);
+            for column in 0..columns {
+                let values: Array1<f64> = elements
+                    .ids()
+                    .iter()
+                    .map(|elem_id| {
+                        materials
+                            .get(elem_id)
+                            .and_then(|row| row.get(column))
+                            .map_or(f64::NAN, |&material| material as f64)
+                    })
+                    .collect();
+                elements
+                    .add_attribute(&gr3::material_attribute_name(column + 1), values)
+                    .expect("one value per element");
+            }
+        }
+
         // Check boundary presence
-        let is_open_boundary_present = open_boundaries
-            .as_ref()
-            .map_or(false, |v| !v.is_empty());
-        let is_land_boundary_present = land_boundaries
+        let is_open_boundary_present = open_boundaries.as_ref().is_some_and(|v| !v.is_empty());
+        let is_land_boundary_present = land_boundaries.as_ref().is_some_and(|v| !v.is_empty());
+        let is_interior_boundary_present =
+            interior_boundaries.as_ref().is_some_and(|v| !v.is_empty());
+        let is_flow_boundary_present = flow_boundaries.as_ref().is_some_and(|v| !v.is_empty());
+        let is_external_barrier_present = external_barrier_boundaries
             .as_ref()
-            .map_or(false, |v| !v.is_empty());
-        let is_interior_boundary_present = interior_boundaries
+            .is_some_and(|v| !v.is_empty());
+        let is_internal_barrier_present = internal_barrier_boundaries
             .as_ref()
-            .map_or(false, |v| !v.is_empty());
-
-        let boundaries =
-            if is_open_boundary_present || is_land_boundary_present || is_interior_boundary_present
-            {
-                let mut boundaries_builder = BoundariesBuilder::default();
-
-                if is_open_boundary_present {
-                    boundaries_builder.open(Some(
-                        OpenBoundariesBuilder::default()
-                            .nodes_ids(open_boundaries.unwrap_or_default())
-                            .nodes(nodes.clone())
-                            .build()?,
-                    ));
-                }
+            .is_some_and(|v| !v.is_empty());
+        let is_culvert_present = culvert_boundaries.as_ref().is_some_and(|v| !v.is_empty());
+
+        let boundaries = if is_open_boundary_present
+            || is_land_boundary_present
+            || is_interior_boundary_present
+            || is_flow_boundary_present
+            || is_external_barrier_present
+            || is_internal_barrier_present
+            || is_culvert_present
+        {
+            let mut boundaries_builder = BoundariesBuilder::default();
+            boundaries_builder.non_ocean_order(non_ocean_boundaries_order);
+
+            if is_open_boundary_present {
+                boundaries_builder.open(Some(
+                    OpenBoundariesBuilder::default()
+                        .nodes_ids(open_boundaries.unwrap_or_default())
+                        .nodes(nodes.clone())
+                        .build()?,
+                ));
+            }
 
-                if is_land_boundary_present {
-                    boundaries_builder.land(Some(
-                        LandBoundariesBuilder::default()
-                            .nodes_ids(land_boundaries.unwrap_or_default())
-                            .nodes(nodes.clone())
-                            .build()?,
-                    ));
-                }
+            if is_land_boundary_present {
+                boundaries_builder.land(Some(
+                    LandBoundariesBuilder::default()
+                        .nodes_ids(land_boundaries.unwrap_or_default())
+                        .ibtypes(land_boundaries_ibtypes.unwrap_or_default())
+                        .nodes(nodes.clone())
+                        .build()?,
+                ));
+            }
 
-                if is_interior_boundary_present {
-                    boundaries_builder.interior(Some(
-                        InteriorBoundariesBuilder::default()
-                            .nodes_ids(interior_boundaries.unwrap_or_default())
-                            .nodes(nodes.clone())
-                            .build()?,
-                    ));
-                }
+            if is_interior_boundary_present {
+                boundaries_builder.interior(Some(
+                    InteriorBoundariesBuilder::default()
+                        .nodes_ids(interior_boundaries.unwrap_or_default())
+                        .ibtypes(interior_boundaries_ibtypes.unwrap_or_default())
+                        .nodes(nodes.clone())
+                        .build()?,
+                ));
+            }
+
+            if is_flow_boundary_present {
+                boundaries_builder.flow(Some(
+                    FlowBoundariesBuilder::default()
+                        .nodes_ids(flow_boundaries.unwrap_or_default())
+                        .ibtypes(flow_boundaries_ibtypes.unwrap_or_default())
+                        .nodes(nodes.clone())
+                        .build()?,
+                ));
+            }
+
+            if is_external_barrier_present {
+                boundaries_builder.external_barrier(Some(
+                    ExternalBarrierBoundariesBuilder::default()
+                        .segments(external_barrier_boundaries.unwrap_or_default())
+                        .nodes(nodes.clone())
+                        .build()?,
+                ));
+            }
 
-                Some(boundaries_builder.build()?)
-            } else {
-                None
-            };
+            if is_internal_barrier_present {
+                boundaries_builder.internal_barrier(Some(
+                    InternalBarrierBoundariesBuilder::default()
+                        .segments(internal_barrier_boundaries.unwrap_or_default())
+                        .nodes(nodes.clone())
+                        .build()?,
+                ));
+            }
+
+            if is_culvert_present {
+                boundaries_builder.culvert(Some(
+                    CulvertBoundariesBuilder::default()
+                        .segments(culvert_boundaries.unwrap_or_default())
+                        .nodes(nodes.clone())
+                        .build()?,
+                ));
+            }
+
+            Some(boundaries_builder.build()?)
+        } else {
+            None
+        };
 
         Ok(Self {
             description,
@@ -582,6 +952,55 @@ impl TryFrom<Gr3ParserOutput> for Hgrid {
             elements,
             boundaries,
             depth_convention: DepthConvention::PositiveDown, // gr3 uses positive-down
+            topology: OnceLock::new(),
+        })
+    }
+}
+
+/// Serialized layout of [`Hgrid`]: nodes are stored once and shared again by
+/// the elements and boundaries on deserialization.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct HgridRepr<'a> {
+    nodes: Cow<'a, Nodes>,
+    elements: crate::elements::ElementsRepr<'a>,
+    boundaries: Option<crate::boundaries::BoundariesRepr<'a>>,
+    description: Option<Cow<'a, str>>,
+    #[serde(default)]
+    depth_convention: DepthConvention,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Hgrid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        HgridRepr {
+            nodes: Cow::Borrowed(self.nodes.as_ref()),
+            elements: self.elements.to_repr(false),
+            boundaries: self.boundaries.as_ref().map(|b| b.to_repr(false)),
+            description: self.description.as_deref().map(Cow::Borrowed),
+            depth_convention: self.depth_convention,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Hgrid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = HgridRepr::deserialize(deserializer)?;
+        let nodes = Arc::new(repr.nodes.into_owned());
+        let elements = repr
+            .elements
+            .into_elements(nodes.clone())
+            .map_err(serde::de::Error::custom)?;
+        let boundaries = repr.boundaries.map(|b| b.into_boundaries(nodes.clone()));
+        Ok(Hgrid {
+            nodes,
+            elements,
+            boundaries,
+            description: repr.description.map(Cow::into_owned),
+            depth_convention: repr.depth_convention,
+            topology: OnceLock::new(),
         })
     }
 }
@@ -857,6 +1276,283 @@ mod tests {
         assert!((depths_final[2] - 15.0).abs() < 1e-9);
     }
 
+    #[test]
+    fn test_2dm_materials_round_trip() {
+        let sms2dm = "MESH2D
+MESHNAME \"estuary\"
+NUM_MATERIALS_PER_ELEM 2
+E3T 1 1 2 3 1 7
+E4Q 2 2 4 5 3 2 7
+E3T 3 4 6 5 1 8
+ND 1 0 0 10
+ND 2 1 0 11
+ND 3 0 1 12
+ND 4 2 0 13
+ND 5 2 1 14
+ND 6 3 0.5 15
+";
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("mesh.2dm");
+        std::fs::write(&path, sms2dm).unwrap();
+        let hgrid = Hgrid::try_from(&path).unwrap();
+        assert_eq!(
+            hgrid.element_attribute("material_1").unwrap().to_vec(),
+            vec![1.0, 2.0, 1.0]
+        );
+        assert_eq!(
+            hgrid.element_attribute("material_2").unwrap().to_vec(),
+            vec![7.0, 7.0, 8.0]
+        );
+
+        let written = temp_dir.path().join("written.2dm");
+        hgrid.write_2dm(&written).unwrap();
+        let reparsed = gr3::parse_2dm_from_path_ref(&written).unwrap();
+        let original = gr3::parse_2dm_from_path_ref(&path).unwrap();
+        assert_eq!(reparsed.mesh_name(), Some("estuary"));
+        assert_eq!(reparsed.num_materials_per_elem(), Some(2));
+        // Triangles are written before quads, so compare by element id
+        for elem_id in [1, 2, 3] {
+            assert_eq!(
+                reparsed.element_materials().unwrap().get(&elem_id),
+                original.element_materials().unwrap().get(&elem_id)
+            );
+        }
+        assert_eq!(reparsed.to_2dm_string(), original.to_2dm_string());
+    }
+
+    #[test]
+    fn test_write_with_attribute() {
+        let mut nodes_hash_map: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> =
+            LinkedHashMap::new();
+        nodes_hash_map.insert(1, (vec![0.0, 0.0], Some(vec![10.0])));
+        nodes_hash_map.insert(2, (vec![1.0, 0.0], Some(vec![20.0])));
+        nodes_hash_map.insert(3, (vec![0.5, 1.0], Some(vec![15.0])));
+        let mut elements_hash_map: LinkedHashMap<u32, Vec<u32>> = LinkedHashMap::new();
+        elements_hash_map.insert(1, vec![1, 2, 3]);
+        let gr3 = Gr3ParserOutputBuilder::default()
+            .nodes(nodes_hash_map)
+            .elements(elements_hash_map)
+            .build()
+            .unwrap();
+        let mut hgrid = Hgrid::try_from(gr3).unwrap();
+
+        let manning = NodeAttribute::Multi(ndarray::array![[0.02, 1.0], [0.03, 2.0], [0.04, 3.0]]);
+        hgrid
+            .add_node_attribute("manning", manning.clone())
+            .unwrap();
+        assert_eq!(hgrid.node_attribute("manning"), Some(&manning));
+        assert!(std::ptr::eq(hgrid.elements().nodes(), hgrid.nodes()));
+
+        // Attributes survive a depth flip
+        hgrid.flip_depths();
+        assert_eq!(hgrid.node_attribute("manning"), Some(&manning));
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("manning.gr3");
+        hgrid.write_with_attribute(&path, "manning").unwrap();
+        let written = gr3::parse_from_path_ref(&path).unwrap();
+        assert_eq!(written.nodes().get(&3).unwrap().1, Some(vec![0.04, 3.0]));
+
+        let path = temp_dir.path().join("hgrid.gr3");
+        hgrid.write_with_attribute(&path, DEPTH_ATTRIBUTE).unwrap();
+        let written = gr3::parse_from_path_ref(&path).unwrap();
+        assert_eq!(written.nodes().get(&2).unwrap().1, Some(vec![20.0]));
+
+        assert!(hgrid.write_with_attribute(&path, "missing").is_err());
+        assert_eq!(hgrid.remove_node_attribute("manning"), Some(manning));
+        assert!(hgrid.node_attribute("manning").is_none());
+    }
+
+    const GRID_SET_GR3: &str = "EPSG:4326 grid set
+2 4
+1 -70 40 5
+2 -69 40 6
+3 -69 41 7
+4 -70 41 8
+1 3 1 2 3
+2 3 1 3 4
+1 ! total number of open boundaries
+2 ! total number of open boundary nodes
+2 ! number of nodes for open_boundary_1
+1
+2
+1 ! total number of non-ocean boundaries
+3 ! total number of non-ocean boundaries nodes
+3 0 ! number of nodes for land_boundary_1
+2
+3
+4";
+
+    #[test]
+    fn test_write_schism_grid_set() {
+        let gr3 = gr3::parse_from_reader(std::io::BufReader::new(GRID_SET_GR3.as_bytes()), "test")
+            .unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        hgrid.write_schism_grid_set(temp_dir.path()).unwrap();
+        let gr3_path = temp_dir.path().join("hgrid.gr3");
+        let ll_path = temp_dir.path().join("hgrid.ll");
+        verify_grid_set(&gr3_path, &ll_path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&gr3_path).unwrap(),
+            std::fs::read_to_string(&ll_path).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_write_schism_grid_set_from_projected_crs() {
+        // UTM zone 19N; node 1 lies on the central meridian (69W) at 42N
+        let utm = GRID_SET_GR3
+            .replace("EPSG:4326", "EPSG:32619")
+            .replace("1 -70 40 5", "1 500000 4649776.2248 5")
+            .replace("2 -69 40 6", "2 501000 4649776.2248 6")
+            .replace("3 -69 41 7", "3 501000 4650776.2248 7")
+            .replace("4 -70 41 8", "4 500000 4650776.2248 8");
+        let gr3 = gr3::parse_from_reader(std::io::BufReader::new(utm.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+        assert!(!hgrid.is_geographic());
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        hgrid.write_schism_grid_set(temp_dir.path()).unwrap();
+        let gr3_path = temp_dir.path().join("hgrid.gr3");
+        let ll_path = temp_dir.path().join("hgrid.ll");
+        verify_grid_set(&gr3_path, &ll_path).unwrap();
+
+        let native = Hgrid::try_from(&gr3_path).unwrap();
+        assert_eq!(native.x().to_vec(), hgrid.x().to_vec());
+        assert_eq!(native.y().to_vec(), hgrid.y().to_vec());
+
+        let lonlat = Hgrid::try_from(&ll_path).unwrap();
+        assert!(lonlat.is_geographic());
+        assert!((lonlat.x()[0] + 69.0).abs() < 1e-6);
+        assert!((lonlat.y()[0] - 42.0).abs() < 1e-6);
+        // 1 km east is about 0.0121 degrees of longitude at 42N
+        assert!((lonlat.x()[1] - lonlat.x()[0] - 0.0121).abs() < 1e-4);
+        assert!((lonlat.y()[3] - lonlat.y()[0] - 0.009).abs() < 1e-4);
+        assert_eq!(lonlat.depths(), hgrid.depths());
+    }
+
+    #[test]
+    fn test_unreadable_grid_set_is_not_written() {
+        let gr3 = gr3::parse_from_reader(std::io::BufReader::new(GRID_SET_GR3.as_bytes()), "test")
+            .unwrap();
+        let mut hgrid = Hgrid::try_from(gr3).unwrap();
+        // Pushes the node and element counts off the second line
+        hgrid.description = Some("grid\nset".to_string());
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(hgrid.write_schism_grid_set(temp_dir.path()).is_err());
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_topology_mismatch_is_detected() {
+        let gr3 = gr3::parse_from_reader(std::io::BufReader::new(GRID_SET_GR3.as_bytes()), "test")
+            .unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+        assert!(hgrid.has_same_topology(&hgrid.clone()));
+
+        let renumbered = GRID_SET_GR3.replace("2 3 1 3 4", "2 3 1 4 3");
+        let gr3 =
+            gr3::parse_from_reader(std::io::BufReader::new(renumbered.as_bytes()), "test").unwrap();
+        let other = Hgrid::try_from(gr3).unwrap();
+        assert!(!hgrid.has_same_topology(&other));
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let gr3_path = temp_dir.path().join("hgrid.gr3");
+        let ll_path = temp_dir.path().join("hgrid.ll");
+        hgrid.write(&gr3_path).unwrap();
+        other.write(&ll_path).unwrap();
+        assert!(matches!(
+            verify_grid_set(&gr3_path, &ll_path),
+            Err(HgridTryFromError::TopologyMismatch(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json_roundtrip() {
+        let gr3 = gr3::parse_from_reader(std::io::BufReader::new(GRID_SET_GR3.as_bytes()), "test")
+            .unwrap();
+        let mut hgrid = Hgrid::try_from(gr3).unwrap();
+        hgrid
+            .add_node_attribute(
+                "zeta",
+                NodeAttribute::Scalar(ndarray::array![0.1, 0.2, 0.3, 0.4]),
+            )
+            .unwrap();
+        hgrid
+            .add_node_attribute("manning", NodeAttribute::Scalar(Array1::from_elem(4, 0.02)))
+            .unwrap();
+        hgrid
+            .add_element_attribute("quality", ndarray::array![0.9, 0.8])
+            .unwrap();
+        hgrid.flip_depths();
+
+        let json = serde_json::to_string(&hgrid).unwrap();
+        // Nodes are written once, not once per Arc holder
+        assert_eq!(json.matches("\"coords\"").count(), 1);
+        let restored: Hgrid = serde_json::from_str(&json).unwrap();
+
+        assert!(std::ptr::eq(restored.elements().nodes(), restored.nodes()));
+        assert_eq!(
+            restored.nodes().attribute_names(),
+            hgrid.nodes().attribute_names()
+        );
+        assert_eq!(restored.depths(), hgrid.depths());
+        assert_eq!(restored.depth_convention(), DepthConvention::PositiveUp);
+        assert_eq!(restored.crs(), hgrid.crs());
+        assert_eq!(restored.description(), hgrid.description());
+        assert_eq!(
+            restored.elements().connectivity(),
+            hgrid.elements().connectivity()
+        );
+        assert_eq!(
+            restored.element_attribute("quality"),
+            hgrid.element_attribute("quality")
+        );
+        let boundaries = restored.boundaries().unwrap();
+        assert_eq!(boundaries.open().unwrap().nodes_ids(), &vec![vec![1, 2]]);
+        assert_eq!(boundaries.land().unwrap().nodes_ids(), &vec![vec![2, 3, 4]]);
+        assert!(boundaries.interior().is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_standalone_components() {
+        let gr3 = gr3::parse_from_reader(std::io::BufReader::new(GRID_SET_GR3.as_bytes()), "test")
+            .unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+
+        let json = serde_json::to_string(hgrid.elements()).unwrap();
+        let elements: Elements = serde_json::from_str(&json).unwrap();
+        assert_eq!(elements.ids(), hgrid.elements().ids());
+        assert_eq!(elements.nodes().ids(), hgrid.nodes().ids());
+
+        let json = serde_json::to_string(hgrid.boundaries().unwrap()).unwrap();
+        let boundaries: Boundaries = serde_json::from_str(&json).unwrap();
+        assert_eq!(boundaries.land().unwrap().ibtypes(), vec![0]);
+
+        // Inside an Hgrid the nodes come from the grid, standalone they are required
+        let mut value = serde_json::to_value(hgrid.elements()).unwrap();
+        value.as_object_mut().unwrap().remove("nodes");
+        assert!(serde_json::from_value::<Elements>(value).is_err());
+
+        let validation = hgrid.check_validity();
+        let json = serde_json::to_string(&validation).unwrap();
+        let restored: crate::MeshValidation = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.is_ok(), validation.is_ok());
+    }
+
+    #[test]
+    fn test_wgs84_detection() {
+        assert!(is_wgs84("EPSG:4326"));
+        assert!(is_wgs84("epsg:4326"));
+        // Geographic, but not WGS84: hgrid.ll still needs a datum shift
+        assert!(!is_wgs84("EPSG:4269"));
+    }
+
     #[test]
     #[ignore] // Requires the dev hgrid file to exist
     fn test_load_dev_hgrid_epsg4326() {
@@ -3,13 +3,33 @@ pub use hgrid::DepthConvention;
 pub use hgrid::Hgrid;
 pub use hgrid::HgridBuilder;
 pub use hgrid::HgridTryFromError;
+pub use nodes::NodeAttribute;
+pub use topology::Topology;
 pub use validation::MeshValidation;
 
 pub mod boundaries;
 pub mod boundary_polygon;
+pub mod cache;
+pub mod coincident;
+pub mod components;
+pub mod compression;
 pub mod elements;
+pub mod features;
+#[cfg(test)]
+mod fixtures;
+pub mod fort13;
+pub mod gmsh;
 pub mod gr3;
 mod hash;
 pub mod hgrid;
+mod netcdf3;
 pub mod nodes;
+mod output;
+pub mod prop;
+pub mod selafin;
+pub mod shapefile;
+pub mod topology;
+pub mod triangle;
+pub mod ugrid;
 pub mod validation;
+pub mod vtu;
@@ -0,0 +1,568 @@
+//! Minimal pure-Rust NetCDF-3 (classic and 64-bit offset) codec
+//!
+//! Only what the UGRID import/export needs: fixed-size variables of the classic
+//! types, with dimensions and attributes. Record (unlimited) variables are
+//! skipped on read and never written.
+
+use std::io::{self, Write};
+use thiserror::Error;
+
+const NC_DIMENSION: u32 = 0x0A;
+const NC_VARIABLE: u32 = 0x0B;
+const NC_ATTRIBUTE: u32 = 0x0C;
+
+const NC_BYTE: u32 = 1;
+const NC_CHAR: u32 = 2;
+const NC_SHORT: u32 = 3;
+const NC_INT: u32 = 4;
+const NC_FLOAT: u32 = 5;
+const NC_DOUBLE: u32 = 6;
+
+/// Values of a variable or attribute, tagged with their NetCDF type
+#[derive(Debug, Clone, PartialEq)]
+pub enum NcValues {
+    Byte(Vec<i8>),
+    Char(Vec<u8>),
+    Short(Vec<i16>),
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+}
+
+impl NcValues {
+    pub fn text(text: &str) -> NcValues {
+        NcValues::Char(text.as_bytes().to_vec())
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            NcValues::Byte(v) => v.len(),
+            NcValues::Char(v) => v.len(),
+            NcValues::Short(v) => v.len(),
+            NcValues::Int(v) => v.len(),
+            NcValues::Float(v) => v.len(),
+            NcValues::Double(v) => v.len(),
+        }
+    }
+
+    /// Text of a character attribute, without trailing NULs
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            NcValues::Char(v) => Some(
+                String::from_utf8_lossy(v)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Numeric values widened to f64 (None for text)
+    pub fn to_f64(&self) -> Option<Vec<f64>> {
+        Some(match self {
+            NcValues::Byte(v) => v.iter().map(|&x| x as f64).collect(),
+            NcValues::Char(_) => return None,
+            NcValues::Short(v) => v.iter().map(|&x| x as f64).collect(),
+            NcValues::Int(v) => v.iter().map(|&x| x as f64).collect(),
+            NcValues::Float(v) => v.iter().map(|&x| x as f64).collect(),
+            NcValues::Double(v) => v.clone(),
+        })
+    }
+
+    /// Numeric values as i64, rounding floats (None for text)
+    pub fn to_i64(&self) -> Option<Vec<i64>> {
+        Some(match self {
+            NcValues::Byte(v) => v.iter().map(|&x| x as i64).collect(),
+            NcValues::Char(_) => return None,
+            NcValues::Short(v) => v.iter().map(|&x| x as i64).collect(),
+            NcValues::Int(v) => v.iter().map(|&x| x as i64).collect(),
+            NcValues::Float(v) => v.iter().map(|&x| x.round() as i64).collect(),
+            NcValues::Double(v) => v.iter().map(|&x| x.round() as i64).collect(),
+        })
+    }
+
+    fn nc_type(&self) -> u32 {
+        match self {
+            NcValues::Byte(_) => NC_BYTE,
+            NcValues::Char(_) => NC_CHAR,
+            NcValues::Short(_) => NC_SHORT,
+            NcValues::Int(_) => NC_INT,
+            NcValues::Float(_) => NC_FLOAT,
+            NcValues::Double(_) => NC_DOUBLE,
+        }
+    }
+
+    fn byte_len(&self) -> usize {
+        self.len() * type_size(self.nc_type())
+    }
+
+    fn write_be<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            NcValues::Byte(v) => {
+                let bytes: Vec<u8> = v.iter().map(|&x| x as u8).collect();
+                writer.write_all(&bytes)
+            }
+            NcValues::Char(v) => writer.write_all(v),
+            NcValues::Short(v) => v
+                .iter()
+                .try_for_each(|x| writer.write_all(&x.to_be_bytes())),
+            NcValues::Int(v) => v
+                .iter()
+                .try_for_each(|x| writer.write_all(&x.to_be_bytes())),
+            NcValues::Float(v) => v
+                .iter()
+                .try_for_each(|x| writer.write_all(&x.to_be_bytes())),
+            NcValues::Double(v) => v
+                .iter()
+                .try_for_each(|x| writer.write_all(&x.to_be_bytes())),
+        }
+    }
+
+    fn read_be(nc_type: u32, bytes: &[u8]) -> Result<NcValues, NetCdfError> {
+        Ok(match nc_type {
+            NC_BYTE => NcValues::Byte(bytes.iter().map(|&b| b as i8).collect()),
+            NC_CHAR => NcValues::Char(bytes.to_vec()),
+            NC_SHORT => NcValues::Short(
+                bytes
+                    .chunks_exact(2)
+                    .map(|c| i16::from_be_bytes([c[0], c[1]]))
+                    .collect(),
+            ),
+            NC_INT => NcValues::Int(
+                bytes
+                    .chunks_exact(4)
+                    .map(|c| i32::from_be_bytes(c.try_into().unwrap()))
+                    .collect(),
+            ),
+            NC_FLOAT => NcValues::Float(
+                bytes
+                    .chunks_exact(4)
+                    .map(|c| f32::from_be_bytes(c.try_into().unwrap()))
+                    .collect(),
+            ),
+            NC_DOUBLE => NcValues::Double(
+                bytes
+                    .chunks_exact(8)
+                    .map(|c| f64::from_be_bytes(c.try_into().unwrap()))
+                    .collect(),
+            ),
+            other => return Err(NetCdfError::Format(format!("unknown nc_type {}", other))),
+        })
+    }
+}
+
+fn type_size(nc_type: u32) -> usize {
+    match nc_type {
+        NC_BYTE | NC_CHAR => 1,
+        NC_SHORT => 2,
+        NC_INT | NC_FLOAT => 4,
+        _ => 8,
+    }
+}
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NcDimension {
+    pub name: String,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NcAttribute {
+    pub name: String,
+    pub values: NcValues,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NcVariable {
+    pub name: String,
+    /// Indices into the file's dimensions
+    pub dims: Vec<usize>,
+    pub attributes: Vec<NcAttribute>,
+    pub values: NcValues,
+}
+
+impl NcVariable {
+    pub fn attribute(&self, name: &str) -> Option<&NcValues> {
+        self.attributes
+            .iter()
+            .find(|a| a.name == name)
+            .map(|a| &a.values)
+    }
+
+    pub fn text_attribute(&self, name: &str) -> Option<String> {
+        self.attribute(name).and_then(NcValues::as_text)
+    }
+}
+
+/// In-memory NetCDF-3 dataset
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NcFile {
+    pub dimensions: Vec<NcDimension>,
+    pub attributes: Vec<NcAttribute>,
+    pub variables: Vec<NcVariable>,
+}
+
+#[derive(Error, Debug)]
+pub enum NetCdfError {
+    #[error("Not a NetCDF-3 file: {0}")]
+    Format(String),
+
+    #[error("Variable {0} has {1} values but its dimensions hold {2}")]
+    ShapeMismatch(String, usize, usize),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl NcFile {
+    /// Add a dimension, returning its index. Lengths must be non-zero, since a
+    /// zero length marks the record dimension in NetCDF-3.
+    pub fn add_dimension(&mut self, name: &str, len: usize) -> usize {
+        assert!(len > 0, "NetCDF-3 fixed dimensions must be non-empty");
+        self.dimensions.push(NcDimension {
+            name: name.to_string(),
+            len,
+        });
+        self.dimensions.len() - 1
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&NcVariable> {
+        self.variables.iter().find(|v| v.name == name)
+    }
+
+    pub fn global_text_attribute(&self, name: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|a| a.name == name)
+            .and_then(|a| a.values.as_text())
+    }
+
+    /// Encode the dataset. Uses the 64-bit offset variant only when the data
+    /// would not fit classic 32-bit offsets.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), NetCdfError> {
+        for variable in &self.variables {
+            let expected: usize = variable
+                .dims
+                .iter()
+                .map(|&d| self.dimensions[d].len)
+                .product();
+            if variable.values.len() != expected {
+                return Err(NetCdfError::ShapeMismatch(
+                    variable.name.clone(),
+                    variable.values.len(),
+                    expected,
+                ));
+            }
+        }
+        let data_len: usize = self
+            .variables
+            .iter()
+            .map(|v| padded(v.values.byte_len()))
+            .sum();
+        let header_len = self.header(1, &[]).len();
+        let version = if header_len + data_len > i32::MAX as usize {
+            2
+        } else {
+            1
+        };
+
+        let header_len = self.header(version, &[]).len();
+        let mut begins = Vec::with_capacity(self.variables.len());
+        let mut offset = header_len;
+        for variable in &self.variables {
+            begins.push(offset as u64);
+            offset += padded(variable.values.byte_len());
+        }
+        writer.write_all(&self.header(version, &begins))?;
+        for variable in &self.variables {
+            variable.values.write_be(writer)?;
+            let padding = padded(variable.values.byte_len()) - variable.values.byte_len();
+            writer.write_all(&[0u8; 4][..padding])?;
+        }
+        Ok(())
+    }
+
+    fn header(&self, version: u8, begins: &[u64]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(b"CDF");
+        header.push(version);
+        put_u32(&mut header, 0); // numrecs
+
+        put_list_tag(&mut header, NC_DIMENSION, self.dimensions.len());
+        for dimension in &self.dimensions {
+            put_name(&mut header, &dimension.name);
+            put_u32(&mut header, dimension.len as u32);
+        }
+
+        put_attributes(&mut header, &self.attributes);
+
+        put_list_tag(&mut header, NC_VARIABLE, self.variables.len());
+        for (index, variable) in self.variables.iter().enumerate() {
+            put_name(&mut header, &variable.name);
+            put_u32(&mut header, variable.dims.len() as u32);
+            for &dim in &variable.dims {
+                put_u32(&mut header, dim as u32);
+            }
+            put_attributes(&mut header, &variable.attributes);
+            put_u32(&mut header, variable.values.nc_type());
+            let vsize = padded(variable.values.byte_len()).min(u32::MAX as usize);
+            put_u32(&mut header, vsize as u32);
+            let begin = begins.get(index).copied().unwrap_or(0);
+            if version == 1 {
+                put_u32(&mut header, begin as u32);
+            } else {
+                header.extend_from_slice(&begin.to_be_bytes());
+            }
+        }
+        header
+    }
+
+    /// Decode a NetCDF-3 dataset held in memory.
+    pub fn read(bytes: &[u8]) -> Result<NcFile, NetCdfError> {
+        let mut cursor = Cursor { bytes, position: 0 };
+        let magic = cursor.take(4)?;
+        if &magic[..3] != b"CDF" || !(magic[3] == 1 || magic[3] == 2) {
+            return Err(NetCdfError::Format(
+                "missing CDF\\x01 or CDF\\x02 magic (NetCDF-4/HDF5 files are not supported)"
+                    .to_string(),
+            ));
+        }
+        let version = magic[3];
+        let _numrecs = cursor.u32()?;
+
+        let mut file = NcFile::default();
+        let mut record_dimension = None;
+        let count = cursor.list_tag(NC_DIMENSION)?;
+        for index in 0..count {
+            let name = cursor.name()?;
+            let len = cursor.u32()? as usize;
+            if len == 0 {
+                record_dimension = Some(index);
+            }
+            file.dimensions.push(NcDimension { name, len });
+        }
+
+        file.attributes = cursor.attributes()?;
+
+        let count = cursor.list_tag(NC_VARIABLE)?;
+        for _ in 0..count {
+            let name = cursor.name()?;
+            let ndims = cursor.u32()? as usize;
+            let mut dims = Vec::new();
+            for _ in 0..ndims {
+                let dim = cursor.u32()? as usize;
+                if dim >= file.dimensions.len() {
+                    return Err(NetCdfError::Format(format!(
+                        "variable {} references unknown dimension {}",
+                        name, dim
+                    )));
+                }
+                dims.push(dim);
+            }
+            let attributes = cursor.attributes()?;
+            let nc_type = cursor.u32()?;
+            let _vsize = cursor.u32()?;
+            let begin = if version == 1 {
+                cursor.u32()? as usize
+            } else {
+                cursor.u64()? as usize
+            };
+            if dims.first().is_some_and(|d| Some(*d) == record_dimension) {
+                log::debug!("Skipping record variable {}", name);
+                continue;
+            }
+            // Lengths come from the header, so a crafted file may overflow
+            let data = dims
+                .iter()
+                .try_fold(type_size(nc_type), |size, &d| {
+                    size.checked_mul(file.dimensions[d].len)
+                })
+                .and_then(|byte_len| begin.checked_add(byte_len))
+                .and_then(|end| bytes.get(begin..end))
+                .ok_or_else(|| {
+                    NetCdfError::Format(format!("data of variable {} is truncated", name))
+                })?;
+            file.variables.push(NcVariable {
+                name,
+                dims,
+                attributes,
+                values: NcValues::read_be(nc_type, data)?,
+            });
+        }
+        Ok(file)
+    }
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_list_tag(buffer: &mut Vec<u8>, tag: u32, count: usize) {
+    // An empty list is written as ABSENT (two zero words)
+    put_u32(buffer, if count == 0 { 0 } else { tag });
+    put_u32(buffer, count as u32);
+}
+
+fn put_name(buffer: &mut Vec<u8>, name: &str) {
+    put_u32(buffer, name.len() as u32);
+    buffer.extend_from_slice(name.as_bytes());
+    buffer.resize(padded(buffer.len()), 0);
+}
+
+fn put_attributes(buffer: &mut Vec<u8>, attributes: &[NcAttribute]) {
+    put_list_tag(buffer, NC_ATTRIBUTE, attributes.len());
+    for attribute in attributes {
+        put_name(buffer, &attribute.name);
+        put_u32(buffer, attribute.values.nc_type());
+        put_u32(buffer, attribute.values.len() as u32);
+        attribute.values.write_be(buffer).unwrap();
+        buffer.resize(padded(buffer.len()), 0);
+    }
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NetCdfError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| NetCdfError::Format("header is truncated".to_string()))?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, NetCdfError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, NetCdfError> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn list_tag(&mut self, expected: u32) -> Result<usize, NetCdfError> {
+        let tag = self.u32()?;
+        let count = self.u32()? as usize;
+        if tag != expected && !(tag == 0 && count == 0) {
+            return Err(NetCdfError::Format(format!(
+                "expected list tag {:#x} but found {:#x}",
+                expected, tag
+            )));
+        }
+        Ok(count)
+    }
+
+    fn name(&mut self) -> Result<String, NetCdfError> {
+        let len = self.u32()? as usize;
+        let name = String::from_utf8_lossy(self.take(len)?).to_string();
+        self.take(padded(len) - len)?;
+        Ok(name)
+    }
+
+    fn attributes(&mut self) -> Result<Vec<NcAttribute>, NetCdfError> {
+        let count = self.list_tag(NC_ATTRIBUTE)?;
+        let mut attributes = Vec::new();
+        for _ in 0..count {
+            let name = self.name()?;
+            let nc_type = self.u32()?;
+            let len = self.u32()? as usize;
+            let byte_len = len
+                .checked_mul(type_size(nc_type))
+                .ok_or_else(|| NetCdfError::Format("header is truncated".to_string()))?;
+            let values = NcValues::read_be(nc_type, self.take(byte_len)?)?;
+            self.take(padded(byte_len) - byte_len)?;
+            attributes.push(NcAttribute { name, values });
+        }
+        Ok(attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut file = NcFile::default();
+        let n = file.add_dimension("n", 3);
+        let two = file.add_dimension("two", 2);
+        file.attributes.push(NcAttribute {
+            name: "title".to_string(),
+            values: NcValues::text("odd"),
+        });
+        file.variables.push(NcVariable {
+            name: "x".to_string(),
+            dims: vec![n],
+            attributes: vec![NcAttribute {
+                name: "units".to_string(),
+                values: NcValues::text("m"),
+            }],
+            values: NcValues::Double(vec![1.0, 2.5, -3.0]),
+        });
+        file.variables.push(NcVariable {
+            name: "pairs".to_string(),
+            dims: vec![n, two],
+            attributes: vec![],
+            values: NcValues::Short(vec![1, 2, 3, 4, 5, 6]),
+        });
+        file.variables.push(NcVariable {
+            name: "scalar".to_string(),
+            dims: vec![],
+            attributes: vec![],
+            values: NcValues::Int(vec![7]),
+        });
+
+        let mut bytes = Vec::new();
+        file.write(&mut bytes).unwrap();
+        assert_eq!(&bytes[..4], b"CDF\x01");
+        assert_eq!(bytes.len() % 4, 0);
+        assert_eq!(NcFile::read(&bytes).unwrap(), file);
+    }
+
+    #[test]
+    fn test_shape_mismatch_is_rejected() {
+        let mut file = NcFile::default();
+        let n = file.add_dimension("n", 3);
+        file.variables.push(NcVariable {
+            name: "x".to_string(),
+            dims: vec![n],
+            attributes: vec![],
+            values: NcValues::Int(vec![1]),
+        });
+        assert!(file.write(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_oversized_dimensions_are_rejected() {
+        let mut file = NcFile::default();
+        let n = file.add_dimension("n", 1);
+        let m = file.add_dimension("m", 1);
+        file.variables.push(NcVariable {
+            name: "x".to_string(),
+            dims: vec![n, m],
+            attributes: vec![],
+            values: NcValues::Double(vec![1.0]),
+        });
+        let mut bytes = Vec::new();
+        file.write(&mut bytes).unwrap();
+        // Both dimension lengths, after the one-letter padded names
+        for offset in [24, 36] {
+            bytes[offset..offset + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        }
+        assert!(matches!(NcFile::read(&bytes), Err(NetCdfError::Format(_))));
+    }
+
+    #[test]
+    fn test_hdf5_is_rejected() {
+        assert!(NcFile::read(b"\x89HDF\r\n\x1a\n").is_err());
+    }
+}
@@ -1,23 +1,240 @@
 use derive_builder::Builder;
 use ndarray::prelude::*;
 use proj::Proj;
+#[cfg(feature = "serde")]
+use std::borrow::Cow;
 // use std::collections::BTreeMap;
 use linked_hash_map::LinkedHashMap;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
 
+/// Name of the attribute backed by the gr3 value column(s).
+pub const DEPTH_ATTRIBUTE: &str = "depth";
+
+/// Values of a named nodal attribute, one row per node in node order.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeAttribute {
+    /// One value per node
+    Scalar(Array1<f64>),
+    /// Several values per node, shaped (number of nodes, number of columns)
+    Multi(Array2<f64>),
+}
+
+impl NodeAttribute {
+    /// Number of rows (nodes) covered by the attribute
+    pub fn len(&self) -> usize {
+        match self {
+            NodeAttribute::Scalar(values) => values.len(),
+            NodeAttribute::Multi(values) => values.nrows(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of values per node
+    pub fn ncols(&self) -> usize {
+        match self {
+            NodeAttribute::Scalar(_) => 1,
+            NodeAttribute::Multi(values) => values.ncols(),
+        }
+    }
+
+    /// Values of the attribute at node position `index`
+    pub fn row(&self, index: usize) -> Vec<f64> {
+        match self {
+            NodeAttribute::Scalar(values) => vec![values[index]],
+            NodeAttribute::Multi(values) => values.row(index).to_vec(),
+        }
+    }
+
+    /// Apply `f` to every value
+    pub fn mapv(&self, f: impl Fn(f64) -> f64) -> NodeAttribute {
+        match self {
+            NodeAttribute::Scalar(values) => NodeAttribute::Scalar(values.mapv(f)),
+            NodeAttribute::Multi(values) => NodeAttribute::Multi(values.mapv(f)),
+        }
+    }
+
+    /// Rows of the attribute at the given node positions
+    pub(crate) fn select(&self, positions: &[usize]) -> NodeAttribute {
+        match self {
+            NodeAttribute::Scalar(values) => {
+                NodeAttribute::Scalar(values.select(Axis(0), positions))
+            }
+            NodeAttribute::Multi(values) => NodeAttribute::Multi(values.select(Axis(0), positions)),
+        }
+    }
+
+    /// The `index`-th column of the attribute
+    pub fn column(&self, index: usize) -> Option<Array1<f64>> {
+        match self {
+            NodeAttribute::Scalar(values) if index == 0 => Some(values.clone()),
+            NodeAttribute::Multi(values) if index < values.ncols() => {
+                Some(values.column(index).to_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum NodeAttributeError {
+    #[error("Attribute {0} has {1} rows but the mesh has {2} nodes")]
+    LengthMismatch(String, usize, usize),
+}
+
+/// Mesh nodes stored as contiguous arrays.
+///
+/// Coordinates live in an `(n, 2)` array in node order, with a side table from
+/// node id to row index. Depth (the gr3 value column) and any other named
+/// attributes are stored column-wise alongside.
 #[derive(Builder, Debug, Clone)]
-#[builder(setter(into))]
+#[builder(
+    setter(into),
+    build_fn(private, name = "build_unindexed", validate = "Self::validate")
+)]
 pub struct Nodes {
-    hash_map: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)>,
+    /// Node ids in node order
+    ids: Vec<u32>,
+    #[builder(setter(skip))]
+    index_from_id: HashMap<u32, usize>,
+    /// Node coordinates, shaped (number of nodes, 2)
+    coords: Array2<f64>,
+    /// The gr3 value column(s), if any
+    #[builder(default)]
+    depth: Option<NodeAttribute>,
     /// CRS definition string (e.g., "EPSG:4326", "EPSG:32618")
     /// Stored as string to make Nodes Send + Sync safe.
     /// Use `proj()` to create a Proj instance when needed.
     #[builder(default)]
     crs: Option<String>,
+    /// Named attributes other than depth
+    #[builder(default)]
+    attributes: LinkedHashMap<String, NodeAttribute>,
+}
+
+impl NodesBuilder {
+    /// Fill ids, coordinates and depth from the parser's node map.
+    ///
+    /// Nodes without values get NaN depths; if no node carries values, no depth is set.
+    pub fn hash_map<VALUE: Into<LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)>>>(
+        &mut self,
+        value: VALUE,
+    ) -> &mut Self {
+        let hash_map = value.into();
+        let ncols = hash_map
+            .values()
+            .filter_map(|(_, values)| values.as_ref().map(Vec::len))
+            .max();
+        let mut ids = Vec::with_capacity(hash_map.len());
+        let mut coords = Vec::with_capacity(hash_map.len() * 2);
+        let mut depth = Vec::with_capacity(hash_map.len() * ncols.unwrap_or(0));
+        for (&node_id, (node_coords, values)) in hash_map.iter() {
+            ids.push(node_id);
+            coords.push(node_coords[0]);
+            coords.push(node_coords[1]);
+            if let Some(ncols) = ncols {
+                let values = values.as_deref().unwrap_or(&[]);
+                depth.extend((0..ncols).map(|i| values.get(i).copied().unwrap_or(f64::NAN)));
+            }
+        }
+        self.coords = Some(Array2::from_shape_vec((ids.len(), 2), coords).unwrap());
+        self.depth = Some(ncols.map(|ncols| match ncols {
+            1 => NodeAttribute::Scalar(Array1::from(depth)),
+            _ => NodeAttribute::Multi(Array2::from_shape_vec((ids.len(), ncols), depth).unwrap()),
+        }));
+        self.ids = Some(ids);
+        self
+    }
+
+    pub fn build(&self) -> Result<Nodes, NodesBuilderError> {
+        let mut nodes = self.build_unindexed()?;
+        nodes.index_from_id = nodes
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(index, &node_id)| (node_id, index))
+            .collect();
+        Ok(nodes)
+    }
+
+    /// Validates array shapes, id uniqueness and that every attribute has one row per node.
+    fn validate(&self) -> Result<(), NodesBuilderError> {
+        let Some(ids) = &self.ids else {
+            return Ok(());
+        };
+        let mut seen = HashSet::with_capacity(ids.len());
+        if let Some(node_id) = ids.iter().find(|&&node_id| !seen.insert(node_id)) {
+            return Err(NodesBuilderError::ValidationError(format!(
+                "Node id {} is repeated",
+                node_id
+            )));
+        }
+        if let Some(coords) = &self.coords {
+            if coords.dim() != (ids.len(), 2) {
+                return Err(NodesBuilderError::ValidationError(format!(
+                    "Coordinates have shape {:?} but expected ({}, 2)",
+                    coords.dim(),
+                    ids.len()
+                )));
+            }
+        }
+        let depth = self
+            .depth
+            .as_ref()
+            .and_then(Option::as_ref)
+            .map(|depth| (DEPTH_ATTRIBUTE, depth));
+        let attributes = self
+            .attributes
+            .iter()
+            .flat_map(|attributes| attributes.iter().map(|(name, a)| (name.as_str(), a)));
+        for (name, attribute) in depth.into_iter().chain(attributes) {
+            if attribute.len() != ids.len() {
+                return Err(NodesBuilderError::ValidationError(
+                    NodeAttributeError::LengthMismatch(
+                        name.to_string(),
+                        attribute.len(),
+                        ids.len(),
+                    )
+                    .to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Nodes {
-    pub fn hash_map(&self) -> &LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> {
-        &self.hash_map
+    /// Node map in the parser's layout.
+    ///
+    /// Built on demand, so prefer `ids()`, `xy()` and `attribute()` in hot paths.
+    /// A node whose depth row is all NaN comes back without values.
+    pub fn hash_map(&self) -> LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> {
+        self.ids
+            .iter()
+            .enumerate()
+            .map(|(index, &node_id)| {
+                let values = self
+                    .depth
+                    .as_ref()
+                    .map(|depth| depth.row(index))
+                    .filter(|values| !values.iter().all(|v| v.is_nan()));
+                (node_id, (self.coords.row(index).to_vec(), values))
+            })
+            .collect()
+    }
+
+    /// Node ids in node order
+    pub fn ids(&self) -> &[u32] {
+        &self.ids
+    }
+
+    /// Row index of a node id in the coordinate and attribute arrays
+    pub fn index_of(&self, node_id: u32) -> Option<usize> {
+        self.index_from_id.get(&node_id).copied()
     }
 
     /// Get the CRS definition string
@@ -35,43 +252,235 @@ impl Nodes {
     // pub fn new(hash_maptree_map, crs }
     // }
 
-    pub fn x(&self) -> Array1<f64> {
-        let mut v = Vec::new();
-        for (_node_id, (coords, _values)) in &self.hash_map {
-            v.push(coords[0]);
-        }
-        Array1::from(v)
+    pub fn x(&self) -> ArrayView1<'_, f64> {
+        self.coords.column(0)
     }
 
-    pub fn y(&self) -> Array1<f64> {
-        let mut v = Vec::new();
-        for (_node_id, (coords, _values)) in &self.hash_map {
-            v.push(coords[1]);
-        }
-        Array1::from(v)
+    pub fn y(&self) -> ArrayView1<'_, f64> {
+        self.coords.column(1)
     }
 
-    pub fn xy(&self) -> Array2<f64> {
-        let mut v = Vec::new();
-        for (_node_id, (coords, _values)) in &self.hash_map {
-            v.push(coords[0]);
-            v.push(coords[1]);
-        }
-        Array2::from_shape_vec((self.len(), 2), v).unwrap()
+    pub fn xy(&self) -> ArrayView2<'_, f64> {
+        self.coords.view()
     }
 
     pub fn len(&self) -> usize {
-        self.hash_map.len()
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
     }
 
     // pub fn set_crs(&mut self, crs: Option<Proj>) {
     //     let crs = Rc::new(crs.unwrap());
     //     self.crs = Some(crs);
     // }
+    /// Names of the attributes defined on the nodes, depth first if present
+    pub fn attribute_names(&self) -> Vec<&str> {
+        self.depth
+            .as_ref()
+            .map(|_| DEPTH_ATTRIBUTE)
+            .into_iter()
+            .chain(self.attributes.keys().map(String::as_str))
+            .collect()
+    }
+
+    /// Get a named attribute, `depth` being the gr3 value column(s).
+    pub fn attribute(&self, name: &str) -> Option<&NodeAttribute> {
+        if name == DEPTH_ATTRIBUTE {
+            return self.depth.as_ref();
+        }
+        self.attributes.get(name)
+    }
+
+    /// Add (or replace) a named attribute, returning the previous one.
+    ///
+    /// Adding `depth` replaces the gr3 value column(s).
+    pub fn add_attribute(
+        &mut self,
+        name: &str,
+        attribute: NodeAttribute,
+    ) -> Result<Option<NodeAttribute>, NodeAttributeError> {
+        if attribute.len() != self.len() {
+            return Err(NodeAttributeError::LengthMismatch(
+                name.to_string(),
+                attribute.len(),
+                self.len(),
+            ));
+        }
+        if name == DEPTH_ATTRIBUTE {
+            return Ok(self.depth.replace(attribute));
+        }
+        Ok(self.attributes.insert(name.to_string(), attribute))
+    }
+
+    /// Remove a named attribute, returning it if it was defined.
+    ///
+    /// Removing `depth` clears the gr3 value column(s).
+    pub fn remove_attribute(&mut self, name: &str) -> Option<NodeAttribute> {
+        if name == DEPTH_ATTRIBUTE {
+            return self.depth.take();
+        }
+        self.attributes.remove(name)
+    }
+
+    /// Copy of the nodes with new coordinates in a (possibly) different CRS
+    pub(crate) fn with_coords(&self, coords: Array2<f64>, crs: Option<String>) -> Nodes {
+        assert_eq!(coords.dim(), self.coords.dim());
+        Nodes {
+            ids: self.ids.clone(),
+            index_from_id: self.index_from_id.clone(),
+            coords,
+            depth: self.depth.clone(),
+            crs,
+            attributes: self.attributes.clone(),
+        }
+    }
+
+    /// Copy of the nodes at the given positions, in that order
+    pub(crate) fn select(&self, positions: &[usize]) -> Nodes {
+        let ids: Vec<u32> = positions.iter().map(|&index| self.ids[index]).collect();
+        Nodes {
+            index_from_id: ids
+                .iter()
+                .enumerate()
+                .map(|(index, &node_id)| (node_id, index))
+                .collect(),
+            ids,
+            coords: self.coords.select(Axis(0), positions),
+            depth: self.depth.as_ref().map(|depth| depth.select(positions)),
+            crs: self.crs.clone(),
+            attributes: self
+                .attributes
+                .iter()
+                .map(|(name, attribute)| (name.clone(), attribute.select(positions)))
+                .collect(),
+        }
+    }
+
     pub fn get_node(&self, idx: u32) -> Option<(f64, f64)> {
-        self.hash_map.get(&idx).map(|(coords, _)| {
+        self.index_of(idx).map(|index| {
             // Assuming coords[0] is longitude and coords[1] is latitude
-            (coords[0], coords[1])
+            (self.coords[[index, 0]], self.coords[[index, 1]])
         })
     }
 }
+
+/// Serialized layout of [`Nodes`]; the id index is rebuilt on deserialization.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct NodesRepr<'a> {
+    ids: Cow<'a, [u32]>,
+    coords: Cow<'a, Array2<f64>>,
+    depth: Option<Cow<'a, NodeAttribute>>,
+    crs: Option<Cow<'a, str>>,
+    attributes: Cow<'a, LinkedHashMap<String, NodeAttribute>>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Nodes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        NodesRepr {
+            ids: Cow::Borrowed(&self.ids),
+            coords: Cow::Borrowed(&self.coords),
+            depth: self.depth.as_ref().map(Cow::Borrowed),
+            crs: self.crs.as_deref().map(Cow::Borrowed),
+            attributes: Cow::Borrowed(&self.attributes),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Nodes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = NodesRepr::deserialize(deserializer)?;
+        NodesBuilder::default()
+            .ids(repr.ids.into_owned())
+            .coords(repr.coords.into_owned())
+            .depth(repr.depth.map(Cow::into_owned))
+            .crs(repr.crs.map(Cow::into_owned))
+            .attributes(repr.attributes.into_owned())
+            .build()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_nodes() -> Nodes {
+        let mut hash_map = LinkedHashMap::new();
+        hash_map.insert(1, (vec![0.0, 0.0], Some(vec![10.0])));
+        hash_map.insert(2, (vec![1.0, 0.0], Some(vec![20.0])));
+        hash_map.insert(3, (vec![0.5, 1.0], None));
+        NodesBuilder::default().hash_map(hash_map).build().unwrap()
+    }
+
+    #[test]
+    fn test_coordinate_views_and_lookup() {
+        let nodes = sample_nodes();
+        assert_eq!(nodes.ids(), &[1, 2, 3]);
+        assert_eq!(nodes.x(), array![0.0, 1.0, 0.5]);
+        assert_eq!(nodes.y(), array![0.0, 0.0, 1.0]);
+        assert_eq!(nodes.xy().dim(), (3, 2));
+        assert_eq!(nodes.index_of(3), Some(2));
+        assert_eq!(nodes.get_node(2), Some((1.0, 0.0)));
+        assert_eq!(nodes.hash_map().get(&3), Some(&(vec![0.5, 1.0], None)));
+    }
+
+    #[test]
+    fn test_repeated_node_id_is_rejected() {
+        let result = NodesBuilder::default()
+            .ids(vec![1, 2, 1])
+            .coords(Array2::zeros((3, 2)))
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_depth_is_the_default_attribute() {
+        let nodes = sample_nodes();
+        assert_eq!(nodes.attribute_names(), vec![DEPTH_ATTRIBUTE]);
+        let depth = nodes.attribute(DEPTH_ATTRIBUTE).unwrap().clone();
+        let depth = depth.column(0).unwrap();
+        assert_eq!(depth[1], 20.0);
+        assert!(depth[2].is_nan());
+    }
+
+    #[test]
+    fn test_add_get_remove_attribute() {
+        let mut nodes = sample_nodes();
+        let manning = NodeAttribute::Multi(array![[0.02, 1.0], [0.03, 2.0], [0.04, 3.0]]);
+        assert!(nodes
+            .add_attribute("manning", manning.clone())
+            .unwrap()
+            .is_none());
+        assert_eq!(nodes.attribute_names(), vec![DEPTH_ATTRIBUTE, "manning"]);
+        assert_eq!(nodes.attribute("manning"), Some(&manning));
+        assert_eq!(nodes.remove_attribute("manning"), Some(manning));
+        assert!(nodes.attribute("manning").is_none());
+
+        let short = NodeAttribute::Scalar(array![1.0, 2.0]);
+        assert!(nodes.add_attribute("short", short).is_err());
+    }
+
+    #[test]
+    fn test_replace_depth_attribute() {
+        let mut nodes = sample_nodes();
+        let previous = nodes
+            .add_attribute(
+                DEPTH_ATTRIBUTE,
+                NodeAttribute::Scalar(array![1.0, 2.0, 3.0]),
+            )
+            .unwrap();
+        assert!(previous.is_some());
+        assert_eq!(nodes.hash_map().get(&3).unwrap().1, Some(vec![3.0]));
+
+        nodes.remove_attribute(DEPTH_ATTRIBUTE);
+        assert!(nodes.attribute(DEPTH_ATTRIBUTE).is_none());
+        assert!(nodes.attribute_names().is_empty());
+    }
+}
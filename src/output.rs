@@ -0,0 +1,38 @@
+//! Helpers shared by the file writers
+
+use crate::nodes::Nodes;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Write `path` through a temporary file, persisted once `write` succeeds.
+///
+/// I/O errors are reported through `io_error` with the target path.
+pub(crate) fn persist_with<E, F>(path: &Path, io_error: fn(String) -> E, write: F) -> Result<(), E>
+where
+    F: FnOnce(&mut BufWriter<&mut std::fs::File>) -> std::io::Result<()>,
+{
+    let io_error =
+        |e: std::io::Error| io_error(format!("Failed to write {}: {}", path.display(), e));
+    let mut tmpfile = NamedTempFile::new().map_err(io_error)?;
+    log::debug!("Will write to tmpfile: {:?}", tmpfile);
+    let mut writer = BufWriter::new(tmpfile.as_file_mut());
+    write(&mut writer).map_err(io_error)?;
+    writer.flush().map_err(io_error)?;
+    drop(writer);
+    tmpfile.persist(path).map_err(|e| io_error(e.error))?;
+    Ok(())
+}
+
+/// One-based position of `node_id`, for the index-based output formats
+pub(crate) fn node_number(nodes: &Nodes, node_id: u32) -> std::io::Result<usize> {
+    nodes
+        .index_of(node_id)
+        .map(|index| index + 1)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unknown node {}", node_id),
+            )
+        })
+}
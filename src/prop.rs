@@ -0,0 +1,271 @@
+//! SCHISM element-centred property files (`tvd.prop`, `fluxflag.prop`, ...)
+//!
+//! A `.prop` file has one `element_id value` line per element. Values are read
+//! as floats and written back with integers printed without a decimal point, so
+//! flag files such as `fluxflag.prop` round-trip unchanged.
+
+use crate::boundary_polygon::BoundaryPolygon;
+use crate::elements::{ElementAttributeError, Elements};
+use crate::Hgrid;
+use linked_hash_map::LinkedHashMap;
+use ndarray::{Array1, ArrayView1};
+use std::fmt;
+use std::fs::File;
+use std::io::{prelude::*, BufReader};
+use std::path::Path;
+use tempfile::NamedTempFile;
+use thiserror::Error;
+
+/// Contents of a `.prop` file: one value per element, keyed by element id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Prop {
+    pub(crate) values: LinkedHashMap<u32, f64>,
+}
+
+impl Prop {
+    pub fn values(&self) -> &LinkedHashMap<u32, f64> {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Build a prop from per-element values given in element order.
+    pub fn from_element_values(
+        elements: &Elements,
+        values: ArrayView1<'_, f64>,
+    ) -> Result<Prop, PropError> {
+        if values.len() != elements.len() {
+            return Err(PropError::ElementCountMismatch(
+                values.len(),
+                elements.len(),
+            ));
+        }
+        Ok(Prop {
+            values: elements
+                .ids()
+                .iter()
+                .copied()
+                .zip(values.iter().copied())
+                .collect(),
+        })
+    }
+
+    /// Build a prop that is `inside` for elements whose centroid falls in `mask`
+    /// and `outside` elsewhere.
+    pub fn from_polygon_mask(
+        hgrid: &Hgrid,
+        mask: &BoundaryPolygon,
+        inside: f64,
+        outside: f64,
+    ) -> Prop {
+        let elements = hgrid.elements();
+        let is_inside = mask.contains_points(&elements.centroids());
+        Prop {
+            values: elements
+                .ids()
+                .iter()
+                .zip(is_inside)
+                .map(|(&elem_id, is_inside)| (elem_id, if is_inside { inside } else { outside }))
+                .collect(),
+        }
+    }
+
+    /// Check that the prop covers exactly the elements of the mesh.
+    pub fn validate_against(&self, elements: &Elements) -> Result<(), PropError> {
+        if self.values.len() != elements.len() {
+            return Err(PropError::ElementCountMismatch(
+                self.values.len(),
+                elements.len(),
+            ));
+        }
+        match self
+            .values
+            .keys()
+            .find(|elem_id| elements.index_of(**elem_id).is_none())
+        {
+            Some(elem_id) => Err(PropError::UnknownElement(*elem_id)),
+            None => Ok(()),
+        }
+    }
+
+    /// Values in element order, ready to be stored as an element attribute.
+    pub fn to_element_values(&self, elements: &Elements) -> Result<Array1<f64>, PropError> {
+        self.validate_against(elements)?;
+        let mut values = Array1::zeros(elements.len());
+        for (elem_id, value) in self.values.iter() {
+            // Presence checked by validate_against
+            values[elements.index_of(*elem_id).unwrap()] = *value;
+        }
+        Ok(values)
+    }
+}
+
+impl fmt::Display for Prop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self
+            .values
+            .iter()
+            .map(|(elem_id, value)| format!("{} {}", elem_id, value))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl Hgrid {
+    /// Read a `.prop` file and store it as the element attribute `name`.
+    pub fn load_prop(&mut self, path: &Path, name: &str) -> Result<(), PropError> {
+        let prop = parse_from_path_ref(path)?;
+        let values = prop.to_element_values(self.elements())?;
+        self.add_element_attribute(name, values)?;
+        Ok(())
+    }
+
+    /// Write the element attribute `name` as a `.prop` file.
+    pub fn write_prop(&self, path: &Path, name: &str) -> Result<(), PropError> {
+        let values = self
+            .element_attribute(name)
+            .ok_or_else(|| PropError::UnknownAttribute(name.to_string()))?;
+        let prop = Prop::from_element_values(self.elements(), values)?;
+        write_to_path(path, &prop)
+            .map_err(|e| PropError::IoError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PropError {
+    #[error("I/O error: {0}")]
+    IoError(String),
+
+    #[error("Empty file error: {0}")]
+    EmptyFile(String),
+
+    #[error("Line read error: file {0}, error: {1}")]
+    LineReadError(String, String),
+
+    #[error("prop defines {0} elements but the mesh has {1}")]
+    ElementCountMismatch(usize, usize),
+
+    #[error("prop references element {0} which is not in the mesh")]
+    UnknownElement(u32),
+
+    #[error("Element attribute {0} not found")]
+    UnknownAttribute(String),
+
+    #[error(transparent)]
+    ElementAttributeError(#[from] ElementAttributeError),
+}
+
+pub fn parse_from_path_ref(path: &Path) -> Result<Prop, PropError> {
+    let fname = &path.display().to_string();
+    let file = match File::open(fname) {
+        Ok(file) => file,
+        Err(e) => {
+            return Err(PropError::IoError(format!(
+                "Failed to open {}: {}",
+                fname, e
+            )));
+        }
+    };
+    let reader = BufReader::new(file);
+    parse_from_reader(reader, fname)
+}
+
+pub fn parse_from_reader<R: Read>(
+    reader: BufReader<R>,
+    fname: &str, // Passed separately for error messages
+) -> Result<Prop, PropError> {
+    let mut values = LinkedHashMap::new();
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| PropError::LineReadError(fname.to_string(), e.to_string()))?;
+        let mut split_line = line.split_whitespace();
+        let elem_id = match split_line.next() {
+            Some(item) => match item.parse::<u32>() {
+                Ok(elem_id) => elem_id,
+                Err(_) => return Err(PropError::LineReadError(
+                    fname.to_string(),
+                    format!("Error reading prop file: {}. Expected line {} to start with an element id but found {}.", fname, line_index + 1, item)
+                )),
+            },
+            None => continue,
+        };
+        let value = match split_line.next() {
+            Some(item) => match item.parse::<f64>() {
+                Ok(value) => value,
+                Err(_) => return Err(PropError::LineReadError(
+                    fname.to_string(),
+                    format!("Error reading prop file: {}. Expected the value on line {} to be castable to an f64 but found {}.", fname, line_index + 1, item)
+                )),
+            },
+            None => return Err(PropError::LineReadError(
+                fname.to_string(),
+                format!("Error reading prop file: {}. Line {} has an element id but no value.", fname, line_index + 1)
+            )),
+        };
+        values.insert(elem_id, value);
+    }
+    if values.is_empty() {
+        return Err(PropError::EmptyFile(fname.to_string()));
+    }
+    Ok(Prop { values })
+}
+
+pub fn write_to_path(path: &Path, prop: &Prop) -> std::io::Result<()> {
+    let mut tmpfile = NamedTempFile::new()?;
+    log::debug!("Will write to tmpfile: {:?}", tmpfile);
+    writeln!(tmpfile, "{}", prop)?;
+    tmpfile.persist(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{hgrid_from_gr3, UNIT_SQUARE};
+    use tempfile::tempdir;
+
+    // Two triangles in the unit square, split along the diagonal 1-3
+    #[test]
+    fn test_load_and_write_prop() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("fluxflag.prop");
+        std::fs::write(&path, "1 -1\n2 0\n").unwrap();
+
+        let mut hgrid = hgrid_from_gr3(UNIT_SQUARE);
+        hgrid.load_prop(&path, "fluxflag").unwrap();
+        assert_eq!(
+            hgrid.element_attribute("fluxflag").unwrap(),
+            ndarray::array![-1.0, 0.0]
+        );
+
+        let out_path = temp_dir.path().join("out.prop");
+        hgrid.write_prop(&out_path, "fluxflag").unwrap();
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "1 -1\n2 0\n");
+    }
+
+    #[test]
+    fn test_element_count_mismatch() {
+        let prop = parse_from_reader(BufReader::new("1 1\n".as_bytes()), "test").unwrap();
+        assert!(matches!(
+            prop.to_element_values(hgrid_from_gr3(UNIT_SQUARE).elements()),
+            Err(PropError::ElementCountMismatch(1, 2))
+        ));
+    }
+
+    #[test]
+    fn test_prop_from_polygon_mask() {
+        let hgrid = hgrid_from_gr3(UNIT_SQUARE);
+        // Only the centroid of element 1, (2/3, 1/3), lies right of x = 0.5
+        let mask =
+            BoundaryPolygon::from_rings(&[vec![(0.5, -1.0), (2.0, -1.0), (2.0, 2.0), (0.5, 2.0)]]);
+        let prop = Prop::from_polygon_mask(&hgrid, &mask, 1.0, 0.0);
+        assert_eq!(prop.values().get(&1), Some(&1.0));
+        assert_eq!(prop.values().get(&2), Some(&0.0));
+        assert_eq!(prop.to_string(), "1 1\n2 0");
+    }
+}
@@ -0,0 +1,688 @@
+//! Telemac SELAFIN (`.slf`) reader and writer for 2D meshes
+//!
+//! SELAFIN files are sequences of big-endian Fortran unformatted records. The
+//! mesh is written with a single time step holding the `BOTTOM` variable, the
+//! bed elevation (positive up), so depths are negated from the gr3 convention
+//! on the way out and back on the way in. On read `FOND` is accepted as well.
+//!
+//! Boundary nodes are numbered by the IPOBO array, outer contours
+//! counterclockwise and islands clockwise, as in [`Hgrid::domain_polygons`].
+//! Boundary types live in the companion `.cli` file, written beside the mesh:
+//! open boundary nodes get a prescribed elevation (`5 4 4`) and all others are
+//! solid walls (`2 2 2`). On read the `.cli` file is optional; without it outer
+//! contours become land boundaries. An edge between two open nodes is open,
+//! any other boundary edge is land or island.
+//!
+//! SELAFIN has no node ids, CRS or IBTYPE flags: nodes and elements are
+//! numbered from 1 in file order, and the description goes in the title.
+
+use crate::compression::open_reader;
+use crate::gr3::{Gr3ParserOutput, Gr3ParserOutputBuilder, Gr3ParserOutputBuilderError};
+use crate::nodes::DEPTH_ATTRIBUTE;
+use crate::output::{node_number, persist_with};
+use crate::Hgrid;
+use linked_hash_map::LinkedHashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::prelude::*;
+use std::path::Path;
+use thiserror::Error;
+
+const TITLE_LENGTH: usize = 72;
+const NAME_LENGTH: usize = 32;
+const BOTTOM_NAMES: [&str; 2] = ["BOTTOM", "FOND"];
+
+/// `.cli` LIHBOR codes
+const WALL: i32 = 2;
+const FREE_DEPTH: i32 = 4;
+const PRESCRIBED_DEPTH: i32 = 5;
+
+/// Size of the reals in a SELAFIN file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelafinPrecision {
+    /// 4-byte reals (`SERAFIN`)
+    Single,
+    /// 8-byte reals (`SERAFIND`)
+    #[default]
+    Double,
+}
+
+impl SelafinPrecision {
+    fn tag(&self) -> &'static str {
+        match self {
+            SelafinPrecision::Single => "SERAFIN ",
+            SelafinPrecision::Double => "SERAFIND",
+        }
+    }
+
+    fn encode(&self, values: impl Iterator<Item = f64>) -> Vec<u8> {
+        match self {
+            SelafinPrecision::Single => values.flat_map(|v| (v as f32).to_be_bytes()).collect(),
+            SelafinPrecision::Double => values.flat_map(|v| v.to_be_bytes()).collect(),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SelafinError {
+    #[error("I/O error: {0}")]
+    IoError(String),
+
+    #[error("Empty file error: {0}")]
+    EmptyFile(String),
+
+    #[error("Invalid SELAFIN file {0}: {1}")]
+    FormatError(String, String),
+
+    #[error("Unsupported SELAFIN mesh {0}: {1}")]
+    Unsupported(String, String),
+
+    #[error(transparent)]
+    Gr3ParserOutputBuilderError(#[from] Gr3ParserOutputBuilderError),
+}
+
+impl Hgrid {
+    /// Write the mesh as a SELAFIN file plus its `.cli` boundary conditions.
+    pub fn write_selafin(
+        &self,
+        path: &Path,
+        precision: SelafinPrecision,
+    ) -> Result<(), SelafinError> {
+        write_to_path(path, self, precision)
+    }
+}
+
+/// Fortran unformatted sequential records: a big-endian byte count before
+/// and after each payload.
+struct RecordReader<R: Read> {
+    reader: R,
+    fname: String,
+    record_number: usize,
+}
+
+impl<R: Read> RecordReader<R> {
+    /// The next record, or `None` at the end of the file
+    fn next_record(&mut self) -> Result<Option<Vec<u8>>, SelafinError> {
+        let mut marker = [0u8; 4];
+        match self.reader.read_exact(&mut marker) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(SelafinError::IoError(format!("{}: {}", self.fname, e))),
+        }
+        self.record_number += 1;
+        let length = u32::from_be_bytes(marker) as usize;
+        let mut payload = vec![0u8; length];
+        self.reader
+            .read_exact(&mut payload)
+            .and_then(|_| self.reader.read_exact(&mut marker))
+            .map_err(|_| self.error(format!("Record {} is truncated.", self.record_number)))?;
+        if u32::from_be_bytes(marker) as usize != length {
+            return Err(self.error(format!(
+                "Record {} has mismatched length markers.",
+                self.record_number
+            )));
+        }
+        Ok(Some(payload))
+    }
+
+    fn expect_record(&mut self, what: &str) -> Result<Vec<u8>, SelafinError> {
+        match self.next_record()? {
+            Some(payload) => Ok(payload),
+            None if self.record_number == 0 => Err(SelafinError::EmptyFile(self.fname.clone())),
+            None => Err(self.error(format!(
+                "Expected {} but reached the end of the file.",
+                what
+            ))),
+        }
+    }
+
+    /// A record of exactly `len` big-endian integers
+    fn expect_ints(&mut self, what: &str, len: usize) -> Result<Vec<i32>, SelafinError> {
+        let payload = self.expect_record(what)?;
+        if payload.len() != 4 * len {
+            return Err(self.error(format!(
+                "Expected {} to hold {} integers but found {} bytes.",
+                what,
+                len,
+                payload.len()
+            )));
+        }
+        Ok(payload
+            .chunks_exact(4)
+            .map(|chunk| i32::from_be_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+
+    /// A record of `len` big-endian reals, 4 or 8 bytes wide
+    fn reals(&self, what: &str, payload: &[u8], len: usize) -> Result<Vec<f64>, SelafinError> {
+        if payload.len() == 4 * len {
+            Ok(payload
+                .chunks_exact(4)
+                .map(|chunk| f32::from_be_bytes(chunk.try_into().unwrap()) as f64)
+                .collect())
+        } else if payload.len() == 8 * len {
+            Ok(payload
+                .chunks_exact(8)
+                .map(|chunk| f64::from_be_bytes(chunk.try_into().unwrap()))
+                .collect())
+        } else {
+            Err(self.error(format!(
+                "Expected {} to hold {} reals but found {} bytes.",
+                what,
+                len,
+                payload.len()
+            )))
+        }
+    }
+
+    fn error(&self, message: String) -> SelafinError {
+        SelafinError::FormatError(self.fname.clone(), message)
+    }
+}
+
+/// Parse a SELAFIN mesh and, if present, the `.cli` file beside it.
+pub fn parse_from_path_ref(path: &Path) -> Result<Gr3ParserOutput, SelafinError> {
+    let fname = path.display().to_string();
+    // Decompresses .gz/.zst/.bz2 input on the fly
+    let reader = open_reader(path)
+        .map_err(|e| SelafinError::IoError(format!("Failed to open {}: {}", fname, e)))?;
+    let cli_path = path.with_extension("cli");
+    let open_nodes = if cli_path.exists() {
+        Some(read_cli(&cli_path)?)
+    } else {
+        None
+    };
+    parse_from_reader(reader, &fname, open_nodes.as_ref())
+}
+
+/// Parse a SELAFIN mesh. `open_nodes` holds the node ids with an open
+/// boundary condition; without it every outer contour is land.
+pub fn parse_from_reader<R: Read>(
+    reader: R,
+    fname: &str, // Passed separately for error messages
+    open_nodes: Option<&HashSet<u32>>,
+) -> Result<Gr3ParserOutput, SelafinError> {
+    let mut records = RecordReader {
+        reader,
+        fname: fname.to_string(),
+        record_number: 0,
+    };
+    let title = records.expect_record("the title")?;
+    let description = String::from_utf8_lossy(&title[..title.len().min(TITLE_LENGTH)])
+        .trim()
+        .to_string();
+    let counts = records.expect_ints("the variable counts", 2)?;
+    let mut bottom_index = None;
+    for index in 0..counts[0].max(0) as usize {
+        let name = records.expect_record("a variable name")?;
+        let name = String::from_utf8_lossy(&name[..name.len().min(NAME_LENGTH / 2)]);
+        if bottom_index.is_none() && BOTTOM_NAMES.contains(&name.trim().to_uppercase().as_str()) {
+            bottom_index = Some(index);
+        }
+    }
+    for _ in 0..counts[1].max(0) {
+        records.expect_record("a quadratic variable name")?;
+    }
+    let iparam = records.expect_ints("IPARAM", 10)?;
+    if iparam[6] > 1 {
+        return Err(SelafinError::Unsupported(
+            fname.to_string(),
+            format!("3D mesh with {} planes", iparam[6]),
+        ));
+    }
+    if iparam[9] == 1 {
+        records.expect_ints("the date", 6)?;
+    }
+    let sizes = records.expect_ints("the mesh sizes", 4)?;
+    let (nelem, npoin, ndp) = (sizes[0] as usize, sizes[1] as usize, sizes[2] as usize);
+    if npoin == 0 {
+        return Err(SelafinError::EmptyFile(fname.to_string()));
+    }
+    if ndp != 3 && ndp != 4 {
+        return Err(SelafinError::Unsupported(
+            fname.to_string(),
+            format!("{} nodes per element", ndp),
+        ));
+    }
+    let ikle = records.expect_ints("IKLE", nelem * ndp)?;
+    let ipobo = records.expect_ints("IPOBO", npoin)?;
+    let payload = records.expect_record("the x coordinates")?;
+    let x = records.reals("the x coordinates", &payload, npoin)?;
+    let payload = records.expect_record("the y coordinates")?;
+    let y = records.reals("the y coordinates", &payload, npoin)?;
+
+    // Only the first time step is read; BOTTOM is an elevation
+    let mut depths = None;
+    if let (Some(bottom_index), Some(_time)) = (bottom_index, records.next_record()?) {
+        for index in 0..=bottom_index {
+            let payload = records.expect_record("a variable record")?;
+            if index == bottom_index {
+                depths = Some(records.reals("BOTTOM", &payload, npoin)?);
+            }
+        }
+    }
+
+    // IPARAM 3 and 4 are the coordinate origin
+    let (x_origin, y_origin) = (iparam[2] as f64, iparam[3] as f64);
+    let nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> = (0..npoin)
+        .map(|index| {
+            let depth = depths.as_ref().map(|bottom| vec![-bottom[index]]);
+            (
+                index as u32 + 1,
+                (vec![x[index] + x_origin, y[index] + y_origin], depth),
+            )
+        })
+        .collect();
+    let mut elements: LinkedHashMap<u32, Vec<u32>> = LinkedHashMap::new();
+    for (index, element) in ikle.chunks_exact(ndp).enumerate() {
+        if let Some(node_id) = element.iter().find(|&&id| id < 1 || id as usize > npoin) {
+            return Err(records.error(format!(
+                "Element {} references unknown node {}.",
+                index + 1,
+                node_id
+            )));
+        }
+        elements.insert(
+            index as u32 + 1,
+            element.iter().map(|&id| id as u32).collect(),
+        );
+    }
+
+    let (open_boundaries, land_boundaries, interior_boundaries) =
+        split_boundaries(&ipobo, &elements, &nodes, open_nodes);
+    let mut builder = Gr3ParserOutputBuilder::default();
+    builder
+        .description((!description.is_empty()).then_some(description))
+        .nodes(nodes)
+        .elements(Some(elements))
+        .open_boundaries(open_boundaries)
+        .land_boundaries(land_boundaries)
+        .interior_boundaries(interior_boundaries);
+    Ok(builder.build()?)
+}
+
+/// Node ids with an open (prescribed or free depth) condition in a `.cli` file
+fn read_cli(path: &Path) -> Result<HashSet<u32>, SelafinError> {
+    let fname = path.display().to_string();
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| SelafinError::IoError(format!("Failed to open {}: {}", fname, e)))?;
+    let mut open_nodes = HashSet::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let items: Vec<&str> = line.split_whitespace().collect();
+        if items.is_empty() {
+            continue;
+        }
+        let parsed = items
+            .first()
+            .zip(items.get(11))
+            .and_then(|(lihbor, node_id)| {
+                Some((lihbor.parse::<i32>().ok()?, node_id.parse::<u32>().ok()?))
+            });
+        let Some((lihbor, node_id)) = parsed else {
+            return Err(SelafinError::FormatError(
+                fname,
+                format!(
+                    "Line {}: expected at least 12 numeric columns but found {}.",
+                    line_number + 1,
+                    line
+                ),
+            ));
+        };
+        if lihbor == FREE_DEPTH || lihbor == PRESCRIBED_DEPTH {
+            open_nodes.insert(node_id);
+        }
+    }
+    Ok(open_nodes)
+}
+
+type SplitBoundaries = (Vec<Vec<u32>>, Vec<Vec<u32>>, Vec<Vec<u32>>);
+
+/// Rebuild boundary segments from the IPOBO numbering.
+///
+/// Boundary nodes in IPOBO order form contours, a new one starting wherever
+/// consecutive nodes are not joined by a boundary edge. Counterclockwise
+/// contours are outer boundaries and split into open and land runs;
+/// clockwise contours without open nodes are islands.
+fn split_boundaries(
+    ipobo: &[i32],
+    elements: &LinkedHashMap<u32, Vec<u32>>,
+    nodes: &LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)>,
+    open_nodes: Option<&HashSet<u32>>,
+) -> SplitBoundaries {
+    let mut edge_count: HashMap<(u32, u32), usize> = HashMap::new();
+    for element in elements.values() {
+        for i in 0..element.len() {
+            let (a, b) = (element[i], element[(i + 1) % element.len()]);
+            *edge_count.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    }
+    let is_boundary_edge = |a: u32, b: u32| edge_count.get(&(a.min(b), a.max(b))) == Some(&1);
+
+    let mut boundary_nodes: Vec<(i32, u32)> = ipobo
+        .iter()
+        .enumerate()
+        .filter(|(_index, &rank)| rank > 0)
+        .map(|(index, &rank)| (rank, index as u32 + 1))
+        .collect();
+    boundary_nodes.sort_unstable();
+    let mut contours: Vec<Vec<u32>> = Vec::new();
+    for (_rank, node_id) in boundary_nodes {
+        match contours.last_mut() {
+            Some(contour) if is_boundary_edge(*contour.last().unwrap(), node_id) => {
+                contour.push(node_id)
+            }
+            _ => contours.push(vec![node_id]),
+        }
+    }
+
+    let is_open = |node_id: &u32| open_nodes.is_some_and(|open| open.contains(node_id));
+    let mut open_boundaries = Vec::new();
+    let mut land_boundaries = Vec::new();
+    let mut interior_boundaries = Vec::new();
+    for contour in contours.into_iter().filter(|contour| contour.len() > 2) {
+        let signed_area: f64 = (0..contour.len())
+            .map(|i| {
+                let p = &nodes[&contour[i]].0;
+                let q = &nodes[&contour[(i + 1) % contour.len()]].0;
+                p[0] * q[1] - q[0] * p[1]
+            })
+            .sum();
+        let n = contour.len();
+        let edge_is_open = |i: usize| is_open(&contour[i]) && is_open(&contour[(i + 1) % n]);
+        if signed_area < 0.0 && !contour.iter().any(is_open) {
+            interior_boundaries.push(contour);
+            continue;
+        }
+        // Start at a change of edge type, if there is one
+        let Some(start) = (0..n).find(|&i| edge_is_open(i) != edge_is_open((i + n - 1) % n)) else {
+            let mut closed = contour.clone();
+            closed.push(contour[0]);
+            if edge_is_open(0) {
+                open_boundaries.push(closed);
+            } else {
+                land_boundaries.push(closed);
+            }
+            continue;
+        };
+        let mut segment = vec![contour[start]];
+        for k in 0..n {
+            let i = (start + k) % n;
+            segment.push(contour[(i + 1) % n]);
+            let last_edge = k + 1 == n;
+            if last_edge || edge_is_open(i) != edge_is_open((i + 1) % n) {
+                let done = std::mem::replace(&mut segment, vec![contour[(i + 1) % n]]);
+                if edge_is_open(i) {
+                    open_boundaries.push(done);
+                } else {
+                    land_boundaries.push(done);
+                }
+            }
+        }
+    }
+    (open_boundaries, land_boundaries, interior_boundaries)
+}
+
+fn write_record<W: Write>(writer: &mut W, payload: &[u8]) -> std::io::Result<()> {
+    let length = (payload.len() as u32).to_be_bytes();
+    writer.write_all(&length)?;
+    writer.write_all(payload)?;
+    writer.write_all(&length)
+}
+
+fn encode_ints(values: impl IntoIterator<Item = i32>) -> Vec<u8> {
+    values.into_iter().flat_map(i32::to_be_bytes).collect()
+}
+
+/// Boundary node ids in IPOBO order
+fn boundary_order(hgrid: &Hgrid) -> Vec<u32> {
+    hgrid
+        .domain_polygons()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect()
+}
+
+fn write_slf<W: Write>(
+    writer: &mut W,
+    hgrid: &Hgrid,
+    precision: SelafinPrecision,
+    boundary_nodes: &[u32],
+) -> std::io::Result<()> {
+    let nodes = hgrid.nodes();
+    let elements = hgrid.elements();
+    let ndp = elements
+        .iter()
+        .next()
+        .map_or(3, |(_id, element)| element.len());
+    let has_depth = nodes.attribute(DEPTH_ATTRIBUTE).is_some();
+
+    // The title is a byte count, so cut before a multi-byte character that
+    // would not fit and pad with spaces
+    let description = hgrid.description().map_or("", String::as_str);
+    let mut end = description.len().min(TITLE_LENGTH);
+    while !description.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut title = description.as_bytes()[..end].to_vec();
+    title.resize(TITLE_LENGTH, b' ');
+    title.extend_from_slice(precision.tag().as_bytes());
+    write_record(writer, &title)?;
+    write_record(writer, &encode_ints([i32::from(has_depth), 0]))?;
+    if has_depth {
+        write_record(writer, format!("{:<16}{:<16}", "BOTTOM", "M").as_bytes())?;
+    }
+    write_record(writer, &encode_ints([1, 0, 0, 0, 0, 0, 0, 0, 0, 0]))?;
+    write_record(
+        writer,
+        &encode_ints([elements.len() as i32, nodes.len() as i32, ndp as i32, 1]),
+    )?;
+    let ikle = elements
+        .iter()
+        .flat_map(|(_elem_id, element)| element.iter())
+        .map(|node_id| node_number(nodes, *node_id).map(|number| number as i32))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    write_record(writer, &encode_ints(ikle))?;
+    let mut ipobo = vec![0; nodes.len()];
+    for (rank, node_id) in boundary_nodes.iter().enumerate() {
+        ipobo[node_number(nodes, *node_id)? - 1] = rank as i32 + 1;
+    }
+    write_record(writer, &encode_ints(ipobo))?;
+    write_record(writer, &precision.encode(hgrid.x().iter().copied()))?;
+    write_record(writer, &precision.encode(hgrid.y().iter().copied()))?;
+    if has_depth {
+        write_record(writer, &precision.encode(std::iter::once(0.0)))?;
+        write_record(
+            writer,
+            &precision.encode(hgrid.depths_positive_up().iter().copied()),
+        )?;
+    }
+    Ok(())
+}
+
+fn write_cli<W: Write>(
+    writer: &mut W,
+    hgrid: &Hgrid,
+    boundary_nodes: &[u32],
+) -> std::io::Result<()> {
+    let nodes = hgrid.nodes();
+    let open_nodes: HashSet<u32> = hgrid
+        .boundaries()
+        .and_then(|boundaries| boundaries.open())
+        .map(|open| open.nodes_ids().iter().flatten().copied().collect())
+        .unwrap_or_default();
+    for (rank, node_id) in boundary_nodes.iter().enumerate() {
+        let (lihbor, liubor) = if open_nodes.contains(node_id) {
+            (PRESCRIBED_DEPTH, FREE_DEPTH)
+        } else {
+            (WALL, WALL)
+        };
+        writeln!(
+            writer,
+            "{} {} {} 0.000 0.000 0.000 0.000 {} 0.000 0.000 0.000 {} {}",
+            lihbor,
+            liubor,
+            liubor,
+            liubor,
+            node_number(nodes, *node_id)?,
+            rank + 1
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_to_path(
+    path: &Path,
+    hgrid: &Hgrid,
+    precision: SelafinPrecision,
+) -> Result<(), SelafinError> {
+    let elements = hgrid.elements();
+    let ndp = elements
+        .iter()
+        .next()
+        .map_or(3, |(_id, element)| element.len());
+    if let Some((elem_id, element)) = elements.iter().find(|(_id, element)| element.len() != ndp) {
+        return Err(SelafinError::Unsupported(
+            path.display().to_string(),
+            format!(
+                "element {} has {} nodes but SELAFIN meshes hold a single element type",
+                elem_id,
+                element.len()
+            ),
+        ));
+    }
+    let boundary_nodes = boundary_order(hgrid);
+    persist_with(path, SelafinError::IoError, |writer| {
+        write_slf(writer, hgrid, precision, &boundary_nodes)
+    })?;
+    persist_with(
+        &path.with_extension("cli"),
+        SelafinError::IoError,
+        |writer| write_cli(writer, hgrid, &boundary_nodes),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::hgrid_from_gr3;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    // 3x3 nodes split into 8 triangles, open along the right side
+    const GR3: &str = "test mesh
+8 9
+1 0 0 1
+2 1 0 2
+3 2 0 3
+4 0 1 4
+5 1 1 5
+6 2 1 6
+7 0 2 7
+8 1 2 8
+9 2 2 9
+1 3 1 2 5
+2 3 1 5 4
+3 3 2 3 6
+4 3 2 6 5
+5 3 4 5 8
+6 3 4 8 7
+7 3 5 6 9
+8 3 5 9 8
+1 = Number of open boundaries
+3 = Total number of open boundary nodes
+3 = Number of nodes for open boundary 1
+3
+6
+9
+1 = number of land boundaries
+7 = Total number of land boundary nodes
+7 0 = Number of nodes for land boundary 1
+9
+8
+7
+4
+1
+2
+3";
+
+    fn gr3_text(hgrid: &Hgrid, path: &Path) -> String {
+        hgrid.write(path).unwrap();
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_selafin_round_trip() {
+        let hgrid = hgrid_from_gr3(GR3);
+        let temp_dir = tempdir().unwrap();
+        let expected = gr3_text(&hgrid, &temp_dir.path().join("expected.gr3"));
+        for precision in [SelafinPrecision::Single, SelafinPrecision::Double] {
+            let path = temp_dir.path().join("mesh.slf");
+            hgrid.write_selafin(&path, precision).unwrap();
+            let reloaded = Hgrid::try_from(&PathBuf::from(&path)).unwrap();
+            assert_eq!(
+                gr3_text(&reloaded, &temp_dir.path().join("reloaded.gr3")),
+                expected,
+                "{:?}",
+                precision
+            );
+        }
+    }
+
+    #[test]
+    fn test_bottom_is_positive_up() {
+        let mut hgrid = hgrid_from_gr3(GR3);
+        hgrid.flip_depths();
+        let mut buffer = Vec::new();
+        let boundary_nodes = boundary_order(&hgrid);
+        write_slf(
+            &mut buffer,
+            &hgrid,
+            SelafinPrecision::Double,
+            &boundary_nodes,
+        )
+        .unwrap();
+        // Without a .cli file the outer contour is a single closed land boundary
+        let parsed = parse_from_reader(buffer.as_slice(), "test", None).unwrap();
+        assert_eq!(parsed.nodes()[&9].1, Some(vec![9.0]));
+        assert!(parsed.open_boundaries().unwrap().is_empty());
+        let land = parsed.land_boundaries().unwrap();
+        assert_eq!(land.len(), 1);
+        assert_eq!(land[0].len(), 9);
+        assert_eq!(land[0].first(), land[0].last());
+    }
+
+    #[test]
+    fn test_non_ascii_title_is_80_bytes() {
+        // 81 bytes, with a two-byte character across byte 72
+        let gr3 = GR3.replacen("test mesh", &format!("a{}", "í".repeat(40)), 1);
+        let hgrid = hgrid_from_gr3(&gr3);
+        let mut buffer = Vec::new();
+        write_slf(&mut buffer, &hgrid, SelafinPrecision::Single, &[]).unwrap();
+        assert_eq!(buffer[..4], 80u32.to_be_bytes());
+        assert_eq!(&buffer[76..84], b"SERAFIN ");
+        let parsed = parse_from_reader(buffer.as_slice(), "test", None).unwrap();
+        assert_eq!(parsed.description(), Some(format!("a{}", "í".repeat(35))));
+    }
+
+    #[test]
+    fn test_unknown_node_is_an_error() {
+        let gr3 = GR3.replace("8 3 5 9 8", "8 3 5 10 8");
+        let hgrid = hgrid_from_gr3(&gr3);
+        let error = write_slf(&mut Vec::new(), &hgrid, SelafinPrecision::Single, &[]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        let error = write_cli(&mut Vec::new(), &hgrid_from_gr3(GR3), &[10]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_truncated_record_is_rejected() {
+        let mut buffer = Vec::new();
+        write_record(&mut buffer, &[b' '; 80]).unwrap();
+        buffer.truncate(40);
+        assert!(matches!(
+            parse_from_reader(buffer.as_slice(), "test", None),
+            Err(SelafinError::FormatError(_, _))
+        ));
+    }
+}
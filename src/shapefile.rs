@@ -0,0 +1,466 @@
+//! ESRI Shapefile export of boundaries and element footprints
+//!
+//! Writes the `.shp`, `.shx` and `.dbf` members directly, plus a `.prj` with
+//! the ESRI WKT of the grid's CRS when one is set. Boundaries become PolyLine
+//! shapes with `type` and `index` attributes (see [`Hgrid::boundary_features`]);
+//! elements become Polygon shapes with `id`, `area` (in squared CRS units) and
+//! `depth_mean` (positive down) attributes.
+
+use crate::features::{Geometry, PropertyValue};
+use crate::output::persist_with;
+use crate::Hgrid;
+use proj::wkt::WktVersion;
+use std::io::prelude::*;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+const FILE_CODE: i32 = 9994;
+const VERSION: i32 = 1000;
+const HEADER_BYTES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShapeType {
+    PolyLine = 3,
+    Polygon = 5,
+}
+
+#[derive(Error, Debug)]
+pub enum ShapefileError {
+    #[error("I/O error: {0}")]
+    IoError(String),
+
+    #[error("PROJ error: {0}")]
+    ProjError(String),
+}
+
+/// dBASE column: name (at most 10 characters), type (`C` or `N`), width, decimals
+struct DbfField {
+    name: &'static str,
+    kind: u8,
+    width: usize,
+    decimals: usize,
+}
+
+enum DbfValue {
+    Text(String),
+    Number(f64),
+    Missing,
+}
+
+impl DbfValue {
+    fn format(&self, field: &DbfField) -> String {
+        let text = match self {
+            DbfValue::Text(text) => format!("{:<width$}", text, width = field.width),
+            DbfValue::Number(value) => format!(
+                "{:>width$.decimals$}",
+                value,
+                width = field.width,
+                decimals = field.decimals
+            ),
+            DbfValue::Missing => " ".repeat(field.width),
+        };
+        // Values wider than the column are cut rather than shifting the record
+        text.chars().take(field.width).collect()
+    }
+}
+
+struct ShapeRecord {
+    parts: Vec<Vec<(f64, f64)>>,
+    values: Vec<DbfValue>,
+}
+
+impl ShapeRecord {
+    fn point_count(&self) -> usize {
+        self.parts.iter().map(Vec::len).sum()
+    }
+
+    /// Size of the record content in bytes (excluding the 8-byte record header)
+    fn content_bytes(&self) -> usize {
+        4 + 32 + 4 + 4 + 4 * self.parts.len() + 16 * self.point_count()
+    }
+
+    fn bounds(&self) -> [f64; 4] {
+        bounds(self.parts.iter().flatten())
+    }
+}
+
+fn bounds<'a>(points: impl Iterator<Item = &'a (f64, f64)>) -> [f64; 4] {
+    let mut bounds = [
+        f64::INFINITY,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NEG_INFINITY,
+    ];
+    for &(x, y) in points {
+        bounds = [
+            bounds[0].min(x),
+            bounds[1].min(y),
+            bounds[2].max(x),
+            bounds[3].max(y),
+        ];
+    }
+    if bounds[0].is_finite() {
+        bounds
+    } else {
+        [0.0; 4]
+    }
+}
+
+fn write_header<W: Write>(
+    writer: &mut W,
+    file_bytes: usize,
+    shape_type: ShapeType,
+    bounds: [f64; 4],
+) -> std::io::Result<()> {
+    writer.write_all(&FILE_CODE.to_be_bytes())?;
+    writer.write_all(&[0u8; 20])?;
+    writer.write_all(&((file_bytes / 2) as i32).to_be_bytes())?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(shape_type as i32).to_le_bytes())?;
+    for value in bounds {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    // Z and M ranges are unused for 2D shapes
+    writer.write_all(&[0u8; 32])
+}
+
+fn write_shp<W: Write>(
+    writer: &mut W,
+    shape_type: ShapeType,
+    records: &[ShapeRecord],
+) -> std::io::Result<()> {
+    let file_bytes = HEADER_BYTES
+        + records
+            .iter()
+            .map(|record| 8 + record.content_bytes())
+            .sum::<usize>();
+    let all_bounds = bounds(
+        records
+            .iter()
+            .flat_map(|record| record.parts.iter().flatten()),
+    );
+    write_header(writer, file_bytes, shape_type, all_bounds)?;
+    for (index, record) in records.iter().enumerate() {
+        writer.write_all(&(index as i32 + 1).to_be_bytes())?;
+        writer.write_all(&((record.content_bytes() / 2) as i32).to_be_bytes())?;
+        writer.write_all(&(shape_type as i32).to_le_bytes())?;
+        for value in record.bounds() {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&(record.parts.len() as i32).to_le_bytes())?;
+        writer.write_all(&(record.point_count() as i32).to_le_bytes())?;
+        let mut start = 0;
+        for part in &record.parts {
+            writer.write_all(&(start as i32).to_le_bytes())?;
+            start += part.len();
+        }
+        for &(x, y) in record.parts.iter().flatten() {
+            writer.write_all(&x.to_le_bytes())?;
+            writer.write_all(&y.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn write_shx<W: Write>(
+    writer: &mut W,
+    shape_type: ShapeType,
+    records: &[ShapeRecord],
+) -> std::io::Result<()> {
+    let all_bounds = bounds(
+        records
+            .iter()
+            .flat_map(|record| record.parts.iter().flatten()),
+    );
+    write_header(
+        writer,
+        HEADER_BYTES + 8 * records.len(),
+        shape_type,
+        all_bounds,
+    )?;
+    let mut offset = HEADER_BYTES;
+    for record in records {
+        writer.write_all(&((offset / 2) as i32).to_be_bytes())?;
+        writer.write_all(&((record.content_bytes() / 2) as i32).to_be_bytes())?;
+        offset += 8 + record.content_bytes();
+    }
+    Ok(())
+}
+
+/// Civil (year, month, day) of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn write_dbf<W: Write>(
+    writer: &mut W,
+    fields: &[DbfField],
+    records: &[ShapeRecord],
+) -> std::io::Result<()> {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86400) as i64;
+    let (year, month, day) = civil_from_days(days);
+    let header_bytes = 32 + 32 * fields.len() + 1;
+    let record_bytes = 1 + fields.iter().map(|field| field.width).sum::<usize>();
+
+    writer.write_all(&[0x03, (year - 1900) as u8, month as u8, day as u8])?;
+    writer.write_all(&(records.len() as u32).to_le_bytes())?;
+    writer.write_all(&(header_bytes as u16).to_le_bytes())?;
+    writer.write_all(&(record_bytes as u16).to_le_bytes())?;
+    writer.write_all(&[0u8; 20])?;
+    for field in fields {
+        let mut name = [0u8; 11];
+        name[..field.name.len()].copy_from_slice(field.name.as_bytes());
+        writer.write_all(&name)?;
+        writer.write_all(&[field.kind])?;
+        writer.write_all(&[0u8; 4])?;
+        writer.write_all(&[field.width as u8, field.decimals as u8])?;
+        writer.write_all(&[0u8; 14])?;
+    }
+    writer.write_all(&[0x0D])?;
+    for record in records {
+        writer.write_all(b" ")?;
+        for (field, value) in fields.iter().zip(&record.values) {
+            writer.write_all(value.format(field).as_bytes())?;
+        }
+    }
+    writer.write_all(&[0x1A])
+}
+
+/// Write the `.shp`, `.shx`, `.dbf` and (with a CRS) `.prj` files next to `path`.
+fn write_shapefile(
+    path: &Path,
+    shape_type: ShapeType,
+    fields: &[DbfField],
+    records: &[ShapeRecord],
+    crs: Option<&str>,
+) -> Result<(), ShapefileError> {
+    persist_with(
+        &path.with_extension("shp"),
+        ShapefileError::IoError,
+        |writer| write_shp(writer, shape_type, records),
+    )?;
+    persist_with(
+        &path.with_extension("shx"),
+        ShapefileError::IoError,
+        |writer| write_shx(writer, shape_type, records),
+    )?;
+    persist_with(
+        &path.with_extension("dbf"),
+        ShapefileError::IoError,
+        |writer| write_dbf(writer, fields, records),
+    )?;
+    if let Some(crs) = crs {
+        let wkt = proj::Proj::new(crs)
+            .map_err(|e| ShapefileError::ProjError(e.to_string()))?
+            .as_wkt(Some(WktVersion::Wkt1_Esri), None)
+            .map_err(|e| ShapefileError::ProjError(e.to_string()))?;
+        persist_with(
+            &path.with_extension("prj"),
+            ShapefileError::IoError,
+            |writer| writer.write_all(wkt.as_bytes()),
+        )?;
+    }
+    Ok(())
+}
+
+impl Hgrid {
+    /// Write the boundary segments as a PolyLine shapefile.
+    ///
+    /// `path` names the `.shp` file; the other members are written beside it.
+    pub fn write_boundaries_shapefile(&self, path: &Path) -> Result<(), ShapefileError> {
+        let fields = [
+            DbfField {
+                name: "type",
+                kind: b'C',
+                width: 16,
+                decimals: 0,
+            },
+            DbfField {
+                name: "index",
+                kind: b'N',
+                width: 10,
+                decimals: 0,
+            },
+        ];
+        let records: Vec<ShapeRecord> = self
+            .boundary_features()
+            .into_iter()
+            .filter_map(|feature| {
+                let Geometry::LineString(coordinates) = feature.geometry else {
+                    return None;
+                };
+                let values = ["type", "index"]
+                    .iter()
+                    .map(|name| match feature.properties.get(*name) {
+                        Some(PropertyValue::Text(text)) => DbfValue::Text(text.clone()),
+                        Some(PropertyValue::Integer(value)) => DbfValue::Number(*value as f64),
+                        None => DbfValue::Missing,
+                    })
+                    .collect();
+                Some(ShapeRecord {
+                    parts: vec![coordinates],
+                    values,
+                })
+            })
+            .collect();
+        write_shapefile(path, ShapeType::PolyLine, &fields, &records, self.crs())
+    }
+
+    /// Write the element footprints as a Polygon shapefile.
+    ///
+    /// `path` names the `.shp` file; the other members are written beside it.
+    pub fn write_elements_shapefile(&self, path: &Path) -> Result<(), ShapefileError> {
+        let fields = [
+            DbfField {
+                name: "id",
+                kind: b'N',
+                width: 10,
+                decimals: 0,
+            },
+            DbfField {
+                name: "area",
+                kind: b'N',
+                width: 24,
+                decimals: 6,
+            },
+            DbfField {
+                name: "depth_mean",
+                kind: b'N',
+                width: 18,
+                decimals: 6,
+            },
+        ];
+        let nodes = self.nodes();
+        let depths = self.depths_positive_down();
+        let records: Vec<ShapeRecord> = self
+            .elements()
+            .iter()
+            .map(|(elem_id, element)| {
+                let indices: Vec<usize> = element
+                    .iter()
+                    .filter_map(|node_id| nodes.index_of(*node_id))
+                    .collect();
+                let ring: Vec<(f64, f64)> = indices
+                    .iter()
+                    .map(|&index| (self.x()[index], self.y()[index]))
+                    .collect();
+                let area = (0..ring.len())
+                    .map(|i| {
+                        let (x0, y0) = ring[i];
+                        let (x1, y1) = ring[(i + 1) % ring.len()];
+                        x0 * y1 - x1 * y0
+                    })
+                    .sum::<f64>()
+                    / 2.0;
+                let depth_mean = indices.iter().map(|&index| depths[index]).sum::<f64>()
+                    / indices.len().max(1) as f64;
+                // Shapefile outer rings are clockwise and explicitly closed
+                let mut part: Vec<(f64, f64)> = ring.iter().rev().copied().collect();
+                if let Some(&first) = part.first() {
+                    part.push(first);
+                }
+                ShapeRecord {
+                    parts: vec![part],
+                    values: vec![
+                        DbfValue::Number(elem_id as f64),
+                        DbfValue::Number(area.abs()),
+                        DbfValue::Number(depth_mean),
+                    ],
+                }
+            })
+            .collect();
+        write_shapefile(path, ShapeType::Polygon, &fields, &records, self.crs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{hgrid_from_gr3, UNIT_SQUARE_WITH_BOUNDARIES};
+    use tempfile::tempdir;
+
+    // Two triangles in the unit square, with an open and a land boundary
+    fn be_i32(bytes: &[u8], offset: usize) -> i32 {
+        i32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_write_elements_shapefile() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("elements.shp");
+        hgrid_from_gr3(UNIT_SQUARE_WITH_BOUNDARIES)
+            .write_elements_shapefile(&path)
+            .unwrap();
+
+        let shp = std::fs::read(&path).unwrap();
+        assert_eq!(be_i32(&shp, 0), FILE_CODE);
+        assert_eq!(be_i32(&shp, 24) as usize * 2, shp.len());
+        // Two triangles: 8 + 4 + 32 + 4 + 4 + 4 + 4 * 16 bytes each
+        assert_eq!(shp.len(), HEADER_BYTES + 2 * 120);
+
+        let shx = std::fs::read(path.with_extension("shx")).unwrap();
+        assert_eq!(shx.len(), HEADER_BYTES + 2 * 8);
+        assert_eq!(
+            be_i32(&shx, HEADER_BYTES + 8),
+            (HEADER_BYTES as i32 + 120) / 2
+        );
+
+        let dbf = std::fs::read(path.with_extension("dbf")).unwrap();
+        assert_eq!(u32::from_le_bytes(dbf[4..8].try_into().unwrap()), 2);
+        let header_bytes = u16::from_le_bytes(dbf[8..10].try_into().unwrap()) as usize;
+        let first_record = String::from_utf8_lossy(&dbf[header_bytes..header_bytes + 53]);
+        assert_eq!(
+            first_record.split_whitespace().collect::<Vec<_>>(),
+            ["1", "0.500000", "6.000000"]
+        );
+        assert_eq!(*dbf.last().unwrap(), 0x1A);
+
+        assert!(path.with_extension("prj").exists());
+    }
+
+    #[test]
+    fn test_write_boundaries_shapefile() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("boundaries.shp");
+        hgrid_from_gr3(UNIT_SQUARE_WITH_BOUNDARIES)
+            .write_boundaries_shapefile(&path)
+            .unwrap();
+
+        let dbf = std::fs::read(path.with_extension("dbf")).unwrap();
+        let header_bytes = u16::from_le_bytes(dbf[8..10].try_into().unwrap()) as usize;
+        let records: Vec<String> = dbf[header_bytes..dbf.len() - 1]
+            .chunks(27)
+            .map(|record| {
+                String::from_utf8_lossy(record)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        assert_eq!(records, ["open 1", "land 1"]);
+
+        let shp = std::fs::read(&path).unwrap();
+        assert_eq!(
+            i32::from_le_bytes(shp[32..36].try_into().unwrap()),
+            ShapeType::PolyLine as i32
+        );
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(20_742), (2026, 10, 16));
+    }
+}
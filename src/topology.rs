@@ -0,0 +1,413 @@
+//! Side (edge) table of a mesh, numbered the way SCHISM numbers sides.
+//!
+//! SCHISM walks the elements in order and, within each element, its local
+//! sides in order, where local side `j` joins vertices `j + 1` and `j + 2`
+//! (`nxq`). A side gets the next number the first time it is met, so
+//! `Topology::sides()[i]` matches `isidenode(:, i + 1)` and `isdel(:, i + 1)`
+//! and `Topology::element_sides(e)` matches `elside(:, e + 1)`. Elements are
+//! referred to by position in element order (SCHISM's 1-based index minus one)
+//! and nodes by id.
+//!
+//! Alongside the sides, the topology holds the adjacency SCHISM keeps in
+//! `indel` (elements around each node), `ic3` (element across each side) and
+//! `indnd` (nodes around each node), stored in compressed sparse row form.
+//! Nodes are referred to by position in node order there.
+//!
+//! Everything is built once per grid and cached, see [`Hgrid::topology`].
+
+use crate::Hgrid;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/// Fill value for the unused fourth slot of a triangle's sides.
+const NO_SIDE: usize = usize::MAX;
+
+/// One side of the mesh (`isidenode(:, i)` and `isdel(:, i)`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Side {
+    /// Node ids at either end, in the order `left` traverses them
+    pub nodes: (u32, u32),
+    /// Position of the element the side was numbered from; a counterclockwise
+    /// element lies to the left of `nodes.0 -> nodes.1`
+    pub left: usize,
+    /// Position of the element across the side, `None` on the mesh boundary
+    pub right: Option<usize>,
+}
+
+impl Side {
+    pub fn is_boundary(&self) -> bool {
+        self.right.is_none()
+    }
+}
+
+/// Compressed sparse row storage: row `i` is `values[offsets[i]..offsets[i + 1]]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Csr<T> {
+    offsets: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T> Csr<T> {
+    /// Row offsets into `values`, one more than the number of rows
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// Values of all rows, concatenated
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    pub fn row(&self, index: usize) -> &[T] {
+        &self.values[self.offsets[index]..self.offsets[index + 1]]
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[T]> + '_ {
+        self.offsets
+            .windows(2)
+            .map(|window| &self.values[window[0]..window[1]])
+    }
+}
+
+impl<T> Default for Csr<T> {
+    fn default() -> Self {
+        Csr {
+            offsets: vec![0],
+            values: Vec::new(),
+        }
+    }
+}
+
+/// Side table of a mesh, the sides of each element and node/element adjacency.
+///
+/// A side shared by more than two elements (a non-manifold edge) joins the
+/// first two elements in element order; every further element gets a
+/// boundary side of its own.
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    sides: Vec<Side>,
+    /// Side number of each local side of each element, padded with `NO_SIDE`
+    element_sides: Vec<[usize; 4]>,
+    /// Elements around each node (`indel`)
+    node_elements: Csr<usize>,
+    /// Element across each local side of each element (`ic3`)
+    element_neighbors: Csr<Option<usize>>,
+    /// Nodes sharing an element with each node (`indnd`)
+    node_neighbors: Csr<usize>,
+}
+
+impl Topology {
+    pub fn new(hgrid: &Hgrid) -> Topology {
+        let rows: Vec<&[u32]> = hgrid.elements().iter().map(|(_, nodes)| nodes).collect();
+        let local_side = |element: usize, j: usize| {
+            let row = rows[element];
+            (row[(j + 1) % row.len()], row[(j + 2) % row.len()])
+        };
+
+        // Element and local side across each local side (SCHISM's ic3)
+        let mut across: Vec<[Option<(usize, usize)>; 4]> = vec![[None; 4]; rows.len()];
+        // Unpaired occurrence of each undirected side, taken once it is paired
+        let mut unpaired: HashMap<(u32, u32), Option<(usize, usize)>> = HashMap::new();
+        for (element, row) in rows.iter().enumerate() {
+            for j in 0..row.len() {
+                let (a, b) = local_side(element, j);
+                match unpaired.entry((a.min(b), a.max(b))) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(Some((element, j)));
+                    }
+                    Entry::Occupied(mut entry) => {
+                        if let Some((other, k)) = entry.get_mut().take() {
+                            across[element][j] = Some((other, k));
+                            across[other][k] = Some((element, j));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut sides = Vec::new();
+        let mut element_sides = vec![[NO_SIDE; 4]; rows.len()];
+        for (element, row) in rows.iter().enumerate() {
+            for j in 0..row.len() {
+                match across[element][j] {
+                    // Already numbered from the element across
+                    Some(other) if other < (element, j) => {}
+                    other => {
+                        element_sides[element][j] = sides.len();
+                        if let Some((other, k)) = other {
+                            element_sides[other][k] = sides.len();
+                        }
+                        sides.push(Side {
+                            nodes: local_side(element, j),
+                            left: element,
+                            right: other.map(|(other, _)| other),
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut element_neighbors = Csr::default();
+        for (element, row) in rows.iter().enumerate() {
+            element_neighbors.values.extend(
+                across[element][..row.len()]
+                    .iter()
+                    .map(|a| a.map(|(other, _)| other)),
+            );
+            element_neighbors
+                .offsets
+                .push(element_neighbors.values.len());
+        }
+
+        // Node positions of each element; ids missing from the nodes are caught
+        // by check_validity() and left out here
+        let nodes = hgrid.nodes();
+        let positions: Vec<Vec<usize>> = rows
+            .iter()
+            .map(|row| row.iter().filter_map(|&id| nodes.index_of(id)).collect())
+            .collect();
+
+        let mut counts = vec![0; nodes.len()];
+        for row in &positions {
+            for (k, &node) in row.iter().enumerate() {
+                if !row[..k].contains(&node) {
+                    counts[node] += 1;
+                }
+            }
+        }
+        let mut node_elements = Csr {
+            offsets: Vec::with_capacity(nodes.len() + 1),
+            values: vec![0; counts.iter().sum()],
+        };
+        node_elements.offsets.push(0);
+        for count in &counts {
+            node_elements
+                .offsets
+                .push(node_elements.offsets.last().unwrap() + count);
+        }
+        let mut cursor = node_elements.offsets[..nodes.len()].to_vec();
+        for (element, row) in positions.iter().enumerate() {
+            for (k, &node) in row.iter().enumerate() {
+                if !row[..k].contains(&node) {
+                    node_elements.values[cursor[node]] = element;
+                    cursor[node] += 1;
+                }
+            }
+        }
+
+        // Like SCHISM, walk the elements around each node and take their other
+        // vertices in turn, starting after the node itself
+        let mut node_neighbors = Csr::default();
+        for node in 0..nodes.len() {
+            let start = node_neighbors.values.len();
+            for &element in node_elements.row(node) {
+                let row = &positions[element];
+                let own = row.iter().position(|&other| other == node).unwrap();
+                for k in 1..row.len() {
+                    let other = row[(own + k) % row.len()];
+                    if other != node && !node_neighbors.values[start..].contains(&other) {
+                        node_neighbors.values.push(other);
+                    }
+                }
+            }
+            node_neighbors.offsets.push(node_neighbors.values.len());
+        }
+
+        Topology {
+            sides,
+            element_sides,
+            node_elements,
+            element_neighbors,
+            node_neighbors,
+        }
+    }
+
+    /// All sides in SCHISM order
+    pub fn sides(&self) -> &[Side] {
+        &self.sides
+    }
+
+    pub fn len(&self) -> usize {
+        self.sides.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sides.is_empty()
+    }
+
+    /// Sides on the mesh boundary, in SCHISM order
+    pub fn boundary_sides(&self) -> impl Iterator<Item = &Side> + '_ {
+        self.sides.iter().filter(|side| side.is_boundary())
+    }
+
+    /// Side numbers of the element at `element`, local side `j` being opposite
+    /// its vertex `j` (`elside`)
+    pub fn element_sides(&self, element: usize) -> &[usize] {
+        let sides = &self.element_sides[element];
+        let count = sides.iter().position(|&side| side == NO_SIDE).unwrap_or(4);
+        &sides[..count]
+    }
+
+    /// Element across each local side of the element at `element`, `None` on
+    /// the mesh boundary (`ic3`)
+    pub fn element_neighbors(&self, element: usize) -> &[Option<usize>] {
+        self.element_neighbors.row(element)
+    }
+
+    /// Elements around the node at position `node`, in element order (`indel`)
+    pub fn node_elements(&self, node: usize) -> &[usize] {
+        self.node_elements.row(node)
+    }
+
+    /// Nodes sharing an element with the node at position `node` (`indnd`).
+    ///
+    /// On quads this includes the opposite corner, as in SCHISM.
+    pub fn node_neighbors(&self, node: usize) -> &[usize] {
+        self.node_neighbors.row(node)
+    }
+
+    /// `indel` for all nodes
+    pub fn node_elements_csr(&self) -> &Csr<usize> {
+        &self.node_elements
+    }
+
+    /// `ic3` for all elements
+    pub fn element_neighbors_csr(&self) -> &Csr<Option<usize>> {
+        &self.element_neighbors
+    }
+
+    /// `indnd` for all nodes
+    pub fn node_neighbors_csr(&self) -> &Csr<usize> {
+        &self.node_neighbors
+    }
+}
+
+impl Hgrid {
+    /// Midpoint of each side in SCHISM order, shaped (number of sides, 2)
+    /// (`xcj`, `ycj`).
+    pub fn side_centers(&self) -> ndarray::Array2<f64> {
+        let nodes = self.nodes();
+        let sides = self.topology().sides();
+        let mut centers = ndarray::Array2::zeros((sides.len(), 2));
+        for (mut center, side) in centers.outer_iter_mut().zip(sides) {
+            // Elements referencing missing nodes are caught by check_validity()
+            let (x0, y0) = nodes.get_node(side.nodes.0).unwrap_or((f64::NAN, f64::NAN));
+            let (x1, y1) = nodes.get_node(side.nodes.1).unwrap_or((f64::NAN, f64::NAN));
+            center[0] = 0.5 * (x0 + x1);
+            center[1] = 0.5 * (y0 + y1);
+        }
+        centers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gr3;
+    use std::io::BufReader;
+
+    /// Two triangles and a quad:
+    ///
+    /// ```text
+    /// 3---4---6
+    /// | \ |   |
+    /// 1---2---5
+    /// ```
+    const GR3: &str = "topology
+3 6
+1 0 0 1
+2 1 0 1
+3 0 1 1
+4 1 1 1
+5 2 0 1
+6 2 1 1
+1 3 1 2 3
+2 3 2 4 3
+3 4 2 5 6 4
+";
+
+    fn sample_hgrid() -> Hgrid {
+        let gr3 = gr3::parse_from_reader(BufReader::new(GR3.as_bytes()), "test").unwrap();
+        Hgrid::try_from(gr3).unwrap()
+    }
+
+    #[test]
+    fn test_schism_side_numbering() {
+        let hgrid = sample_hgrid();
+        let topology = hgrid.topology();
+        let nodes: Vec<(u32, u32)> = topology.sides().iter().map(|side| side.nodes).collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (2, 3),
+                (3, 1),
+                (1, 2),
+                (4, 3),
+                (2, 4),
+                (5, 6),
+                (6, 4),
+                (2, 5)
+            ]
+        );
+        assert_eq!(topology.sides()[0].right, Some(1));
+        assert_eq!(topology.sides()[4].left, 1);
+        assert_eq!(topology.sides()[4].right, Some(2));
+        assert_eq!(topology.element_sides(0), &[0, 1, 2]);
+        assert_eq!(topology.element_sides(1), &[3, 0, 4]);
+        assert_eq!(topology.element_sides(2), &[5, 6, 4, 7]);
+        assert_eq!(topology.boundary_sides().count(), 6);
+        // Euler: sides = elements + nodes - 1 for a simply connected mesh
+        assert_eq!(topology.len(), 3 + 6 - 1);
+    }
+
+    #[test]
+    fn test_element_neighbors_and_side_centers() {
+        let hgrid = sample_hgrid();
+        let topology = hgrid.topology();
+        assert_eq!(topology.element_neighbors(0), &[Some(1), None, None]);
+        assert_eq!(topology.element_neighbors(1), &[None, Some(0), Some(2)]);
+        assert_eq!(topology.element_neighbors(2), &[None, None, Some(1), None]);
+        let centers = hgrid.side_centers();
+        assert_eq!(centers.row(0).to_vec(), vec![0.5, 0.5]);
+        assert_eq!(centers.row(7).to_vec(), vec![1.5, 0.0]);
+    }
+
+    #[test]
+    fn test_node_adjacency() {
+        let hgrid = sample_hgrid();
+        let topology = hgrid.topology();
+        let node_elements: Vec<&[usize]> = topology.node_elements_csr().iter().collect();
+        assert_eq!(
+            node_elements,
+            vec![&[0][..], &[0, 1, 2], &[0, 1], &[1, 2], &[2], &[2]]
+        );
+        assert_eq!(topology.node_neighbors(0), &[1, 2]);
+        assert_eq!(topology.node_neighbors(1), &[2, 0, 3, 4, 5]);
+        // The quad's opposite corner (node 5) counts as a neighbour of node 4
+        assert_eq!(topology.node_neighbors(3), &[2, 1, 4, 5]);
+        assert_eq!(topology.element_neighbors_csr().offsets(), &[0, 3, 6, 10]);
+        assert_eq!(
+            hgrid
+                .get_number_of_elements_connected_to_each_node()
+                .to_vec(),
+            vec![1, 3, 2, 2, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_topology_is_cached_and_shared_by_clones() {
+        let hgrid = sample_hgrid();
+        assert!(std::ptr::eq(hgrid.topology(), hgrid.topology()));
+        let clone = hgrid.clone();
+        assert!(std::ptr::eq(hgrid.topology(), clone.topology()));
+    }
+}
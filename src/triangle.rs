@@ -0,0 +1,647 @@
+//! Shewchuk's Triangle `.node`/`.ele`/`.poly` reader and writer
+//!
+//! Nodes and elements are renumbered consecutively from 1, as Triangle requires.
+//! Node attributes carry the depth column(s) in the gr3 sign convention. The
+//! `.poly` file lists the domain outline from [`Hgrid::domain_polygons`] as
+//! segments on the `.node` vertices, with one hole point per island.
+//!
+//! Boundary markers follow Triangle's convention that 0 is interior and 1 is a
+//! plain boundary: 1 marks land, 2 open and 3 island boundaries. On read,
+//! consecutive `.poly` segments with the same marker are chained back into
+//! boundary segments; without a `.poly` file the `.node` markers of the mesh
+//! boundary edges are used instead (an edge between an open and a land node
+//! counts as land). Triangle files carry no description, CRS or IBTYPE flags,
+//! element attributes are ignored on read, and quadrilaterals cannot be written.
+
+use crate::compression::open_reader;
+use crate::gr3::{Gr3ParserOutput, Gr3ParserOutputBuilder, Gr3ParserOutputBuilderError};
+use crate::hgrid::DepthConvention;
+use crate::nodes::DEPTH_ATTRIBUTE;
+use crate::output::{node_number, persist_with};
+use crate::Hgrid;
+use linked_hash_map::LinkedHashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{prelude::*, BufReader, Lines};
+use std::path::Path;
+use thiserror::Error;
+
+const INTERIOR_MARKER: u32 = 0;
+const LAND_MARKER: u32 = 1;
+const OPEN_MARKER: u32 = 2;
+const ISLAND_MARKER: u32 = 3;
+
+#[derive(Error, Debug)]
+pub enum TriangleError {
+    #[error("I/O error: {0}")]
+    IoError(String),
+
+    #[error("Empty file error: {0}")]
+    EmptyFile(String),
+
+    #[error("Line read error: file {0}, error: {1}")]
+    LineReadError(String, String),
+
+    #[error("Unsupported Triangle mesh {0}: {1}")]
+    Unsupported(String, String),
+
+    #[error(transparent)]
+    Gr3ParserOutputBuilderError(#[from] Gr3ParserOutputBuilderError),
+}
+
+impl Hgrid {
+    /// Write the mesh as Triangle `.node`, `.ele` and `.poly` files.
+    ///
+    /// `path` may name any of the three; the others are written beside it.
+    pub fn write_triangle(&self, path: &Path) -> Result<(), TriangleError> {
+        write_to_path(path, self)
+    }
+}
+
+/// Line source that skips blank lines and `#` comments.
+struct TriangleLines {
+    lines: Lines<BufReader<Box<dyn Read>>>,
+    fname: String,
+    line_number: usize,
+}
+
+impl TriangleLines {
+    fn open(path: &Path) -> Result<TriangleLines, TriangleError> {
+        let fname = path.display().to_string();
+        let reader = open_reader(path)
+            .map_err(|e| TriangleError::IoError(format!("Failed to open {}: {}", fname, e)))?;
+        Ok(TriangleLines {
+            lines: reader.lines(),
+            fname,
+            line_number: 0,
+        })
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, TriangleError> {
+        for line in self.lines.by_ref() {
+            self.line_number += 1;
+            let line =
+                line.map_err(|e| TriangleError::LineReadError(self.fname.clone(), e.to_string()))?;
+            let content = line.split('#').next().unwrap_or("").trim();
+            if !content.is_empty() {
+                return Ok(Some(content.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse a line of at least `min_len` numbers.
+    fn expect_numbers(&mut self, what: &str, min_len: usize) -> Result<Vec<f64>, TriangleError> {
+        let Some(line) = self.next_line()? else {
+            if self.line_number == 0 {
+                return Err(TriangleError::EmptyFile(self.fname.clone()));
+            }
+            return Err(self.error(format!(
+                "Expected {} but reached the end of the file.",
+                what
+            )));
+        };
+        let numbers = line
+            .split_whitespace()
+            .map(|item| {
+                item.parse::<f64>().map_err(|_| {
+                    self.error(format!(
+                        "Expected {} to be numeric but found {}.",
+                        what, item
+                    ))
+                })
+            })
+            .collect::<Result<Vec<f64>, TriangleError>>()?;
+        if numbers.len() < min_len {
+            return Err(self.error(format!(
+                "Expected {} to have at least {} items but found {}.",
+                what, min_len, line
+            )));
+        }
+        Ok(numbers)
+    }
+
+    fn error(&self, message: String) -> TriangleError {
+        TriangleError::LineReadError(
+            self.fname.clone(),
+            format!(
+                "Error reading Triangle file: {}. Line {}: {}",
+                self.fname, self.line_number, message
+            ),
+        )
+    }
+}
+
+/// Parse a Triangle mesh from the `.node`, `.ele` and (if present) `.poly`
+/// files sharing the stem of `path`.
+///
+/// Meshes numbered from 0 are shifted to start at 1.
+pub fn parse_from_path_ref(path: &Path) -> Result<Gr3ParserOutput, TriangleError> {
+    let mut node_lines = TriangleLines::open(&path.with_extension("node"))?;
+    let header = node_lines.expect_numbers("the node header", 4)?;
+    let (count, num_attributes, has_markers) =
+        (header[0] as usize, header[2] as usize, header[3] != 0.0);
+    let mut nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> = LinkedHashMap::new();
+    let mut node_markers: HashMap<u32, u32> = HashMap::new();
+    let mut offset = None;
+    for _ in 0..count {
+        let items = node_lines
+            .expect_numbers("a node line", 3 + num_attributes + usize::from(has_markers))?;
+        let offset = *offset.get_or_insert(u32::from(items[0] == 0.0));
+        let node_id = items[0] as u32 + offset;
+        let values = (num_attributes > 0).then(|| items[3..3 + num_attributes].to_vec());
+        nodes.insert(node_id, (vec![items[1], items[2]], values));
+        if has_markers {
+            node_markers.insert(node_id, items[3 + num_attributes] as u32);
+        }
+    }
+    if nodes.is_empty() {
+        return Err(TriangleError::EmptyFile(node_lines.fname));
+    }
+    let offset = offset.unwrap_or(0);
+
+    let mut element_lines = TriangleLines::open(&path.with_extension("ele"))?;
+    let header = element_lines.expect_numbers("the element header", 2)?;
+    let (count, nodes_per_element) = (header[0] as usize, header[1] as usize);
+    if nodes_per_element != 3 && nodes_per_element != 6 {
+        return Err(element_lines.error(format!(
+            "Expected 3 or 6 nodes per triangle but found {}.",
+            nodes_per_element
+        )));
+    }
+    let mut elements: LinkedHashMap<u32, Vec<u32>> = LinkedHashMap::new();
+    for _ in 0..count {
+        let items = element_lines.expect_numbers("an element line", 1 + nodes_per_element)?;
+        // Second-order triangles list their corners first
+        let corners: Vec<u32> = items[1..4].iter().map(|&id| id as u32 + offset).collect();
+        if let Some(node_id) = corners.iter().find(|id| !nodes.contains_key(id)) {
+            return Err(element_lines.error(format!(
+                "Element {} references unknown node {}.",
+                items[0], node_id
+            )));
+        }
+        elements.insert(items[0] as u32 + offset, corners);
+    }
+
+    let poly_path = path.with_extension("poly");
+    let marked_edges = if poly_path.exists() {
+        read_poly_segments(&poly_path, offset)?
+    } else {
+        marked_boundary_edges(&elements, &node_markers)
+    };
+    let mut open_boundaries = Vec::new();
+    let mut land_boundaries = Vec::new();
+    let mut interior_boundaries = Vec::new();
+    for (marker, segments) in [
+        (OPEN_MARKER, &mut open_boundaries),
+        (LAND_MARKER, &mut land_boundaries),
+        (ISLAND_MARKER, &mut interior_boundaries),
+    ] {
+        let edges: Vec<(u32, u32)> = marked_edges
+            .iter()
+            .filter(|(_a, _b, edge_marker)| *edge_marker == marker)
+            .map(|&(a, b, _marker)| (a, b))
+            .collect();
+        *segments = chain_edges(&edges, marker == ISLAND_MARKER);
+    }
+
+    let mut builder = Gr3ParserOutputBuilder::default();
+    builder
+        .nodes(nodes)
+        .elements(Some(elements))
+        .open_boundaries(open_boundaries)
+        .land_boundaries(land_boundaries)
+        .interior_boundaries(interior_boundaries);
+    Ok(builder.build()?)
+}
+
+/// Segments of a `.poly` file as (node id, node id, marker).
+///
+/// Segment endpoints are taken as `.node` ids: Triangle keeps the input
+/// vertex numbering in its output, and `.poly` files it writes list no vertices.
+fn read_poly_segments(path: &Path, offset: u32) -> Result<Vec<(u32, u32, u32)>, TriangleError> {
+    let mut lines = TriangleLines::open(path)?;
+    let header = lines.expect_numbers("the vertex header", 4)?;
+    let (count, num_attributes, has_markers) =
+        (header[0] as usize, header[2] as usize, header[3] != 0.0);
+    for _ in 0..count {
+        lines.expect_numbers(
+            "a vertex line",
+            3 + num_attributes + usize::from(has_markers),
+        )?;
+    }
+    let header = lines.expect_numbers("the segment header", 1)?;
+    let (count, has_markers) = (
+        header[0] as usize,
+        header.get(1).is_some_and(|&markers| markers != 0.0),
+    );
+    let mut segments = Vec::with_capacity(count);
+    for _ in 0..count {
+        let items = lines.expect_numbers("a segment line", 3 + usize::from(has_markers))?;
+        let marker = if has_markers {
+            items[3] as u32
+        } else {
+            LAND_MARKER
+        };
+        segments.push((items[1] as u32 + offset, items[2] as u32 + offset, marker));
+    }
+    // Holes and regional attributes only matter to the mesher
+    Ok(segments)
+}
+
+/// Mesh boundary edges, in element orientation, marked from their end nodes
+fn marked_boundary_edges(
+    elements: &LinkedHashMap<u32, Vec<u32>>,
+    node_markers: &HashMap<u32, u32>,
+) -> Vec<(u32, u32, u32)> {
+    let mut edge_count: HashMap<(u32, u32), usize> = HashMap::new();
+    let mut edges = Vec::new();
+    for element in elements.values() {
+        for i in 0..element.len() {
+            let (a, b) = (element[i], element[(i + 1) % element.len()]);
+            *edge_count.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            edges.push((a, b));
+        }
+    }
+    edges
+        .into_iter()
+        .filter(|(a, b)| edge_count[&(*a.min(b), *a.max(b))] == 1)
+        .filter_map(|(a, b)| {
+            let marker_a = node_markers.get(&a).copied().unwrap_or(INTERIOR_MARKER);
+            let marker_b = node_markers.get(&b).copied().unwrap_or(INTERIOR_MARKER);
+            match (marker_a, marker_b) {
+                (INTERIOR_MARKER, _) | (_, INTERIOR_MARKER) => None,
+                (marker_a, marker_b) if marker_a == marker_b => Some((a, b, marker_a)),
+                _ => Some((a, b, LAND_MARKER)),
+            }
+        })
+        .collect()
+}
+
+/// Join directed edges into maximal node paths. Edges left over once every
+/// open path is followed form rings; closed islands drop the repeated start
+/// node, matching the gr3 convention.
+fn chain_edges(edges: &[(u32, u32)], is_closed: bool) -> Vec<Vec<u32>> {
+    let mut next: HashMap<u32, Vec<u32>> = HashMap::new();
+    let mut has_incoming: HashSet<u32> = HashSet::new();
+    for &(a, b) in edges.iter().rev() {
+        next.entry(a).or_default().push(b);
+        has_incoming.insert(b);
+    }
+    let follow = |start: u32, next: &mut HashMap<u32, Vec<u32>>| -> Vec<u32> {
+        let mut path = vec![start];
+        let mut current = start;
+        while let Some(node_id) = next.get_mut(&current).and_then(Vec::pop) {
+            path.push(node_id);
+            current = node_id;
+        }
+        path
+    };
+    let mut paths = Vec::new();
+    for &(a, _b) in edges {
+        if !has_incoming.contains(&a) && next.get(&a).is_some_and(|n| !n.is_empty()) {
+            paths.push(follow(a, &mut next));
+        }
+    }
+    for &(a, _b) in edges {
+        if next.get(&a).is_some_and(|n| !n.is_empty()) {
+            let mut ring = follow(a, &mut next);
+            if is_closed && ring.len() > 1 && ring.first() == ring.last() {
+                ring.pop();
+            }
+            paths.push(ring);
+        }
+    }
+    paths
+}
+
+/// A point strictly inside a simple polygon ring
+fn interior_point(ring: &[(f64, f64)]) -> (f64, f64) {
+    let n = ring.len();
+    // The leftmost vertex is convex; use the triangle it forms with its
+    // neighbours unless another vertex pokes into it
+    let v = (0..n)
+        .min_by(|&i, &j| ring[i].partial_cmp(&ring[j]).unwrap())
+        .unwrap();
+    let (p, c, q) = (ring[(v + n - 1) % n], ring[v], ring[(v + 1) % n]);
+    let cross = |a: (f64, f64), b: (f64, f64), r: (f64, f64)| {
+        (b.0 - a.0) * (r.1 - a.1) - (b.1 - a.1) * (r.0 - a.0)
+    };
+    let orientation = cross(p, c, q).signum();
+    let inside_triangle = |r: (f64, f64)| {
+        cross(p, c, r) * orientation > 0.0
+            && cross(c, q, r) * orientation > 0.0
+            && cross(q, p, r) * orientation > 0.0
+    };
+    let intruder = ring
+        .iter()
+        .copied()
+        .filter(|&r| inside_triangle(r))
+        .max_by(|&a, &b| {
+            (cross(q, p, a) * orientation)
+                .partial_cmp(&(cross(q, p, b) * orientation))
+                .unwrap()
+        });
+    match intruder {
+        Some(r) => ((c.0 + r.0) / 2.0, (c.1 + r.1) / 2.0),
+        None => ((p.0 + c.0 + q.0) / 3.0, (p.1 + c.1 + q.1) / 3.0),
+    }
+}
+
+fn write_node<W: Write>(
+    writer: &mut W,
+    hgrid: &Hgrid,
+    node_markers: &HashMap<u32, u32>,
+) -> std::io::Result<()> {
+    let nodes = hgrid.nodes();
+    let depth = nodes.attribute(DEPTH_ATTRIBUTE);
+    // gr3 sign convention, as written by `Hgrid::write`
+    let sign = match hgrid.depth_convention() {
+        DepthConvention::PositiveDown => 1.0,
+        DepthConvention::PositiveUp => -1.0,
+    };
+    let num_attributes = depth.map_or(0, |depth| depth.ncols());
+    if let Some(description) = hgrid.description() {
+        writeln!(writer, "# {}", description)?;
+    }
+    writeln!(writer, "{} 2 {} 1", nodes.len(), num_attributes)?;
+    for (index, node_id) in nodes.ids().iter().enumerate() {
+        write!(
+            writer,
+            "{} {} {}",
+            index + 1,
+            hgrid.x()[index],
+            hgrid.y()[index]
+        )?;
+        if let Some(depth) = depth {
+            for value in depth.row(index) {
+                write!(writer, " {}", sign * value)?;
+            }
+        }
+        writeln!(
+            writer,
+            " {}",
+            node_markers
+                .get(node_id)
+                .copied()
+                .unwrap_or(INTERIOR_MARKER)
+        )?;
+    }
+    Ok(())
+}
+
+fn write_ele<W: Write>(writer: &mut W, hgrid: &Hgrid) -> std::io::Result<()> {
+    let nodes = hgrid.nodes();
+    let elements = hgrid.elements();
+    writeln!(writer, "{} 3 0", elements.len())?;
+    for (index, (_elem_id, element)) in elements.iter().enumerate() {
+        write!(writer, "{}", index + 1)?;
+        for node_id in element {
+            write!(writer, " {}", node_number(nodes, *node_id)?)?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+fn write_poly<W: Write>(
+    writer: &mut W,
+    hgrid: &Hgrid,
+    segments: &[(u32, u32, u32)],
+    holes: &[(f64, f64)],
+) -> std::io::Result<()> {
+    let nodes = hgrid.nodes();
+    // Vertices are read from the .node file
+    writeln!(writer, "0 2 0 1")?;
+    writeln!(writer, "{} 1", segments.len())?;
+    for (index, (a, b, marker)) in segments.iter().enumerate() {
+        writeln!(
+            writer,
+            "{} {} {} {}",
+            index + 1,
+            node_number(nodes, *a)?,
+            node_number(nodes, *b)?,
+            marker
+        )?;
+    }
+    writeln!(writer, "{}", holes.len())?;
+    for (index, (x, y)) in holes.iter().enumerate() {
+        writeln!(writer, "{} {} {}", index + 1, x, y)?;
+    }
+    Ok(())
+}
+
+/// Domain outline edges with boundary markers, and one point in each island
+type MarkedOutline = (Vec<(u32, u32, u32)>, Vec<(f64, f64)>);
+
+fn marked_outline(hgrid: &Hgrid) -> MarkedOutline {
+    let mut edge_markers: HashMap<(u32, u32), u32> = HashMap::new();
+    if let Some(boundaries) = hgrid.boundaries() {
+        for (segments, marker, is_closed) in [
+            (boundaries.land().map(|b| b.nodes_ids()), LAND_MARKER, false),
+            (
+                boundaries.interior().map(|b| b.nodes_ids()),
+                ISLAND_MARKER,
+                true,
+            ),
+            (boundaries.open().map(|b| b.nodes_ids()), OPEN_MARKER, false),
+        ] {
+            for segment in segments.into_iter().flatten() {
+                let mut pairs: Vec<(u32, u32)> = segment.windows(2).map(|w| (w[0], w[1])).collect();
+                if is_closed && segment.len() > 2 {
+                    pairs.push((segment[segment.len() - 1], segment[0]));
+                }
+                for (a, b) in pairs {
+                    edge_markers.insert((a.min(b), a.max(b)), marker);
+                }
+            }
+        }
+    }
+    let nodes = hgrid.nodes();
+    let mut segments = Vec::new();
+    let mut holes = Vec::new();
+    for polygon in hgrid.domain_polygons() {
+        for (ring_index, ring) in polygon.iter().enumerate() {
+            let default_marker = if ring_index == 0 {
+                LAND_MARKER
+            } else {
+                ISLAND_MARKER
+            };
+            for i in 0..ring.len() {
+                let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+                let marker = edge_markers
+                    .get(&(a.min(b), a.max(b)))
+                    .copied()
+                    .unwrap_or(default_marker);
+                segments.push((a, b, marker));
+            }
+            if ring_index > 0 {
+                let coords: Vec<(f64, f64)> = ring
+                    .iter()
+                    .filter_map(|node_id| nodes.get_node(*node_id))
+                    .collect();
+                holes.push(interior_point(&coords));
+            }
+        }
+    }
+    (segments, holes)
+}
+
+pub fn write_to_path(path: &Path, hgrid: &Hgrid) -> Result<(), TriangleError> {
+    if let Some((elem_id, element)) = hgrid
+        .elements()
+        .iter()
+        .find(|(_elem_id, element)| element.len() != 3)
+    {
+        return Err(TriangleError::Unsupported(
+            path.display().to_string(),
+            format!(
+                "element {} has {} nodes but .ele files hold triangles only",
+                elem_id,
+                element.len()
+            ),
+        ));
+    }
+    let (segments, holes) = marked_outline(hgrid);
+    let mut node_markers: HashMap<u32, u32> = HashMap::new();
+    for &(a, b, marker) in &segments {
+        for node_id in [a, b] {
+            // Nodes shared by open and land segments are marked open
+            let entry = node_markers.entry(node_id).or_insert(marker);
+            if marker == OPEN_MARKER {
+                *entry = marker;
+            }
+        }
+    }
+    persist_with(
+        &path.with_extension("node"),
+        TriangleError::IoError,
+        |writer| write_node(writer, hgrid, &node_markers),
+    )?;
+    persist_with(
+        &path.with_extension("ele"),
+        TriangleError::IoError,
+        |writer| write_ele(writer, hgrid),
+    )?;
+    persist_with(
+        &path.with_extension("poly"),
+        TriangleError::IoError,
+        |writer| write_poly(writer, hgrid, &segments, &holes),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gr3;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    // 3x3 nodes split into 8 triangles, open along the right side
+    const GR3: &str = "test mesh
+8 9
+1 0 0 1
+2 1 0 2
+3 2 0 3
+4 0 1 4
+5 1 1 5
+6 2 1 6
+7 0 2 7
+8 1 2 8
+9 2 2 9
+1 3 1 2 5
+2 3 1 5 4
+3 3 2 3 6
+4 3 2 6 5
+5 3 4 5 8
+6 3 4 8 7
+7 3 5 6 9
+8 3 5 9 8
+1 = Number of open boundaries
+3 = Total number of open boundary nodes
+3 = Number of nodes for open boundary 1
+3
+6
+9
+1 = number of land boundaries
+7 = Total number of land boundary nodes
+7 0 = Number of nodes for land boundary 1
+9
+8
+7
+4
+1
+2
+3";
+
+    #[test]
+    fn test_triangle_round_trip() {
+        let gr3 = gr3::parse_from_reader(BufReader::new(GR3.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.node");
+        hgrid.write_triangle(&path).unwrap();
+
+        let poly = std::fs::read_to_string(path.with_extension("poly")).unwrap();
+        assert!(poly.starts_with("0 2 0 1\n8 1\n"));
+
+        let reloaded = Hgrid::try_from(&PathBuf::from(&path)).unwrap();
+        let write_gr3 = |hgrid: &Hgrid, name: &str| {
+            let gr3_path = temp_dir.path().join(name);
+            hgrid.write(&gr3_path).unwrap();
+            let text = std::fs::read_to_string(gr3_path).unwrap();
+            // The description line is not stored in Triangle files
+            text.lines().skip(1).map(str::to_string).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            write_gr3(&reloaded, "reloaded.gr3"),
+            write_gr3(&hgrid, "expected.gr3")
+        );
+    }
+
+    #[test]
+    fn test_parse_zero_based_node_markers() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("mesh.1.node");
+        // Unit square in two triangles; the bottom edge is open
+        std::fs::write(
+            &path,
+            "# Triangle output\n4 2 1 1\n0 0 0 5 2\n1 1 0 6 2\n2 1 1 7 1\n3 0 1 8 1\n",
+        )
+        .unwrap();
+        std::fs::write(path.with_extension("ele"), "2 3 0\n0 0 1 2\n1 0 2 3\n").unwrap();
+        let parsed = parse_from_path_ref(&path.with_extension("ele")).unwrap();
+        assert_eq!(parsed.nodes()[&1], (vec![0.0, 0.0], Some(vec![5.0])));
+        assert_eq!(parsed.elements().unwrap()[&2], vec![1, 3, 4]);
+        assert_eq!(parsed.open_boundaries().unwrap(), vec![vec![1, 2]]);
+        assert_eq!(parsed.land_boundaries().unwrap(), vec![vec![2, 3, 4, 1]]);
+    }
+
+    #[test]
+    fn test_unknown_node_is_an_error() {
+        let gr3 = GR3.replace("8 3 5 9 8", "8 3 5 10 8");
+        let gr3 = gr3::parse_from_reader(BufReader::new(gr3.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+        let error = write_ele(&mut Vec::new(), &hgrid).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        let error = write_poly(&mut Vec::new(), &hgrid, &[(9, 10, LAND_MARKER)], &[]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_interior_point_of_concave_ring() {
+        // An L-shape whose leftmost vertex triangle would cross the notch
+        let ring = [
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (0.5, 1.0),
+            (0.5, 2.0),
+            (0.0, 2.0),
+        ];
+        let (x, y) = interior_point(&ring);
+        let in_foot = x > 0.0 && x < 2.0 && y > 0.0 && y < 1.0;
+        let in_stem = x > 0.0 && x < 0.5 && y > 0.0 && y < 2.0;
+        assert!(in_foot || in_stem, "({}, {}) is outside the ring", x, y);
+    }
+}
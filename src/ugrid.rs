@@ -0,0 +1,769 @@
+//! UGRID-conformant NetCDF import and export
+//!
+//! Meshes are written as NetCDF-3 classic files following the UGRID 1.0
+//! conventions, so they open directly in xugrid, QGIS (MDAL) and similar tools:
+//! a `mesh2d` topology variable, `mesh2d_node_x`/`mesh2d_node_y` coordinates,
+//! `mesh2d_face_nodes` padded with `_FillValue` for triangles in mixed meshes,
+//! `mesh2d_edge_nodes`, and the depth as the node variable `mesh2d_depth`
+//! (positive down) when the mesh has one. Meshes without elements are written
+//! without faces and edges, since NetCDF-3 has no empty fixed dimensions. The
+//! CRS string is kept on a `crs` grid mapping variable.
+//!
+//! Open, land and interior boundaries are stored as CF contiguous ragged arrays
+//! of 0-based node indices (e.g. `mesh2d_open_boundary_nodes` together with
+//! `mesh2d_open_boundary_node_count`). ADCIRC flow, barrier and culvert
+//! boundaries have no UGRID counterpart and are not written.
+
+use crate::compression::open_reader;
+use crate::gr3::{Gr3ParserOutput, Gr3ParserOutputBuilder, Gr3ParserOutputBuilderError};
+use crate::netcdf3::{NcAttribute, NcFile, NcValues, NcVariable, NetCdfError};
+use crate::Hgrid;
+use linked_hash_map::LinkedHashMap;
+use std::collections::hash_map::{Entry, HashMap};
+use std::io::{prelude::*, BufWriter};
+use std::path::Path;
+use tempfile::NamedTempFile;
+use thiserror::Error;
+
+const MESH: &str = "mesh2d";
+const FILL_VALUE: i32 = -1;
+
+/// Segments of one boundary kind, with their IBTYPE flags when stored
+type RaggedBoundaries = Option<(Vec<Vec<u32>>, Option<Vec<u8>>)>;
+
+#[derive(Error, Debug)]
+pub enum UgridError {
+    #[error("I/O error: {0}")]
+    IoError(String),
+
+    #[error("NetCDF error in {0}: {1}")]
+    NetCdfError(String, NetCdfError),
+
+    #[error("Invalid UGRID file {0}: {1}")]
+    InvalidMesh(String, String),
+
+    #[error(transparent)]
+    Gr3ParserOutputBuilderError(#[from] Gr3ParserOutputBuilderError),
+}
+
+impl Hgrid {
+    /// Write the mesh as a UGRID NetCDF file.
+    pub fn write_ugrid(&self, path: &Path) -> Result<(), UgridError> {
+        write_to_path(path, self)
+    }
+}
+
+fn attribute(name: &str, values: NcValues) -> NcAttribute {
+    NcAttribute {
+        name: name.to_string(),
+        values,
+    }
+}
+
+fn text(name: &str, value: &str) -> NcAttribute {
+    attribute(name, NcValues::text(value))
+}
+
+fn variable(
+    name: &str,
+    dims: Vec<usize>,
+    attributes: Vec<NcAttribute>,
+    values: NcValues,
+) -> NcVariable {
+    NcVariable {
+        name: name.to_string(),
+        dims,
+        attributes,
+        values,
+    }
+}
+
+/// Build the UGRID dataset for a mesh.
+pub(crate) fn to_netcdf(hgrid: &Hgrid) -> NcFile {
+    let nodes = hgrid.nodes();
+    let elements = hgrid.elements();
+    let node_index = |node_id: u32| nodes.index_of(node_id).map_or(FILL_VALUE, |i| i as i32);
+
+    let mut face_nodes = Vec::with_capacity(elements.len() * 4);
+    let mut edge_index = HashMap::new();
+    let mut edge_nodes = Vec::new();
+    for (_elem_id, element) in elements.iter() {
+        let indices: Vec<i32> = element.iter().map(|&node_id| node_index(node_id)).collect();
+        face_nodes.extend(&indices);
+        face_nodes.extend(std::iter::repeat_n(FILL_VALUE, 4 - indices.len()));
+        for (k, &a) in indices.iter().enumerate() {
+            let b = indices[(k + 1) % indices.len()];
+            let key = (a.min(b), a.max(b));
+            if let Entry::Vacant(entry) = edge_index.entry(key) {
+                entry.insert(edge_nodes.len() / 2);
+                edge_nodes.extend([a, b]);
+            }
+        }
+    }
+
+    // NetCDF-3 reserves zero-length dimensions for records, so a mesh without
+    // elements is written without faces and edges
+    let mut file = NcFile::default();
+    let n_nodes = file.add_dimension("mesh2d_nNodes", nodes.len());
+    let has_faces = !elements.is_empty();
+
+    file.attributes
+        .push(text("Conventions", "CF-1.8 UGRID-1.0"));
+    if let Some(description) = hgrid.description() {
+        file.attributes.push(text("title", description));
+    }
+
+    let mut topology_attributes = vec![
+        text("cf_role", "mesh_topology"),
+        text("long_name", "Topology data of 2D mesh"),
+        attribute("topology_dimension", NcValues::Int(vec![2])),
+        text("node_coordinates", "mesh2d_node_x mesh2d_node_y"),
+    ];
+    if has_faces {
+        topology_attributes.extend([
+            text("face_node_connectivity", "mesh2d_face_nodes"),
+            text("edge_node_connectivity", "mesh2d_edge_nodes"),
+            text("face_dimension", "mesh2d_nFaces"),
+            text("edge_dimension", "mesh2d_nEdges"),
+            text("max_face_nodes_dimension", "mesh2d_nMax_face_nodes"),
+        ]);
+    }
+    file.variables.push(variable(
+        MESH,
+        vec![],
+        topology_attributes,
+        NcValues::Int(vec![0]),
+    ));
+
+    let (x_name, y_name, units) = if hgrid.is_geographic() {
+        (
+            ("longitude", "degrees_east"),
+            ("latitude", "degrees_north"),
+            None,
+        )
+    } else {
+        (
+            ("projection_x_coordinate", "m"),
+            ("projection_y_coordinate", "m"),
+            Some("m"),
+        )
+    };
+    for (name, (standard_name, axis_units), values) in [
+        ("mesh2d_node_x", x_name, hgrid.x()),
+        ("mesh2d_node_y", y_name, hgrid.y()),
+    ] {
+        let mut attributes = vec![
+            text("standard_name", standard_name),
+            text("units", units.unwrap_or(axis_units)),
+            text("mesh", MESH),
+            text("location", "node"),
+        ];
+        if hgrid.crs().is_some() {
+            attributes.push(text("grid_mapping", "crs"));
+        }
+        file.variables.push(variable(
+            name,
+            vec![n_nodes],
+            attributes,
+            NcValues::Double(values.to_vec()),
+        ));
+    }
+
+    file.variables.push(variable(
+        "mesh2d_node_id",
+        vec![n_nodes],
+        vec![
+            text("long_name", "Original node ids"),
+            text("mesh", MESH),
+            text("location", "node"),
+        ],
+        NcValues::Int(nodes.ids().iter().map(|&id| id as i32).collect()),
+    ));
+    if has_faces {
+        let n_faces = file.add_dimension("mesh2d_nFaces", elements.len());
+        let n_max_face_nodes = file.add_dimension("mesh2d_nMax_face_nodes", 4);
+        let n_edges = file.add_dimension("mesh2d_nEdges", edge_nodes.len() / 2);
+        let two = file.add_dimension("Two", 2);
+        file.variables.push(variable(
+            "mesh2d_face_nodes",
+            vec![n_faces, n_max_face_nodes],
+            vec![
+                text("cf_role", "face_node_connectivity"),
+                text("long_name", "Vertex nodes of mesh faces (counterclockwise)"),
+                attribute("start_index", NcValues::Int(vec![0])),
+                attribute("_FillValue", NcValues::Int(vec![FILL_VALUE])),
+            ],
+            NcValues::Int(face_nodes),
+        ));
+        file.variables.push(variable(
+            "mesh2d_face_id",
+            vec![n_faces],
+            vec![
+                text("long_name", "Original element ids"),
+                text("mesh", MESH),
+                text("location", "face"),
+            ],
+            NcValues::Int(elements.ids().iter().map(|&id| id as i32).collect()),
+        ));
+        file.variables.push(variable(
+            "mesh2d_edge_nodes",
+            vec![n_edges, two],
+            vec![
+                text("cf_role", "edge_node_connectivity"),
+                text("long_name", "Start and end nodes of mesh edges"),
+                attribute("start_index", NcValues::Int(vec![0])),
+            ],
+            NcValues::Int(edge_nodes),
+        ));
+    }
+
+    if hgrid.has_depths() {
+        let mut depth_attributes = vec![
+            text("standard_name", "sea_floor_depth_below_geoid"),
+            text("units", "m"),
+            text("positive", "down"),
+            text("mesh", MESH),
+            text("location", "node"),
+        ];
+        if hgrid.crs().is_some() {
+            depth_attributes.push(text("grid_mapping", "crs"));
+        }
+        file.variables.push(variable(
+            "mesh2d_depth",
+            vec![n_nodes],
+            depth_attributes,
+            NcValues::Double(hgrid.depths_positive_down().to_vec()),
+        ));
+    }
+
+    if let Some(crs) = hgrid.crs() {
+        let mut attributes = vec![text("spatial_ref", crs)];
+        if hgrid.is_geographic() {
+            attributes.push(text("grid_mapping_name", "latitude_longitude"));
+        }
+        if let Some(code) = crs
+            .strip_prefix("EPSG:")
+            .and_then(|code| code.parse::<i32>().ok())
+        {
+            attributes.push(attribute("epsg", NcValues::Int(vec![code])));
+            attributes.push(text("EPSG_code", crs));
+        }
+        file.variables
+            .push(variable("crs", vec![], attributes, NcValues::Int(vec![0])));
+    }
+
+    if let Some(boundaries) = hgrid.boundaries() {
+        if let Some(open) = boundaries.open() {
+            add_ragged_boundaries(&mut file, "open", open.nodes_ids(), None, &node_index);
+        }
+        if let Some(land) = boundaries.land() {
+            add_ragged_boundaries(
+                &mut file,
+                "land",
+                land.nodes_ids(),
+                Some(land.ibtypes()),
+                &node_index,
+            );
+        }
+        if let Some(interior) = boundaries.interior() {
+            add_ragged_boundaries(
+                &mut file,
+                "interior",
+                interior.nodes_ids(),
+                Some(interior.ibtypes()),
+                &node_index,
+            );
+        }
+    }
+    file
+}
+
+fn add_ragged_boundaries(
+    file: &mut NcFile,
+    kind: &str,
+    segments: &[Vec<u32>],
+    ibtypes: Option<Vec<u8>>,
+    node_index: &dyn Fn(u32) -> i32,
+) {
+    let node_count: usize = segments.iter().map(Vec::len).sum();
+    // NetCDF-3 reserves zero-length dimensions for records
+    if segments.is_empty() || node_count == 0 {
+        return;
+    }
+    let sample_dimension = format!("mesh2d_n_{}_boundary_nodes", kind);
+    let n_segments = file.add_dimension(&format!("mesh2d_n_{}_boundaries", kind), segments.len());
+    let n_nodes = file.add_dimension(&sample_dimension, node_count);
+    file.variables.push(variable(
+        &format!("mesh2d_{}_boundary_nodes", kind),
+        vec![n_nodes],
+        vec![
+            text("long_name", &format!("Nodes of {} boundary segments", kind)),
+            attribute("start_index", NcValues::Int(vec![0])),
+        ],
+        NcValues::Int(
+            segments
+                .iter()
+                .flatten()
+                .map(|&node_id| node_index(node_id))
+                .collect(),
+        ),
+    ));
+    file.variables.push(variable(
+        &format!("mesh2d_{}_boundary_node_count", kind),
+        vec![n_segments],
+        vec![
+            text(
+                "long_name",
+                &format!("Number of nodes in each {} boundary segment", kind),
+            ),
+            text("sample_dimension", &sample_dimension),
+        ],
+        NcValues::Int(
+            segments
+                .iter()
+                .map(|segment| segment.len() as i32)
+                .collect(),
+        ),
+    ));
+    if let Some(ibtypes) = ibtypes {
+        file.variables.push(variable(
+            &format!("mesh2d_{}_boundary_ibtype", kind),
+            vec![n_segments],
+            vec![text(
+                "long_name",
+                &format!("IBTYPE flag of each {} boundary segment", kind),
+            )],
+            NcValues::Int(ibtypes.into_iter().map(i32::from).collect()),
+        ));
+    }
+}
+
+pub fn write_to_path(path: &Path, hgrid: &Hgrid) -> Result<(), UgridError> {
+    let fname = path.display().to_string();
+    if hgrid.nodes().is_empty() {
+        return Err(UgridError::InvalidMesh(
+            fname,
+            "mesh has no nodes".to_string(),
+        ));
+    }
+    let io_error =
+        |e: std::io::Error| UgridError::IoError(format!("Failed to write {}: {}", fname, e));
+    let mut tmpfile = NamedTempFile::new().map_err(io_error)?;
+    log::debug!("Will write to tmpfile: {:?}", tmpfile);
+    let mut writer = BufWriter::new(tmpfile.as_file_mut());
+    to_netcdf(hgrid)
+        .write(&mut writer)
+        .map_err(|e| UgridError::NetCdfError(fname.clone(), e))?;
+    writer.flush().map_err(io_error)?;
+    drop(writer);
+    tmpfile.persist(path).map_err(|e| io_error(e.error))?;
+    Ok(())
+}
+
+pub fn parse_from_path_ref(path: &Path) -> Result<Gr3ParserOutput, UgridError> {
+    let fname = &path.display().to_string();
+    let mut bytes = Vec::new();
+    open_reader(path)
+        .and_then(|mut reader| reader.read_to_end(&mut bytes))
+        .map_err(|e| UgridError::IoError(format!("Failed to open {}: {}", fname, e)))?;
+    let file = NcFile::read(&bytes).map_err(|e| UgridError::NetCdfError(fname.clone(), e))?;
+    from_netcdf(&file, fname)
+}
+
+/// Convert a UGRID dataset into parser output.
+///
+/// The first variable with `cf_role = "mesh_topology"` is used. Node and face ids
+/// come from `<mesh>_node_id`/`<mesh>_face_id` when present and are numbered from
+/// 1 otherwise. The depth is the node variable whose standard name or name says
+/// depth, bathymetry or altitude, flipped to positive down when `positive = "up"`.
+pub(crate) fn from_netcdf(file: &NcFile, fname: &str) -> Result<Gr3ParserOutput, UgridError> {
+    let invalid = |message: String| UgridError::InvalidMesh(fname.to_string(), message);
+    let mesh = file
+        .variables
+        .iter()
+        .find(|v| v.text_attribute("cf_role").as_deref() == Some("mesh_topology"))
+        .ok_or_else(|| invalid("no variable with cf_role = mesh_topology".to_string()))?;
+    let linked_variable = |attribute: &str| -> Result<&NcVariable, UgridError> {
+        let name = mesh
+            .text_attribute(attribute)
+            .ok_or_else(|| invalid(format!("{} has no {} attribute", mesh.name, attribute)))?;
+        file.variable(&name).ok_or_else(|| {
+            invalid(format!(
+                "{} references missing variable {}",
+                mesh.name, name
+            ))
+        })
+    };
+    let floats = |variable: &NcVariable| {
+        variable
+            .values
+            .to_f64()
+            .ok_or_else(|| invalid(format!("{} is not numeric", variable.name)))
+    };
+    let integers = |variable: &NcVariable| {
+        variable
+            .values
+            .to_i64()
+            .ok_or_else(|| invalid(format!("{} is not numeric", variable.name)))
+    };
+
+    let coordinates = mesh.text_attribute("node_coordinates").unwrap_or_default();
+    let coordinate_names: Vec<&str> = coordinates.split_whitespace().collect();
+    let [x_name, y_name] = coordinate_names[..] else {
+        return Err(invalid(format!(
+            "expected two node_coordinates but found {:?}",
+            coordinates
+        )));
+    };
+    let x_variable = file
+        .variable(x_name)
+        .ok_or_else(|| invalid(format!("missing node coordinate {}", x_name)))?;
+    let x = floats(x_variable)?;
+    let y = floats(
+        file.variable(y_name)
+            .ok_or_else(|| invalid(format!("missing node coordinate {}", y_name)))?,
+    )?;
+    let node_ids: Vec<u32> = match file.variable(&format!("{}_node_id", mesh.name)) {
+        Some(variable) => integers(variable)?
+            .into_iter()
+            .map(|id| id as u32)
+            .collect(),
+        None => (1..=x.len() as u32).collect(),
+    };
+    if y.len() != x.len() || node_ids.len() != x.len() {
+        return Err(invalid(format!(
+            "{} has {} values but {} has {} and there are {} node ids",
+            x_name,
+            x.len(),
+            y_name,
+            y.len(),
+            node_ids.len()
+        )));
+    }
+
+    let depth_variable = file.variables.iter().find(|v| {
+        v.values.len() == x.len()
+            && v.text_attribute("location").as_deref() == Some("node")
+            && is_depth_variable(v)
+    });
+    let depths = match depth_variable {
+        Some(variable) => {
+            let sign = match variable.text_attribute("positive").as_deref() {
+                Some("up") => -1.0,
+                Some(_) => 1.0,
+                None if variable.text_attribute("standard_name").as_deref() == Some("altitude") => {
+                    -1.0
+                }
+                None => 1.0,
+            };
+            Some(
+                floats(variable)?
+                    .into_iter()
+                    .map(|d| sign * d)
+                    .collect::<Vec<f64>>(),
+            )
+        }
+        None => None,
+    };
+    let nodes: LinkedHashMap<u32, (Vec<f64>, Option<Vec<f64>>)> = node_ids
+        .iter()
+        .enumerate()
+        .map(|(i, &node_id)| {
+            (
+                node_id,
+                (vec![x[i], y[i]], depths.as_ref().map(|d| vec![d[i]])),
+            )
+        })
+        .collect();
+
+    let index_to_id = |index: i64, start_index: i64| -> Result<u32, UgridError> {
+        node_ids
+            .get((index - start_index) as usize)
+            .copied()
+            .ok_or_else(|| invalid(format!("node index {} is out of range", index)))
+    };
+
+    // A mesh written without elements has no face connectivity
+    let mut elements = LinkedHashMap::new();
+    if mesh.attribute("face_node_connectivity").is_some() {
+        let faces = linked_variable("face_node_connectivity")?;
+        let start_index = faces
+            .attribute("start_index")
+            .and_then(NcValues::to_i64)
+            .and_then(|v| v.first().copied())
+            .unwrap_or(0);
+        let fill_value = faces
+            .attribute("_FillValue")
+            .and_then(NcValues::to_i64)
+            .and_then(|v| v.first().copied());
+        let max_face_nodes = faces
+            .dims
+            .get(1)
+            .map(|&dim| file.dimensions[dim].len)
+            .filter(|&len| len > 0)
+            .ok_or_else(|| invalid(format!("{} is not two-dimensional", faces.name)))?;
+        let connectivity = integers(faces)?;
+        let face_count = connectivity.len() / max_face_nodes;
+        let face_ids: Vec<u32> = match file.variable(&format!("{}_face_id", mesh.name)) {
+            Some(variable) => integers(variable)?
+                .into_iter()
+                .map(|id| id as u32)
+                .collect(),
+            None => (1..=face_count as u32).collect(),
+        };
+        if face_ids.len() != face_count || connectivity.len() % max_face_nodes != 0 {
+            return Err(invalid(format!(
+                "{} has {} values for {} faces of {} nodes",
+                faces.name,
+                connectivity.len(),
+                face_ids.len(),
+                max_face_nodes
+            )));
+        }
+        for (face, &elem_id) in connectivity.chunks(max_face_nodes).zip(&face_ids) {
+            let element = face
+                .iter()
+                .filter(|&&index| Some(index) != fill_value && index >= start_index)
+                .map(|&index| index_to_id(index, start_index))
+                .collect::<Result<Vec<u32>, UgridError>>()?;
+            elements.insert(elem_id, element);
+        }
+    }
+
+    let crs = x_variable
+        .text_attribute("grid_mapping")
+        .and_then(|name| file.variable(&name))
+        .and_then(|grid_mapping| {
+            ["spatial_ref", "crs_wkt", "EPSG_code"]
+                .iter()
+                .find_map(|name| grid_mapping.text_attribute(name))
+                .or_else(|| {
+                    grid_mapping
+                        .attribute("epsg")
+                        .and_then(NcValues::to_i64)
+                        .and_then(|code| code.first().map(|code| format!("EPSG:{}", code)))
+                })
+        });
+
+    let ragged = |kind: &str| -> Result<RaggedBoundaries, UgridError> {
+        let (Some(nodes), Some(counts)) = (
+            file.variable(&format!("{}_{}_boundary_nodes", mesh.name, kind)),
+            file.variable(&format!("{}_{}_boundary_node_count", mesh.name, kind)),
+        ) else {
+            return Ok(None);
+        };
+        let start_index = nodes
+            .attribute("start_index")
+            .and_then(NcValues::to_i64)
+            .and_then(|v| v.first().copied())
+            .unwrap_or(0);
+        let mut indices = integers(nodes)?.into_iter();
+        let mut segments = Vec::new();
+        for count in integers(counts)? {
+            let segment = indices
+                .by_ref()
+                .take(count as usize)
+                .map(|index| index_to_id(index, start_index))
+                .collect::<Result<Vec<u32>, UgridError>>()?;
+            segments.push(segment);
+        }
+        let ibtypes = match file.variable(&format!("{}_{}_boundary_ibtype", mesh.name, kind)) {
+            Some(variable) => Some(integers(variable)?.into_iter().map(|t| t as u8).collect()),
+            None => None,
+        };
+        Ok(Some((segments, ibtypes)))
+    };
+
+    let mut builder = Gr3ParserOutputBuilder::default();
+    builder
+        .description(file.global_text_attribute("title"))
+        .crs(crs)
+        .nodes(nodes)
+        .elements(Some(elements));
+    match ragged("open")? {
+        Some((segments, _)) => builder.open_boundaries(segments),
+        None => builder.open_boundaries(Vec::new()),
+    };
+    match ragged("land")? {
+        Some((segments, ibtypes)) => builder
+            .land_boundaries(segments)
+            .land_boundaries_ibtypes(ibtypes),
+        None => builder.land_boundaries(Vec::new()),
+    };
+    match ragged("interior")? {
+        Some((segments, ibtypes)) => builder
+            .interior_boundaries(segments)
+            .interior_boundaries_ibtypes(ibtypes),
+        None => builder.interior_boundaries(Vec::new()),
+    };
+    Ok(builder.build()?)
+}
+
+fn is_depth_variable(variable: &NcVariable) -> bool {
+    let standard_name = variable.text_attribute("standard_name").unwrap_or_default();
+    let name = variable.name.to_lowercase();
+    matches!(
+        standard_name.as_str(),
+        "sea_floor_depth_below_geoid"
+            | "sea_floor_depth"
+            | "sea_floor_depth_below_sea_surface"
+            | "altitude"
+    ) || name.ends_with("depth")
+        || name.ends_with("bathymetry")
+        || name.ends_with("_node_z")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gr3;
+    use std::io::BufReader;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    // A quad and a triangle sharing the edge 2-5, with non-contiguous ids
+    const GR3: &str = "EPSG:32619
+2 5
+1 0 0 5
+2 1 0 6
+5 1 1 7
+4 0 1 8
+7 2 0.5 9
+10 4 1 2 5 4
+20 3 2 7 5
+1 = Number of open boundaries
+2 = Total number of open boundary nodes
+2 = Number of nodes for open boundary 1
+2
+7
+1 = number of land boundaries
+4 = Total number of land boundary nodes
+4 0 = Number of nodes for land boundary 1
+5
+4
+1
+2";
+
+    #[test]
+    fn test_ugrid_round_trip() {
+        let gr3 = gr3::parse_from_reader(BufReader::new(GR3.as_bytes()), "test").unwrap();
+        let hgrid = Hgrid::try_from(gr3).unwrap();
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.nc");
+        hgrid.write_ugrid(&path).unwrap();
+
+        let file = NcFile::read(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(
+            file.variable("mesh2d_face_nodes").unwrap().values,
+            NcValues::Int(vec![0, 1, 2, 3, 1, 4, 2, -1])
+        );
+        let edges = file.variable("mesh2d_edge_nodes").unwrap();
+        assert_eq!(edges.values.len(), 2 * 6);
+        assert_eq!(
+            file.variable("crs")
+                .unwrap()
+                .text_attribute("spatial_ref")
+                .as_deref(),
+            Some("EPSG:32619")
+        );
+
+        let reloaded = Hgrid::try_from(&PathBuf::from(&path)).unwrap();
+        assert_eq!(reloaded.calculate_hash(), hgrid.calculate_hash());
+        assert_eq!(reloaded.crs(), Some("EPSG:32619"));
+        assert_eq!(reloaded.depths(), hgrid.depths());
+        let boundaries = reloaded.boundaries().unwrap();
+        assert_eq!(boundaries.open().unwrap().nodes_ids(), &vec![vec![2, 7]]);
+        assert_eq!(
+            boundaries.land().unwrap().nodes_ids(),
+            &vec![vec![5, 4, 1, 2]]
+        );
+    }
+
+    #[test]
+    fn test_depthless_mesh_round_trip() {
+        use crate::elements::ElementsBuilder;
+        use crate::hgrid::HgridBuilder;
+        use crate::nodes::NodesBuilder;
+        use std::sync::Arc;
+
+        let mut nodes = LinkedHashMap::new();
+        nodes.insert(1, (vec![0.0, 0.0], None));
+        nodes.insert(2, (vec![1.0, 0.0], None));
+        nodes.insert(3, (vec![0.0, 1.0], None));
+        nodes.insert(4, (vec![1.0, 1.0], None));
+        let nodes = NodesBuilder::default()
+            .hash_map(nodes)
+            .crs(None::<String>)
+            .build()
+            .map(Arc::new)
+            .unwrap();
+        let hgrid_with = |elements: LinkedHashMap<u32, Vec<u32>>| {
+            HgridBuilder::default()
+                .nodes(nodes.clone())
+                .elements(
+                    ElementsBuilder::default()
+                        .hash_map(elements)
+                        .nodes(nodes.clone())
+                        .build()
+                        .unwrap(),
+                )
+                .boundaries(None)
+                .description(None::<String>)
+                .build()
+                .unwrap()
+        };
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hgrid.nc");
+
+        let hgrid = hgrid_with(LinkedHashMap::from_iter([(1, vec![1, 2, 4, 3])]));
+        assert!(!hgrid.has_depths());
+        hgrid.write_ugrid(&path).unwrap();
+        let file = NcFile::read(&std::fs::read(&path).unwrap()).unwrap();
+        assert!(file.variable("mesh2d_depth").is_none());
+        let reloaded = Hgrid::try_from(&PathBuf::from(&path)).unwrap();
+        assert!(!reloaded.has_depths());
+        assert_eq!(reloaded.elements().get(1), Some(&[1, 2, 4, 3][..]));
+
+        // Nodes only, e.g. a Triangle .node file
+        let hgrid = hgrid_with(LinkedHashMap::new());
+        hgrid.write_ugrid(&path).unwrap();
+        let reloaded = Hgrid::try_from(&PathBuf::from(&path)).unwrap();
+        assert_eq!(reloaded.nodes().ids(), &[1, 2, 3, 4]);
+        assert!(reloaded.elements().is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_lengths_are_rejected() {
+        let gr3 = gr3::parse_from_reader(BufReader::new(GR3.as_bytes()), "test").unwrap();
+        let file = to_netcdf(&Hgrid::try_from(gr3).unwrap());
+        let shortened = |name: &str| {
+            let mut file = file.clone();
+            let variable = file.variables.iter_mut().find(|v| v.name == name).unwrap();
+            variable.values = match &variable.values {
+                NcValues::Double(values) => NcValues::Double(values[1..].to_vec()),
+                NcValues::Int(values) => NcValues::Int(values[1..].to_vec()),
+                other => panic!("unexpected values {:?}", other),
+            };
+            file
+        };
+        for name in ["mesh2d_node_y", "mesh2d_node_id", "mesh2d_face_id"] {
+            assert!(
+                matches!(
+                    from_netcdf(&shortened(name), "test.nc"),
+                    Err(UgridError::InvalidMesh(_, _))
+                ),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_topology_is_rejected() {
+        let file = NcFile::default();
+        assert!(matches!(
+            from_netcdf(&file, "empty.nc"),
+            Err(UgridError::InvalidMesh(_, _))
+        ));
+    }
+}
@@ -2,16 +2,20 @@
 //!
 //! Provides comprehensive validation checks for unstructured meshes:
 //! - Structural checks (node references, boundary references)
-//! - Geometric checks (element areas, orientation, concavity)
+//! - Geometric checks (element areas, orientation, concavity, coincident nodes)
+//! - Topological checks (disconnected pieces, non-manifold sides, bow-tie
+//!   and hanging nodes)
 
+use crate::components::largest_component;
 use crate::Hgrid;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Result of mesh validation containing all detected issues.
 ///
 /// Use `is_ok()` for a quick pass/fail check, or inspect individual
 /// fields for detailed information about specific issues.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MeshValidation {
     // Structural issues
     /// Element IDs that reference non-existent node IDs
@@ -22,6 +26,14 @@ pub struct MeshValidation {
     pub invalid_land_boundary_refs: Vec<u32>,
     /// Interior boundary segments with node IDs not in the mesh
     pub invalid_interior_boundary_refs: Vec<u32>,
+    /// Flow boundary segments with node IDs not in the mesh
+    pub invalid_flow_boundary_refs: Vec<u32>,
+    /// External barrier segments with node IDs not in the mesh
+    pub invalid_external_barrier_refs: Vec<u32>,
+    /// Internal barrier segments with front or back node IDs not in the mesh
+    pub invalid_internal_barrier_refs: Vec<u32>,
+    /// Culvert segments with front or back node IDs not in the mesh
+    pub invalid_culvert_refs: Vec<u32>,
 
     // Geometric issues
     /// Elements with negative signed area (clockwise winding, should be counter-clockwise)
@@ -32,6 +44,18 @@ pub struct MeshValidation {
     pub concave_quads: Vec<u32>,
     /// Pairs of adjacent elements with inconsistent edge orientation
     pub orientation_conflicts: Vec<(u32, u32)>,
+    /// Groups of node IDs closer to each other than [`COINCIDENT_NODE_TOL`]
+    pub coincident_nodes: Vec<Vec<u32>>,
+
+    // Topological issues
+    /// Element IDs of each connected component other than the largest one
+    pub disconnected_components: Vec<Vec<u32>>,
+    /// Sides shared by more than two elements, as node ID pairs (smaller first)
+    pub non_manifold_edges: Vec<(u32, u32)>,
+    /// Nodes where the boundary touches itself (bow-tie or hourglass)
+    pub pinched_nodes: Vec<u32>,
+    /// Nodes lying on a side of elements that do not use them (T-junctions)
+    pub hanging_nodes: Vec<u32>,
 }
 
 impl MeshValidation {
@@ -41,10 +65,19 @@ impl MeshValidation {
             && self.invalid_open_boundary_refs.is_empty()
             && self.invalid_land_boundary_refs.is_empty()
             && self.invalid_interior_boundary_refs.is_empty()
+            && self.invalid_flow_boundary_refs.is_empty()
+            && self.invalid_external_barrier_refs.is_empty()
+            && self.invalid_internal_barrier_refs.is_empty()
+            && self.invalid_culvert_refs.is_empty()
             && self.negative_area_elements.is_empty()
             && self.zero_area_elements.is_empty()
             && self.concave_quads.is_empty()
             && self.orientation_conflicts.is_empty()
+            && self.coincident_nodes.is_empty()
+            && self.disconnected_components.is_empty()
+            && self.non_manifold_edges.is_empty()
+            && self.pinched_nodes.is_empty()
+            && self.hanging_nodes.is_empty()
     }
 
     /// Returns true if structural checks passed (ignoring geometric issues).
@@ -53,6 +86,10 @@ impl MeshValidation {
             && self.invalid_open_boundary_refs.is_empty()
             && self.invalid_land_boundary_refs.is_empty()
             && self.invalid_interior_boundary_refs.is_empty()
+            && self.invalid_flow_boundary_refs.is_empty()
+            && self.invalid_external_barrier_refs.is_empty()
+            && self.invalid_internal_barrier_refs.is_empty()
+            && self.invalid_culvert_refs.is_empty()
     }
 
     /// Returns true if geometric checks passed (ignoring structural issues).
@@ -61,6 +98,15 @@ impl MeshValidation {
             && self.zero_area_elements.is_empty()
             && self.concave_quads.is_empty()
             && self.orientation_conflicts.is_empty()
+            && self.coincident_nodes.is_empty()
+    }
+
+    /// Returns true if topological checks passed.
+    pub fn is_topologically_valid(&self) -> bool {
+        self.disconnected_components.is_empty()
+            && self.non_manifold_edges.is_empty()
+            && self.pinched_nodes.is_empty()
+            && self.hanging_nodes.is_empty()
     }
 
     /// Total count of all issues found.
@@ -69,10 +115,19 @@ impl MeshValidation {
             + self.invalid_open_boundary_refs.len()
             + self.invalid_land_boundary_refs.len()
             + self.invalid_interior_boundary_refs.len()
+            + self.invalid_flow_boundary_refs.len()
+            + self.invalid_external_barrier_refs.len()
+            + self.invalid_internal_barrier_refs.len()
+            + self.invalid_culvert_refs.len()
             + self.negative_area_elements.len()
             + self.zero_area_elements.len()
             + self.concave_quads.len()
             + self.orientation_conflicts.len()
+            + self.coincident_nodes.len()
+            + self.disconnected_components.len()
+            + self.non_manifold_edges.len()
+            + self.pinched_nodes.len()
+            + self.hanging_nodes.len()
     }
 }
 
@@ -94,6 +149,18 @@ impl std::fmt::Display for MeshValidation {
             if !self.invalid_interior_boundary_refs.is_empty() {
                 writeln!(f, "  - {} interior boundaries with invalid node refs", self.invalid_interior_boundary_refs.len())?;
             }
+            if !self.invalid_flow_boundary_refs.is_empty() {
+                writeln!(f, "  - {} flow boundaries with invalid node refs", self.invalid_flow_boundary_refs.len())?;
+            }
+            if !self.invalid_external_barrier_refs.is_empty() {
+                writeln!(f, "  - {} external barriers with invalid node refs", self.invalid_external_barrier_refs.len())?;
+            }
+            if !self.invalid_internal_barrier_refs.is_empty() {
+                writeln!(f, "  - {} internal barriers with invalid node refs", self.invalid_internal_barrier_refs.len())?;
+            }
+            if !self.invalid_culvert_refs.is_empty() {
+                writeln!(f, "  - {} culverts with invalid node refs", self.invalid_culvert_refs.len())?;
+            }
             if !self.negative_area_elements.is_empty() {
                 writeln!(f, "  - {} elements with negative area", self.negative_area_elements.len())?;
             }
@@ -106,6 +173,21 @@ impl std::fmt::Display for MeshValidation {
             if !self.orientation_conflicts.is_empty() {
                 writeln!(f, "  - {} orientation conflicts", self.orientation_conflicts.len())?;
             }
+            if !self.coincident_nodes.is_empty() {
+                writeln!(f, "  - {} groups of coincident nodes", self.coincident_nodes.len())?;
+            }
+            if !self.disconnected_components.is_empty() {
+                writeln!(f, "  - {} components disconnected from the main mesh", self.disconnected_components.len())?;
+            }
+            if !self.non_manifold_edges.is_empty() {
+                writeln!(f, "  - {} sides shared by more than two elements", self.non_manifold_edges.len())?;
+            }
+            if !self.pinched_nodes.is_empty() {
+                writeln!(f, "  - {} pinched (bow-tie) nodes", self.pinched_nodes.len())?;
+            }
+            if !self.hanging_nodes.is_empty() {
+                writeln!(f, "  - {} hanging (T-junction) nodes", self.hanging_nodes.len())?;
+            }
             Ok(())
         }
     }
@@ -114,6 +196,12 @@ impl std::fmt::Display for MeshValidation {
 /// Tolerance for zero-area detection
 const AREA_TOL: f64 = 1e-10;
 
+/// Distance in metres below which nodes are reported as coincident
+pub const COINCIDENT_NODE_TOL: f64 = 1e-3;
+
+/// Distance from a side, relative to its length, within which a node lies on it
+const HANGING_NODE_TOL: f64 = 1e-6;
+
 impl Hgrid {
     /// Perform comprehensive mesh validation.
     ///
@@ -132,7 +220,7 @@ impl Hgrid {
         let mut result = MeshValidation::default();
 
         // Build node ID set once
-        let node_ids: HashSet<u32> = self.nodes().hash_map().keys().copied().collect();
+        let node_ids: HashSet<u32> = self.nodes().ids().iter().copied().collect();
 
         // Check element node references
         self.check_element_refs(&node_ids, &mut result);
@@ -146,19 +234,29 @@ impl Hgrid {
         // Check orientation consistency
         self.check_orientation_consistency(&mut result);
 
+        // Check for duplicate nodes, e.g. left over from stitching meshes
+        result.coincident_nodes = self.find_coincident_nodes(COINCIDENT_NODE_TOL);
+
+        // Check for pieces disconnected from the main mesh
+        self.check_connectivity(&mut result);
+
+        // Check for sides and nodes SCHISM cannot handle
+        let split_sides = self.check_hanging_nodes(&mut result);
+        self.check_manifold(&split_sides, &mut result);
+
         result
     }
 
     /// Check that all element node IDs reference existing nodes.
     fn check_element_refs(&self, node_ids: &HashSet<u32>, result: &mut MeshValidation) {
-        for (elem_id, elem_nodes) in self.elements().hash_map().iter() {
+        for (elem_id, elem_nodes) in self.elements().iter() {
             let invalid: Vec<u32> = elem_nodes
                 .iter()
                 .filter(|n| !node_ids.contains(n))
                 .copied()
                 .collect();
             if !invalid.is_empty() {
-                result.invalid_element_node_refs.push((*elem_id, invalid));
+                result.invalid_element_node_refs.push((elem_id, invalid));
             }
         }
     }
@@ -193,19 +291,60 @@ impl Hgrid {
                     }
                 }
             }
+
+            // Check flow boundaries
+            if let Some(flow_ids) = type_map.get(&crate::boundaries::BoundaryType::Flow) {
+                for (idx, segment) in flow_ids.iter().enumerate() {
+                    if segment.iter().any(|n| !node_ids.contains(n)) {
+                        result.invalid_flow_boundary_refs.push(idx as u32);
+                    }
+                }
+            }
+
+            // Check barriers, including the back face of weir pairs
+            if let Some(external) = boundaries.external_barrier() {
+                for (idx, segment) in external.segments().iter().enumerate() {
+                    if segment.nodes_ids.iter().any(|n| !node_ids.contains(n)) {
+                        result.invalid_external_barrier_refs.push(idx as u32);
+                    }
+                }
+            }
+            if let Some(internal) = boundaries.internal_barrier() {
+                for (idx, segment) in internal.segments().iter().enumerate() {
+                    if segment
+                        .nodes_ids
+                        .iter()
+                        .chain(&segment.paired_nodes_ids)
+                        .any(|n| !node_ids.contains(n))
+                    {
+                        result.invalid_internal_barrier_refs.push(idx as u32);
+                    }
+                }
+            }
+            if let Some(culvert) = boundaries.culvert() {
+                for (idx, segment) in culvert.segments().iter().enumerate() {
+                    if segment
+                        .nodes_ids
+                        .iter()
+                        .chain(&segment.paired_nodes_ids)
+                        .any(|n| !node_ids.contains(n))
+                    {
+                        result.invalid_culvert_refs.push(idx as u32);
+                    }
+                }
+            }
         }
     }
 
     /// Check element geometry: areas and concavity.
     fn check_element_geometry(&self, result: &mut MeshValidation) {
-        let nodes_map = self.nodes().hash_map();
+        let nodes = self.nodes();
 
-        for (elem_id, elem_nodes) in self.elements().hash_map().iter() {
+        for (elem_id, elem_nodes) in self.elements().iter() {
             // Get coordinates
             let coords: Vec<(f64, f64)> = elem_nodes
                 .iter()
-                .filter_map(|n| nodes_map.get(n))
-                .map(|(coord, _)| (coord[0], coord[1]))
+                .filter_map(|n| nodes.get_node(*n))
                 .collect();
 
             if coords.len() != elem_nodes.len() {
@@ -217,9 +356,9 @@ impl Hgrid {
                 // Triangle
                 let area = signed_triangle_area(coords[0], coords[1], coords[2]);
                 if area < -AREA_TOL {
-                    result.negative_area_elements.push(*elem_id);
+                    result.negative_area_elements.push(elem_id);
                 } else if area.abs() <= AREA_TOL {
-                    result.zero_area_elements.push(*elem_id);
+                    result.zero_area_elements.push(elem_id);
                 }
             } else if coords.len() == 4 {
                 // Quad - check both triangulations
@@ -228,9 +367,9 @@ impl Hgrid {
                 let total_area = area1 + area2;
 
                 if total_area < -AREA_TOL {
-                    result.negative_area_elements.push(*elem_id);
+                    result.negative_area_elements.push(elem_id);
                 } else if total_area.abs() <= AREA_TOL {
-                    result.zero_area_elements.push(*elem_id);
+                    result.zero_area_elements.push(elem_id);
                 }
 
                 // Check for concavity - all 4 sub-triangles should have same sign
@@ -240,45 +379,173 @@ impl Hgrid {
                 let min_area = areas.iter().cloned().fold(f64::INFINITY, f64::min);
 
                 if min_area <= -AREA_TOL {
-                    result.concave_quads.push(*elem_id);
+                    result.concave_quads.push(elem_id);
                 }
             }
         }
     }
 
     /// Check for orientation conflicts between adjacent elements.
+    ///
+    /// Adjacent elements should traverse their shared side in opposite
+    /// directions; the side's `left` element defines its direction.
     fn check_orientation_consistency(&self, result: &mut MeshValidation) {
-        use std::collections::HashMap;
-
-        // Build edge -> (element_id, is_forward) map
-        // An edge (a,b) stored as-is is "forward", stored as (b,a) is "backward"
-        // Adjacent elements should have opposite edge directions
-        let mut edge_map: HashMap<(u32, u32), (u32, bool)> = HashMap::new();
-
-        for (elem_id, elem_nodes) in self.elements().hash_map().iter() {
-            let n = elem_nodes.len();
-            for i in 0..n {
-                let a = elem_nodes[i];
-                let b = elem_nodes[(i + 1) % n];
-
-                // Canonical edge representation (smaller id first)
-                let canonical = if a < b { (a, b) } else { (b, a) };
-                let is_forward = a < b;
-
-                if let Some(&(other_elem, other_forward)) = edge_map.get(&canonical) {
-                    // Edge already seen - check if orientations are opposite
-                    if is_forward == other_forward {
-                        // Same direction = orientation conflict
-                        result.orientation_conflicts.push((other_elem, *elem_id));
-                    }
-                } else {
-                    edge_map.insert(canonical, (*elem_id, is_forward));
+        let elements = self.elements();
+        let ids = elements.ids();
+        let rows: Vec<&[u32]> = elements.iter().map(|(_, nodes)| nodes).collect();
+        for side in self.topology().sides() {
+            let Some(right) = side.right else {
+                continue;
+            };
+            let row = rows[right];
+            let n = row.len();
+            if (0..n).any(|i| (row[i], row[(i + 1) % n]) == side.nodes) {
+                result
+                    .orientation_conflicts
+                    .push((ids[side.left], ids[right]));
+            }
+        }
+    }
+
+    /// Check that every element belongs to the largest connected component.
+    fn check_connectivity(&self, result: &mut MeshValidation) {
+        let components = self.connected_components();
+        let Some(largest) = largest_component(&components) else {
+            return;
+        };
+        let count = components.iter().max().map_or(0, |&max| max + 1);
+        let mut disconnected = vec![Vec::new(); count];
+        for (&component, &elem_id) in components.iter().zip(self.elements().ids()) {
+            if component != largest {
+                disconnected[component].push(elem_id);
+            }
+        }
+        result.disconnected_components = disconnected
+            .into_iter()
+            .filter(|component| !component.is_empty())
+            .collect();
+    }
+
+    /// Check for sides with more than two elements and pinched boundary nodes.
+    ///
+    /// The side table pairs the first two elements of a side and gives every
+    /// further element a boundary side of its own, so the element count of a
+    /// side is summed over all sides with the same nodes. Sides taking part in
+    /// a T-junction are already reported as hanging nodes and are skipped when
+    /// looking for pinches.
+    fn check_manifold(&self, split_sides: &HashSet<(u32, u32)>, result: &mut MeshValidation) {
+        let topology = self.topology();
+        let mut incidence: HashMap<(u32, u32), usize> = HashMap::new();
+        for side in topology.sides() {
+            *incidence.entry(edge_key(side.nodes)).or_default() +=
+                1 + side.right.is_some() as usize;
+        }
+        result.non_manifold_edges = incidence
+            .iter()
+            .filter(|(_, &count)| count > 2)
+            .map(|(&edge, _)| edge)
+            .collect();
+        result.non_manifold_edges.sort_unstable();
+
+        // A boundary node normally has exactly two boundary sides; more means
+        // several fans of elements meet at the node only
+        let mut boundary_degree: HashMap<u32, usize> = HashMap::new();
+        for side in topology.boundary_sides() {
+            let key = edge_key(side.nodes);
+            if incidence[&key] <= 2 && !split_sides.contains(&key) {
+                *boundary_degree.entry(side.nodes.0).or_default() += 1;
+                *boundary_degree.entry(side.nodes.1).or_default() += 1;
+            }
+        }
+        result.pinched_nodes = boundary_degree
+            .into_iter()
+            .filter(|&(_, degree)| degree > 2)
+            .map(|(node_id, _)| node_id)
+            .collect();
+        result.pinched_nodes.sort_unstable();
+    }
+
+    /// Check for nodes lying inside a side they are not a vertex of.
+    ///
+    /// Both the long side and the short sides of a T-junction are unpaired,
+    /// so only boundary sides and their nodes need to be compared. Returns
+    /// the sides making up each T-junction.
+    fn check_hanging_nodes(&self, result: &mut MeshValidation) -> HashSet<(u32, u32)> {
+        let nodes = self.nodes();
+        let sides: Vec<(u32, u32)> = self
+            .topology()
+            .boundary_sides()
+            .map(|side| side.nodes)
+            .collect();
+        let mut candidates: Vec<(f64, f64, u32)> = sides
+            .iter()
+            .flat_map(|&(a, b)| [a, b])
+            .collect::<HashSet<u32>>()
+            .into_iter()
+            .filter_map(|node_id| nodes.get_node(node_id).map(|(x, y)| (x, y, node_id)))
+            .collect();
+        candidates.sort_unstable_by(|p, q| p.0.total_cmp(&q.0));
+
+        // Nodes of each split side, so that the short sides can be found later
+        let mut split_nodes: HashMap<u32, Vec<usize>> = HashMap::new();
+        let mut split_count = 0;
+        let mut hanging = HashSet::new();
+        for &(a, b) in &sides {
+            let (Some(pa), Some(pb)) = (nodes.get_node(a), nodes.get_node(b)) else {
+                continue;
+            };
+            let (dx, dy) = (pb.0 - pa.0, pb.1 - pa.1);
+            let length = dx.hypot(dy);
+            if length == 0.0 {
+                continue;
+            }
+            let tol = HANGING_NODE_TOL * length;
+            let start = candidates.partition_point(|c| c.0 < pa.0.min(pb.0) - tol);
+            let mut split = false;
+            for &(x, y, node_id) in &candidates[start..] {
+                if x > pa.0.max(pb.0) + tol {
+                    break;
+                }
+                if node_id == a || node_id == b {
+                    continue;
                 }
+                // Position along the side and distance from it
+                let (px, py) = (x - pa.0, y - pa.1);
+                let t = (px * dx + py * dy) / (length * length);
+                let distance = (px * dy - py * dx).abs() / length;
+                if t > HANGING_NODE_TOL && t < 1.0 - HANGING_NODE_TOL && distance <= tol {
+                    hanging.insert(node_id);
+                    split_nodes.entry(node_id).or_default().push(split_count);
+                    split = true;
+                }
+            }
+            if split {
+                split_nodes.entry(a).or_default().push(split_count);
+                split_nodes.entry(b).or_default().push(split_count);
+                split_count += 1;
             }
         }
+
+        let mut split_sides = HashSet::new();
+        for &(a, b) in &sides {
+            if let (Some(at_a), Some(at_b)) = (split_nodes.get(&a), split_nodes.get(&b)) {
+                if at_a.iter().any(|split| at_b.contains(split)) {
+                    split_sides.insert(edge_key((a, b)));
+                }
+            }
+        }
+        result.hanging_nodes = hanging.into_iter().collect();
+        result.hanging_nodes.sort_unstable();
+        split_sides
     }
 }
 
+/// Side key independent of the direction the side is traversed in.
+#[inline]
+fn edge_key((a, b): (u32, u32)) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
 /// Compute signed area of a triangle using the shoelace formula.
 /// Positive = counter-clockwise, negative = clockwise.
 #[inline]
@@ -290,6 +557,7 @@ fn signed_triangle_area(v1: (f64, f64), v2: (f64, f64), v3: (f64, f64)) -> f64 {
 mod tests {
     use super::*;
     use crate::elements::ElementsBuilder;
+    use crate::fixtures::hgrid_from_gr3;
     use crate::hgrid::HgridBuilder;
     use crate::nodes::NodesBuilder;
     use linked_hash_map::LinkedHashMap;
@@ -383,4 +651,43 @@ mod tests {
         let display = format!("{}", validation);
         assert!(display.contains("OK"));
     }
+
+    #[test]
+    fn test_non_manifold_edge_detection() {
+        // Element 3 folds back over element 1 across side 1-2
+        let hgrid = hgrid_from_gr3(
+            "non-manifold\n3 5\n1 0 0 1\n2 1 0 1\n3 0.5 1 1\n4 0.5 -1 1\n5 0.5 2 1\n\
+             1 3 1 2 3\n2 3 2 1 4\n3 3 1 2 5\n",
+        );
+        let validation = hgrid.check_validity();
+        assert_eq!(validation.non_manifold_edges, vec![(1, 2)]);
+        assert!(validation.orientation_conflicts.is_empty());
+        assert!(!validation.is_topologically_valid());
+        assert!(validation
+            .to_string()
+            .contains("1 sides shared by more than two elements"));
+    }
+
+    #[test]
+    fn test_pinched_and_hanging_nodes() {
+        // Two triangles touching at node 3 only
+        let bow_tie = hgrid_from_gr3(
+            "bow-tie\n2 5\n1 0 0 1\n2 1 0 1\n3 1 1 1\n4 2 1 1\n5 2 2 1\n\
+             1 3 1 2 3\n2 3 3 4 5\n",
+        );
+        let validation = bow_tie.check_validity();
+        assert_eq!(validation.pinched_nodes, vec![3]);
+        assert!(validation.hanging_nodes.is_empty());
+
+        // Node 5 splits the right side of element 1 between elements 2 and 3
+        let t_junction = hgrid_from_gr3(
+            "t-junction\n3 5\n1 0 0 1\n2 0 2 1\n3 -1 1 1\n4 1 1 1\n5 0 1 1\n\
+             1 3 1 2 3\n2 3 1 4 5\n3 3 5 4 2\n",
+        );
+        let validation = t_junction.check_validity();
+        assert_eq!(validation.hanging_nodes, vec![5]);
+        assert!(validation.pinched_nodes.is_empty());
+        assert!(validation.non_manifold_edges.is_empty());
+        assert!(validation.is_geometrically_valid());
+    }
 }
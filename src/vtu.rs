@@ -0,0 +1,339 @@
+//! VTK XML unstructured grid (`.vtu`) export for ParaView
+//!
+//! Elements are written as `VTK_TRIANGLE`/`VTK_QUAD` cells on points at z = 0.
+//! Point data holds every node attribute (depth positive down, multi-column
+//! attributes as vectors), the node ids and the number of elements connected to
+//! each node. Cell data holds the element ids and every element attribute, so
+//! quality metrics or validation flags show up once stored with
+//! [`Hgrid::add_element_attribute`].
+
+use crate::nodes::{NodeAttribute, DEPTH_ATTRIBUTE};
+use crate::{DepthConvention, Hgrid};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io::{prelude::*, BufWriter};
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+const VTK_TRIANGLE: u8 = 5;
+const VTK_QUAD: u8 = 9;
+
+/// How `DataArray` values are stored in the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VtuEncoding {
+    /// Human-readable text
+    #[default]
+    Ascii,
+    /// Inline little-endian binary, base64 encoded (much smaller for large grids)
+    Base64,
+}
+
+enum DataValues {
+    Float64(Vec<f64>),
+    Int64(Vec<i64>),
+    UInt8(Vec<u8>),
+}
+
+impl DataValues {
+    fn type_name(&self) -> &'static str {
+        match self {
+            DataValues::Float64(_) => "Float64",
+            DataValues::Int64(_) => "Int64",
+            DataValues::UInt8(_) => "UInt8",
+        }
+    }
+
+    fn to_ascii(&self) -> String {
+        fn join<T: ToString>(values: &[T]) -> String {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        }
+        match self {
+            DataValues::Float64(values) => join(values),
+            DataValues::Int64(values) => join(values),
+            DataValues::UInt8(values) => join(values),
+        }
+    }
+
+    /// Base64 of a UInt64 byte count followed by the little-endian values
+    fn to_base64(&self) -> String {
+        let mut bytes = match self {
+            DataValues::Float64(values) => values
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<u8>>(),
+            DataValues::Int64(values) => values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            DataValues::UInt8(values) => values.clone(),
+        };
+        let mut payload = (bytes.len() as u64).to_le_bytes().to_vec();
+        payload.append(&mut bytes);
+        STANDARD.encode(payload)
+    }
+}
+
+fn write_data_array<W: Write>(
+    writer: &mut W,
+    name: &str,
+    components: usize,
+    values: &DataValues,
+    encoding: VtuEncoding,
+) -> std::io::Result<()> {
+    let (format, data) = match encoding {
+        VtuEncoding::Ascii => ("ascii", values.to_ascii()),
+        VtuEncoding::Base64 => ("binary", values.to_base64()),
+    };
+    writeln!(
+        writer,
+        "        <DataArray type=\"{}\" Name=\"{}\" NumberOfComponents=\"{}\" format=\"{}\">",
+        values.type_name(),
+        escape(name),
+        components,
+        format
+    )?;
+    writeln!(writer, "          {}", data)?;
+    writeln!(writer, "        </DataArray>")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_vtu<W: Write>(
+    writer: &mut W,
+    hgrid: &Hgrid,
+    encoding: VtuEncoding,
+) -> std::io::Result<()> {
+    let nodes = hgrid.nodes();
+    let elements = hgrid.elements();
+
+    // Resolve connectivity first, since loading does not check node references
+    let mut connectivity = Vec::new();
+    let mut offsets = Vec::with_capacity(elements.len());
+    let mut types = Vec::with_capacity(elements.len());
+    for (elem_id, element) in elements.iter() {
+        for &node_id in element {
+            let index = nodes.index_of(node_id).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Element {} references unknown node {}", elem_id, node_id),
+                )
+            })?;
+            connectivity.push(index as i64);
+        }
+        offsets.push(connectivity.len() as i64);
+        types.push(if element.len() == 3 {
+            VTK_TRIANGLE
+        } else {
+            VTK_QUAD
+        });
+    }
+
+    writeln!(writer, "<?xml version=\"1.0\"?>")?;
+    writeln!(
+        writer,
+        "<VTKFile type=\"UnstructuredGrid\" version=\"1.0\" byte_order=\"LittleEndian\" header_type=\"UInt64\">"
+    )?;
+    writeln!(writer, "  <UnstructuredGrid>")?;
+    writeln!(
+        writer,
+        "    <Piece NumberOfPoints=\"{}\" NumberOfCells=\"{}\">",
+        nodes.len(),
+        elements.len()
+    )?;
+
+    if nodes.attribute(DEPTH_ATTRIBUTE).is_some() {
+        writeln!(writer, "      <PointData Scalars=\"{}\">", DEPTH_ATTRIBUTE)?;
+    } else {
+        writeln!(writer, "      <PointData>")?;
+    }
+    for name in nodes.attribute_names() {
+        // Present since the name was just listed
+        let attribute = nodes.attribute(name).unwrap();
+        let mut values: Vec<f64> = match attribute {
+            NodeAttribute::Scalar(values) => values.to_vec(),
+            NodeAttribute::Multi(values) => values.iter().copied().collect(),
+        };
+        // Depths are written positive down, every column alike
+        if name == DEPTH_ATTRIBUTE && hgrid.depth_convention() == DepthConvention::PositiveUp {
+            values.iter_mut().for_each(|value| *value = -*value);
+        }
+        write_data_array(
+            writer,
+            name,
+            attribute.ncols(),
+            &DataValues::Float64(values),
+            encoding,
+        )?;
+    }
+    write_data_array(
+        writer,
+        "node_id",
+        1,
+        &DataValues::Int64(nodes.ids().iter().map(|&id| id as i64).collect()),
+        encoding,
+    )?;
+    write_data_array(
+        writer,
+        "elements_connected",
+        1,
+        &DataValues::Int64(
+            hgrid
+                .topology()
+                .node_elements_csr()
+                .iter()
+                .map(|elements| elements.len() as i64)
+                .collect(),
+        ),
+        encoding,
+    )?;
+    writeln!(writer, "      </PointData>")?;
+
+    writeln!(writer, "      <CellData>")?;
+    write_data_array(
+        writer,
+        "element_id",
+        1,
+        &DataValues::Int64(elements.ids().iter().map(|&id| id as i64).collect()),
+        encoding,
+    )?;
+    for name in elements.attribute_names() {
+        // Present since the name was just listed
+        let values = elements.attribute(name).unwrap();
+        write_data_array(
+            writer,
+            name,
+            1,
+            &DataValues::Float64(values.to_vec()),
+            encoding,
+        )?;
+    }
+    writeln!(writer, "      </CellData>")?;
+
+    writeln!(writer, "      <Points>")?;
+    let points = hgrid
+        .x()
+        .iter()
+        .zip(hgrid.y().iter())
+        .flat_map(|(&x, &y)| [x, y, 0.0])
+        .collect();
+    write_data_array(writer, "Points", 3, &DataValues::Float64(points), encoding)?;
+    writeln!(writer, "      </Points>")?;
+
+    writeln!(writer, "      <Cells>")?;
+    write_data_array(
+        writer,
+        "connectivity",
+        1,
+        &DataValues::Int64(connectivity),
+        encoding,
+    )?;
+    write_data_array(writer, "offsets", 1, &DataValues::Int64(offsets), encoding)?;
+    write_data_array(writer, "types", 1, &DataValues::UInt8(types), encoding)?;
+    writeln!(writer, "      </Cells>")?;
+
+    writeln!(writer, "    </Piece>")?;
+    writeln!(writer, "  </UnstructuredGrid>")?;
+    writeln!(writer, "</VTKFile>")
+}
+
+pub fn write_to_path(path: &Path, hgrid: &Hgrid, encoding: VtuEncoding) -> std::io::Result<()> {
+    let mut tmpfile = NamedTempFile::new()?;
+    log::debug!("Will write to tmpfile: {:?}", tmpfile);
+    let mut writer = BufWriter::new(tmpfile.as_file_mut());
+    write_vtu(&mut writer, hgrid, encoding)?;
+    writer.flush()?;
+    drop(writer);
+    tmpfile.persist(path)?;
+    Ok(())
+}
+
+impl Hgrid {
+    /// Write the mesh and its node and element attributes as a `.vtu` file.
+    pub fn write_vtu(&self, path: &Path, encoding: VtuEncoding) -> std::io::Result<()> {
+        write_to_path(path, self, encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{hgrid_from_gr3, QUAD_AND_TRIANGLE};
+    use ndarray::array;
+
+    // A quad and a triangle sharing the edge 2-5, with non-contiguous ids
+    fn sample_hgrid() -> Hgrid {
+        let mut hgrid = hgrid_from_gr3(QUAD_AND_TRIANGLE);
+        hgrid
+            .add_element_attribute("skewness", array![0.1, 0.4])
+            .unwrap();
+        hgrid
+    }
+
+    fn vtu_string(hgrid: &Hgrid, encoding: VtuEncoding) -> String {
+        let mut buffer = Vec::new();
+        write_vtu(&mut buffer, hgrid, encoding).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_write_vtu_ascii() {
+        let vtu = vtu_string(&sample_hgrid(), VtuEncoding::Ascii);
+        assert!(vtu.contains("<Piece NumberOfPoints=\"5\" NumberOfCells=\"2\">"));
+        assert!(vtu.contains("<PointData Scalars=\"depth\">"));
+        let data_after = |name: &str| {
+            let start = vtu.find(&format!("Name=\"{}\"", name)).unwrap();
+            vtu[start..].lines().nth(1).unwrap().trim().to_string()
+        };
+        assert_eq!(data_after("depth"), "5 6 7 8 9");
+        assert_eq!(data_after("elements_connected"), "1 2 2 1 1");
+        assert_eq!(data_after("skewness"), "0.1 0.4");
+        assert_eq!(data_after("connectivity"), "0 1 2 3 1 4 2");
+        assert_eq!(data_after("offsets"), "4 7");
+        assert_eq!(data_after("types"), "9 5");
+    }
+
+    #[test]
+    fn test_multi_column_depth_and_missing_nodes() {
+        let gr3 = "two depth columns
+1 3
+1 0 0 5 50
+2 1 0 6 60
+3 0 1 7 70
+1 3 1 2 3";
+        let mut hgrid = hgrid_from_gr3(gr3);
+        hgrid.flip_depths();
+        let vtu = vtu_string(&hgrid, VtuEncoding::Ascii);
+        let start = vtu.find("Name=\"depth\"").unwrap();
+        assert!(vtu[start..]
+            .lines()
+            .next()
+            .unwrap()
+            .contains("NumberOfComponents=\"2\""));
+        assert_eq!(
+            vtu[start..].lines().nth(1).unwrap().trim(),
+            "5 50 6 60 7 70"
+        );
+
+        // Loading does not check node references, writing does
+        let gr3 = QUAD_AND_TRIANGLE.replace("20 3 2 7 5", "20 3 2 9 5");
+        let hgrid = hgrid_from_gr3(&gr3);
+        let error = write_vtu(&mut Vec::new(), &hgrid, VtuEncoding::Ascii).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_write_vtu_base64() {
+        let vtu = vtu_string(&sample_hgrid(), VtuEncoding::Base64);
+        let start = vtu.find("Name=\"types\"").unwrap();
+        let data = vtu[start..].lines().nth(1).unwrap().trim();
+        let bytes = STANDARD.decode(data).unwrap();
+        assert_eq!(bytes[..8], 2u64.to_le_bytes());
+        assert_eq!(bytes[8..], [VTK_QUAD, VTK_TRIANGLE]);
+    }
+}